@@ -0,0 +1,23 @@
+//! Fare arithmetic, kept free of `Env` so it can be tested in isolation
+
+use crate::BPS_DENOMINATOR;
+
+/// Price of one seat.
+///
+/// `class_bps` scales `base` (10_000 leaves it unchanged). `surge_bps` is the
+/// premium charged once a flight is full and ramps up linearly with
+/// `occupancy`, given as `(booked, capacity)`; overbooked places pay the full
+/// surge. The result is rounded down once, after all factors are applied.
+pub fn compute_fare(base: i128, class_bps: u32, surge_bps: u32, occupancy: (u32, u32)) -> i128 {
+    let bps = BPS_DENOMINATOR as i128;
+    let (booked, capacity) = occupancy;
+    if capacity == 0 {
+        return base * class_bps as i128 / bps;
+    }
+    let booked = booked.min(capacity) as i128;
+    let capacity = capacity as i128;
+
+    // base * class/bps * (1 + surge/bps * booked/capacity), over one denominator
+    base * class_bps as i128 * (bps * capacity + surge_bps as i128 * booked)
+        / (bps * bps * capacity)
+}
@@ -1,44 +1,935 @@
 #![no_std]
 
 use soroban_sdk::{
-    contract, contracterror, contractimpl, contracttype, log, Address, BytesN, Env, Symbol, Vec,
+    contract, contracterror, contractevent, contractimpl, contracttype, token, Address, BytesN,
+    Env, IntoVal, Map, Symbol, TryFromVal, Val, Vec,
 };
 
 use soroban_sdk::panic_with_error;
+use soroban_sdk::xdr::ToXdr;
 
+/// `log!` that only exists in builds with the `debug-logs` feature. Without it
+/// the arguments are borrowed and dropped, so nothing is formatted or stored.
+/// The saving is in the wasm: native tests don't meter diagnostic output, so
+/// compare release builds with and without the feature to see it.
+macro_rules! debug_log {
+    ($env:expr, $fmt:literal $(, $arg:expr)* $(,)?) => {{
+        #[cfg(feature = "debug-logs")]
+        soroban_sdk::log!($env, $fmt $(, $arg)*);
+        #[cfg(not(feature = "debug-logs"))]
+        let _ = ($env, $(&$arg),*);
+    }};
+}
+
+mod fare;
+use fare::compute_fare;
+
+/// Build identifier returned by `version`, bumped on every release
+pub const CONTRACT_VERSION: u32 = 1;
+
+/// Default share (in basis points) of a no-show fare that goes to the airline
+const DEFAULT_NO_SHOW_AIRLINE_BPS: u32 = 9_000;
+const BPS_DENOMINATOR: u32 = 10_000;
+/// Upper bound on how far past physical capacity a flight may be sold
+const MAX_OVERBOOK_BPS: u32 = 1_500;
+/// Most flights of a route considered when sorting a paged search
+const MAX_SORT_CANDIDATES: u32 = 100;
+/// Largest page a paged read returns
+const MAX_PAGE_LIMIT: u32 = 50;
+/// Most entries one `update_statuses` call applies
+pub const MAX_STATUS_BATCH: u32 = 25;
+/// Most recipients one flight's `PayoutSplit` may name
+const MAX_PAYOUT_RECIPIENTS: u32 = 10;
+/// Most ids one `get_flights_by_ids` or `get_tickets_by_ids` call resolves
+pub const MAX_BULK_IDS: u32 = 20;
+/// Hard cap on records in one flight's passenger list, infants and gifted
+/// bookings included, so full scans of it stay within the host CPU budget
+pub const MAX_PASSENGER_LIST: u32 = 500;
+/// Upper bound on the operator list so it stays cheap to store and scan
+const MAX_OPERATORS: u32 = 20;
+/// Flight ids per passenger history storage entry
+const HISTORY_BUCKET_SIZE: u32 = 50;
+/// Routes per `RouteList` storage entry
+const ROUTE_BUCKET_SIZE: u32 = 50;
+
+/// How long passengers of a rescheduled flight may cancel for free, unless the
+/// admin configures otherwise
+const DEFAULT_RESCHEDULE_GRACE_SECS: u64 = 86_400;
+
+/// Persistent entries are renewed once they have under ~30 days left...
+const DEFAULT_TTL_THRESHOLD: u32 = 17_280 * 30;
+/// ...back up to ~120 days, unless the admin sets a `TtlConfig`
+const DEFAULT_TTL_EXTEND_TO: u32 = 17_280 * 120;
+
+/// `get_flights_search_filtered` flag: skip flights with every seat sold
+pub const SEARCH_EXCLUDE_FULL: u32 = 1;
+/// `get_flights_search_filtered` flag: skip flights that have departed
+pub const SEARCH_EXCLUDE_DEPARTED: u32 = 1 << 1;
+/// `get_flights_search_filtered` flag: skip flights no longer in `booking`
+pub const SEARCH_EXCLUDE_CLOSED: u32 = 1 << 2;
+/// Child fare as a share of the adult fare until the admin sets one
+const DEFAULT_CHILD_FARE_BPS: u32 = 7_500;
+/// Flat infant fee until the admin sets one
+const DEFAULT_INFANT_FEE: i128 = 10;
+/// Fare ceiling until the admin sets one: 100,000 tokens at 7 decimals
+const DEFAULT_MAX_FARE: i128 = 1_000_000_000_000;
+/// Flight numbers are unique per UTC day of scheduled departure
+const SECS_PER_DAY: u64 = 86_400;
+/// Occupancy levels announced until the admin configures others
+const DEFAULT_OCCUPANCY_THRESHOLDS: [u32; 3] = [5_000, 8_000, 10_000];
+/// Fixed-point scale of `fare_per_km` (7 decimals, like the token); the default
+/// rate of one unit per km
+pub const FARE_SCALE: i128 = 10_000_000;
+/// Longest flight `create_flight` accepts: half the Earth's circumference
+pub const MAX_DISTANCE_KM: i128 = 20_038;
+/// Time a queued config change waits before it can be applied
+pub const CONFIG_CHANGE_DELAY_SECS: u64 = 2 * SECS_PER_DAY;
+/// Time a recovery proposal must stand before it can be executed
+pub const RECOVERY_DELAY_SECS: u64 = 7 * SECS_PER_DAY;
+/// Hard cap on the global registry so admin listings stay within host limits
+const DEFAULT_MAX_FLIGHTS: u32 = 10_000;
+/// Ledgers a seat hold lasts unless the admin configures otherwise (~5 minutes)
+const DEFAULT_HOLD_LEDGERS: u32 = 60;
+
+/// Remaining capacity of a flight bought out by a single customer
 #[contracttype]
-#[derive(Clone)]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Charter {
+    pub holder: Address,
+    pub seats: u32,
+    pub paid: i128,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub struct FlightDetails {
     pub id: BytesN<32>,
+    /// Airline-facing code such as `FS204`
+    pub flight_number: Symbol,
     pub max_passengers: u32,
     pub distance: i128,
+    /// Seat fare resolved from `distance` and the fare rate at creation time;
+    /// later rate changes do not reprice the flight
+    pub fare: i128,
     pub src: Symbol,
     pub dest: Symbol,
     pub status: Symbol,
     pub escrow_amount: i128,
     pub passenger_count: u32,
+    pub overbook_bps: u32,
+    pub promotional: bool,
+    pub depart_at: u64,
+    /// Total delay accumulated against the originally scheduled departure
+    pub delay_secs: u64,
+    /// Admin-set price for buying out the remaining seats; `None` charges the
+    /// normal fare for each of them
+    pub charter_price: Option<i128>,
+    /// Set once the flight has been chartered; details live under `DataKey::Charter`
+    pub charter_holder: Option<Address>,
+    /// Admin or operator that scheduled the flight
+    pub created_by: Address,
+    /// Scheduled arrival, once one is given through `reschedule_flight`
+    pub arrive_at: Option<u64>,
+    /// Ledger timestamp of creation or of the last status change
+    pub status_changed_at: u64,
+}
+
+/// Schedule and pricing half of a flight, stored under `DataKey::Flight`.
+/// Only rewritten when the admin reschedules or reprices the flight.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FlightStatic {
+    pub id: BytesN<32>,
+    pub flight_number: Symbol,
+    pub max_passengers: u32,
+    pub distance: i128,
+    pub fare: i128,
+    pub src: Symbol,
+    pub dest: Symbol,
+    pub escrow_amount: i128,
+    pub overbook_bps: u32,
+    pub promotional: bool,
+    pub depart_at: u64,
+    pub delay_secs: u64,
+    pub charter_price: Option<i128>,
+    pub created_by: Address,
+    pub arrive_at: Option<u64>,
+}
+
+/// Booking-driven half of a flight, stored under `DataKey::FlightState`.
+/// Money collected is tracked separately in `DataKey::Balance`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FlightState {
+    pub status: Symbol,
+    pub passenger_count: u32,
+    pub charter_holder: Option<Address>,
+    pub status_changed_at: u64,
+}
+
+/// Seats a passenger holds on one flight and how many of them have boarded,
+/// kept beside the passenger list so gate checks are a single read
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Ticket {
+    pub seats: u32,
+    pub boarded: u32,
+    /// Mirrors `PassengerRecord.dispute`: a disputed ticket doesn't board
+    pub disputed: bool,
+}
+
+/// Who a booking is for. Infants travel on an adult's lap: they take no seat and
+/// do not count against `max_passengers`.
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PassengerType {
+    Adult,
+    Child,
+    Infant,
 }
 
+/// Pricing for non-adult passengers: children pay `child_bps` of the adult fare,
+/// infants a flat `infant_fee`
 #[contracttype]
-#[derive(Clone)]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PassengerTypeFares {
+    pub child_bps: u32,
+    pub infant_fee: i128,
+}
+
+/// What a booking charged and assigned, mirroring the stored record
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Receipt {
+    pub flight_id: BytesN<32>,
+    /// Fare in the base token, excluding the booking fee
+    pub paid: i128,
+    /// Zero for a lap infant
+    pub seat: u32,
+    pub booked_at: u64,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub struct PassengerRecord {
-    pub passenger: Address,
+    /// Hashed when booked in privacy mode, see `set_privacy_mode`
+    pub passenger: PassengerId,
+    /// Who funded the fare; refunds go back here
+    pub payer: PassengerId,
     pub paid: i128,
+    /// Free-form passenger note, e.g. a meal preference. Must be non-empty; the
+    /// Symbol type already limits it to 32 chars of `[a-zA-Z0-9_]`.
     pub details: Symbol,
+    pub seat: u32,
+    pub voucher: Option<BytesN<32>>,
+    pub checked_in: bool,
+    pub settled: bool,
+    /// Delay compensation credited so far
+    pub compensation: i128,
+    /// Ledger timestamp of the purchase
+    pub booked_at: u64,
+    pub passenger_type: PassengerType,
+    /// Accepted token the fare was paid in; `None` for the base token
+    pub pay_token: Option<Address>,
+    /// Amount actually transferred in `pay_token`; equals `paid` for the base
+    /// token. Refunds return the same share of this amount in the same asset.
+    pub token_paid: i128,
+    /// Service fee charged on top of the fare, in base-token units. Goes
+    /// straight to the fee pot of the token paid in and is never refunded.
+    pub fee_paid: i128,
+    /// Purchase order on the flight, from 1. Never reused, so cancellations
+    /// leave gaps; the passenger list is kept in this order.
+    pub booking_seq: u32,
+    /// Reason support flagged the booking. While set the passenger can't
+    /// cancel, rebook or check in; see `resolve_dispute`.
+    pub dispute: Option<Symbol>,
+    /// Bought at the flight's promo fare; cancelling hands the seat back to
+    /// the promo quota
+    pub promo: bool,
+}
+
+/// Launch offer on a flight: `fare` replaces the normal fare for the next
+/// `seats_left` seated bookings made before `expires_at`
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Promo {
+    pub fare: i128,
+    pub seats_left: u32,
+    pub expires_at: u64,
+}
+
+/// Booking sequence numbers of one flight: the next one to hand out and who
+/// holds each live one
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BookingIndex {
+    pub next_seq: u32,
+    pub holders: Map<u32, PassengerId>,
+}
+
+impl BookingIndex {
+    fn assign(&mut self, passenger: &PassengerId) -> u32 {
+        let seq = self.next_seq;
+        self.next_seq += 1;
+        self.holders.set(seq, passenger.clone());
+        seq
+    }
+}
+
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum VoucherKind {
+    /// `value` is subtracted from the fare
+    Fixed,
+    /// `value` is a percentage (1..=100) taken off the fare
+    Percent,
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Voucher {
+    pub kind: VoucherKind,
+    pub value: i128,
+    pub uses_left: u32,
+    pub expiry: u64,
+}
+
+/// Compensation owed to every booked passenger once a flight's delay reaches
+/// `min_delay_secs`: a flat amount plus `bps` of the fare they paid
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DelayTier {
+    pub min_delay_secs: u64,
+    pub flat: i128,
+    pub bps: u32,
+}
+
+/// Operational metadata that can change up until departure
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OpsInfo {
+    pub gate: Symbol,
+    pub terminal: Symbol,
+    pub aircraft: Symbol,
+    pub airline: Symbol,
+}
+
+/// Downstream contract told about bookings and cancellations. It must expose
+/// `on_booking(flight_id, passenger, amount)` and
+/// `on_cancellation(flight_id, passenger, amount)`. In lenient mode a failing
+/// hook is ignored; in strict mode it reverts the booking or cancellation.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct HookConfig {
+    pub hook: Address,
+    pub strict: bool,
+}
+
+/// Why `can_book` expects a booking to fail, or `Ok`
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BookReason {
+    Ok,
+    FlightNotFound,
+    /// Not open for sale: departed, cancelled or chartered
+    BookingClosed,
+    FlightFull,
+    /// Still in the cooldown after a cancellation
+    PassengerBlocked,
+    InsufficientFunds,
+}
+
+/// Outcome of `can_book`; `fare` is what the booking would charge before the
+/// booking fee, 0 when the flight cannot be priced
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BookCheck {
+    pub ok: bool,
+    pub reason: BookReason,
+    pub fare: i128,
+}
+
+/// Kind of privileged action recorded in the audit log
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AuditAction {
+    AccessChanged,
+    ConfigChanged,
+    FlightCreated,
+    FlightStatusChanged,
+    FlightEdited,
+    FeeChanged,
+    CapacityChanged,
+    PassengersEdited,
+    FundsMoved,
+}
+
+/// One audit log entry. `value` carries the action's main argument (a fare, a
+/// capacity, a timestamp...) or 0 when it has none.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AuditEntry {
+    pub action: AuditAction,
+    pub actor: Address,
+    pub flight_id: Option<BytesN<32>>,
+    pub timestamp: u64,
+    pub value: i128,
+}
+
+/// How `resolve_dispute` settles a flagged booking
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DisputeOutcome {
+    /// Lift the flag and keep the booking
+    Clear,
+    /// Cancel the booking and refund the fare in full to whoever paid it
+    Refund,
+    /// Cancel the booking and move the fare to the fee pot
+    Forfeit,
+}
+
+#[contracttype]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SortKey {
+    PriceAsc,
+    PriceDesc,
+    DepartureAsc,
+}
+
+/// One page of a sorted search. Only the first `candidate_cap` flights of the route
+/// are sorted; `truncated` is set when the route holds more than that.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SearchPage {
+    pub flights: Vec<FlightDetails>,
+    pub total: u32,
+    pub candidate_cap: u32,
+    pub truncated: bool,
+}
+
+/// A passenger as stored on a flight and disclosed by `get_passengers`: the
+/// plain address, or in privacy mode `sha256(passenger || flight_id)`
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PassengerId {
+    Plain(Address),
+    Hashed(BytesN<32>),
+}
+
+impl PassengerId {
+    /// The address behind a plain id
+    pub fn address(&self) -> Option<Address> {
+        match self {
+            PassengerId::Plain(address) => Some(address.clone()),
+            PassengerId::Hashed(_) => None,
+        }
+    }
+}
+
+/// A caller looked up on one flight: their address and its privacy-mode hash,
+/// so records stored either way match
+struct Holder {
+    address: Option<Address>,
+    hash: BytesN<32>,
+}
+
+impl Holder {
+    fn is(&self, id: &PassengerId) -> bool {
+        match id {
+            PassengerId::Plain(address) => self.address.as_ref() == Some(address),
+            PassengerId::Hashed(hash) => *hash == self.hash,
+        }
+    }
+}
+
+/// How late a passenger may cancel. Inside the last `cutoff_secs` before departure a
+/// cancellation is refused, or accepted with the whole fare kept as fee when `forfeit`.
+#[contracttype]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CancelPolicy {
+    pub cutoff_secs: u64,
+    pub forfeit: bool,
+}
+
+/// How the passenger's share of a partial refund is rounded to whole units; the
+/// fee takes the remainder, so the two always add up to what was paid
+#[contracttype]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, PartialOrd, Ord)]
+pub enum Rounding {
+    #[default]
+    Floor,
+    /// Half a unit or more rounds up
+    Nearest,
+    Ceil,
+}
+
+/// A sensitive parameter change. Changes that only protect passengers more may
+/// be made at once through the matching setter; the rest are queued through
+/// `queue_config_change` and applied after `CONFIG_CHANGE_DELAY_SECS`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ConfigChange {
+    /// Share (in basis points) of a no-show fare paid out to the airline
+    NoShowShare(u32),
+    CancelPolicy(CancelPolicy),
+    MaxFare(i128),
+    MaxFlights(u32),
+    PassengerTypeFares(PassengerTypeFares),
+    /// Accept a token for fares at `rate_num / rate_den` units per base-token
+    /// unit. Fees, payouts and compensation stay accounted in the base token.
+    AcceptedToken(Address, i128, i128),
+    RemoveAcceptedToken(Address),
+    RefundRounding(Rounding),
+    /// Share (in basis points) of a flight's net revenue kept in the fee pot
+    /// when `settle_flight` pays the airline
+    PlatformCommission(u32),
+}
+
+/// The parameter a `ConfigChange` touches; at most one change per parameter
+/// may be pending
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ConfigParam {
+    NoShowShare,
+    CancelPolicy,
+    MaxFare,
+    MaxFlights,
+    PassengerTypeFares,
+    AcceptedToken(Address),
+    RefundRounding,
+    PlatformCommission,
+}
+
+impl ConfigChange {
+    pub fn param(&self) -> ConfigParam {
+        match self {
+            ConfigChange::NoShowShare(_) => ConfigParam::NoShowShare,
+            ConfigChange::CancelPolicy(_) => ConfigParam::CancelPolicy,
+            ConfigChange::MaxFare(_) => ConfigParam::MaxFare,
+            ConfigChange::MaxFlights(_) => ConfigParam::MaxFlights,
+            ConfigChange::PassengerTypeFares(_) => ConfigParam::PassengerTypeFares,
+            ConfigChange::AcceptedToken(token, _, _) | ConfigChange::RemoveAcceptedToken(token) => {
+                ConfigParam::AcceptedToken(token.clone())
+            }
+            ConfigChange::RefundRounding(_) => ConfigParam::RefundRounding,
+            ConfigChange::PlatformCommission(_) => ConfigParam::PlatformCommission,
+        }
+    }
+}
+
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PendingChange {
+    pub change: ConfigChange,
+    pub executable_at: u64,
+}
+
+/// Storage longevity for persistent entries, in ledgers: any entry read or
+/// written with less than `threshold` ledgers left is extended to `extend_to`
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TtlConfig {
+    pub threshold: u32,
+    pub extend_to: u32,
+}
+
+/// Per-flight escrow accounting, maintained incrementally on every fund movement
+#[contracttype]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct FlightBalance {
+    pub collected: i128,
+    pub refunded: i128,
+    pub fees: i128,
+    pub net_held: i128,
+    /// Paid to passengers out of the fee pot, so it never touches `net_held`
+    pub compensation: i128,
+    /// Paid to the airline, early through `release_escrow` or at
+    /// `settle_flight`
+    pub paid_out: i128,
+    /// Moved onto other flights along with bookings by `split_flight`;
+    /// negative on the flight that received them
+    pub transferred: i128,
+}
+
+/// Who receives a flight's revenue at settlement, as basis-point shares
+/// summing to 10_000. Rounding dust goes to the first recipient.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PayoutSplit {
+    pub shares: Vec<(Address, u32)>,
+}
+
+/// Settlement snapshot of a flight, taken once when it departs and never
+/// changed afterwards. No-show sweeps that run later are recorded as
+/// `NoShowSettlement`s instead.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RevenueReport {
+    /// Collected, counting bookings moved in or out by `split_flight` on the
+    /// flight they ended up on
+    pub gross: i128,
+    pub refunded: i128,
+    /// Cancellation fees kept from refunded fares
+    pub fees_retained: i128,
+    pub compensation: i128,
+    /// Escrow left for the airline at departure
+    pub net_to_airline: i128,
+    pub generated_at: u64,
+}
+
+/// Outcome of one `sweep_no_shows` call on a departed flight
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NoShowSettlement {
+    pub airline: i128,
+    pub fees: i128,
+    pub settled_at: u64,
+}
+
+/// A settled flight moved out of live storage by `archive_flight`, kept as a
+/// single entry with everything needed to restore it
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ArchivedFlight {
+    pub flight: FlightDetails,
+    pub passengers: Vec<PassengerRecord>,
+    pub balance: FlightBalance,
+    pub airline_payout: i128,
+    /// `report`, `ops` and `charter` hold at most one entry, none when the
+    /// flight had no such record
+    pub report: Vec<RevenueReport>,
+    pub supplements: Vec<NoShowSettlement>,
+    pub ops: Vec<OpsInfo>,
+    pub charter: Vec<Charter>,
+    pub archived_at: u64,
 }
 
 #[contracttype]
 pub enum DataKey {
-    Admin,
+    Admins,
+    Token,
     Flight(BytesN<32>),
+    FlightState(BytesN<32>),
     RouteRegistry(Symbol, Symbol),
+    /// Legacy list of every flight id, replaced by `FlightIndex`; only present
+    /// on deployments that have not finished `migrate_flight_index`
     GlobalRegistry,
+    /// Flight id created N-th, for N below `TotalFlights`
+    FlightIndex(u32),
+    TotalFlights,
     PassengerList(BytesN<32>),
     PassengerRegistry(Address),
+    Voucher(BytesN<32>),
+    SeatMap(BytesN<32>),
+    NoShowShareBps,
+    FeePot,
+    AirlinePayout(BytesN<32>),
+    SweepCursor(BytesN<32>),
+    Balance(BytesN<32>),
+    DelayTiers,
+    PendingRefund(Address),
+    Ops(BytesN<32>),
+    RouteFare(Symbol, Symbol),
+    OperatorList,
+    PassengerHistory(Address, u32),
+    HistoryCount(Address),
+    Agent(Address, Address),
+    CancelPolicy,
+    CancelCutoff(BytesN<32>),
+    /// Temporary entry holding the expiry ledger of a passenger's seat hold
+    Hold(BytesN<32>, Address),
+    HoldList(BytesN<32>),
+    HoldLedgers,
+    /// Map of scheduled departure day to flight id for one flight number
+    FlightNumber(Symbol),
+    PrivacyMode,
+    Charter(BytesN<32>),
+    MaxFare,
+    PassengerTypeFares,
+    RefundCursor(BytesN<32>),
+    MaxFlights,
+    Hook,
+    /// Running sum of `passenger_count` over every flight
+    SeatsSold,
+    /// Ordered flight ids of one of a passenger's multi-leg bookings
+    Itinerary(Address, u64),
+    ItineraryCount(Address),
+    /// Every route ever flown, in order of first flight, bucketed
+    RouteList(u32),
+    RouteCount,
+    /// When the passenger last cancelled, while a cooldown is configured
+    CancelCooldown(Address),
+    CooldownSecs,
+    /// `(rate_num, rate_den)` converting base-token fares into an accepted token
+    AcceptedToken(Address),
+    TtlConfig,
+    /// `(rescheduled_at, until)` of a flight's free-cancellation window
+    RescheduleWindow(BytesN<32>),
+    RescheduleGraceSecs,
+    /// N-th admin action, for N below `AuditCount`. Never removed.
+    AuditLog(u32),
+}
+
+/// Keys added once `DataKey` hit the 50-variant limit of a contract type
+#[contracttype]
+pub enum DataKeyExt {
+    AuditCount,
+    Recovery,
+    PendingChange(ConfigParam),
+    FarePerKm,
+    OccupancyThresholds,
+    /// Highest occupancy threshold already announced for a flight
+    OccupancyMark(BytesN<32>),
+    BookingFee,
+    Ticket(BytesN<32>, PassengerId),
+    Report(BytesN<32>),
+    ReportSupplements(BytesN<32>),
+    /// Fares a passenger has booked over all time, in base-token units
+    LifetimeSpend(Address),
+    /// Fares refunded on a passenger's bookings over all time
+    LifetimeRefund(Address),
+    /// Present while a flight is frozen for an investigation
+    Frozen(BytesN<32>),
+    Archive(BytesN<32>),
+    /// Base-token amount owed to passengers across all flights
+    Liabilities,
+    RefundRounding,
+    BookingIndex(BytesN<32>),
+    /// Passengers with an unclaimed refund credit, oldest first
+    PendingRefundList,
+    /// Cancellation fees kept on a flight
+    FlightFees(BytesN<32>),
+    PlatformCommission,
+    PayoutSplit(BytesN<32>),
+    Promo(BytesN<32>),
+    /// Fares a flight holds in accepted tokens, in each token's own units
+    TokenEscrow(BytesN<32>),
+    /// Accepted-token fares held across all flights
+    TokenLiabilities(Address),
+    /// Booking and cancellation fees kept in an accepted token
+    TokenFeePot(Address),
+    /// Refund credit owed to a hashed id, see `claim_private_refund`
+    PrivateRefund(BytesN<32>),
+}
+
+/// Published when a passenger rewrites the details on their ticket
+#[contractevent(topics = ["ticket", "details"], data_format = "single-value")]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TicketDetailsUpdated {
+    #[topic]
+    pub flight_id: BytesN<32>,
+    #[topic]
+    pub passenger: Address,
+    pub details: Symbol,
+}
+
+/// Published when a flight is scheduled
+#[contractevent(topics = ["flight", "created"], data_format = "single-value")]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FlightCreated {
+    #[topic]
+    pub flight_id: BytesN<32>,
+    #[topic]
+    pub flight_number: Symbol,
+    pub depart_at: u64,
+}
+
+/// Published whenever a flight's gate, terminal, aircraft or airline changes
+#[contractevent(topics = ["flight", "ops"], data_format = "single-value")]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OpsInfoUpdated {
+    #[topic]
+    pub flight_id: BytesN<32>,
+    pub info: OpsInfo,
+}
+
+/// Published when a flight's departure or arrival time is changed
+#[contractevent(topics = ["flight", "rescheduled"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FlightRescheduled {
+    #[topic]
+    pub flight_id: BytesN<32>,
+    pub old_departure: u64,
+    pub new_departure: u64,
+    pub old_arrival: Option<u64>,
+    pub new_arrival: u64,
+}
+
+/// Published for each passenger removed from a flight by `reduce_capacity`
+#[contractevent(topics = ["passenger", "bumped"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PassengerBumped {
+    #[topic]
+    pub flight_id: BytesN<32>,
+    #[topic]
+    pub passenger: Address,
+    pub refund: i128,
+    pub compensation: i128,
+}
+
+/// Published for every booking, so wallets can follow a passenger by topic
+#[contractevent(topics = ["ticket", "booked"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TicketBooked {
+    #[topic]
+    pub passenger: Address,
+    pub flight_id: BytesN<32>,
+    pub paid: i128,
+    pub seat: u32,
+}
+
+/// Published when a passenger or their payer cancels bookings on a flight
+#[contractevent(topics = ["ticket", "cancelled"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TicketCancelled {
+    #[topic]
+    pub passenger: Address,
+    pub flight_id: BytesN<32>,
+    pub refund: i128,
+}
+
+/// Published for each live seat hold voided by a flight's takeoff or cancellation
+#[contractevent(topics = ["hold", "voided"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct HoldVoided {
+    #[topic]
+    pub flight_id: BytesN<32>,
+    #[topic]
+    pub passenger: Address,
+}
+
+/// Published for each booking `refund_batch` pays back on a cancelled flight
+#[contractevent(topics = ["booking", "refunded"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BookingRefunded {
+    #[topic]
+    pub flight_id: BytesN<32>,
+    #[topic]
+    pub passenger: Address,
+    pub payer: Address,
+    pub amount: i128,
+}
+
+/// Published when support flags a passenger's bookings on a flight
+#[contractevent(topics = ["ticket", "flagged"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TicketFlagged {
+    #[topic]
+    pub flight_id: BytesN<32>,
+    #[topic]
+    pub passenger: Address,
+    pub reason: Symbol,
+}
+
+/// Published when a dispute is settled. `amount` is the fare refunded or
+/// forfeited, 0 when the flag is simply cleared.
+#[contractevent(topics = ["ticket", "resolved"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DisputeResolved {
+    #[topic]
+    pub flight_id: BytesN<32>,
+    #[topic]
+    pub passenger: Address,
+    pub outcome: DisputeOutcome,
+    pub amount: i128,
+}
+
+/// Published when `settle_flight` pays out a departed flight
+#[contractevent(topics = ["flight", "settled"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct FlightSettled {
+    #[topic]
+    pub flight_id: BytesN<32>,
+    pub commission: i128,
+    pub payouts: Vec<(Address, i128)>,
+}
+
+/// Published by `reconcile_flight` when the books and the bookings disagree
+#[contractevent(topics = ["escrow", "drift"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EscrowDrift {
+    #[topic]
+    pub flight_id: BytesN<32>,
+    pub held: i128,
+    pub computed: i128,
+}
+
+/// A pending last-resort transfer of `amount` of `token` out of the contract
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RecoveryProposal {
+    pub token: Address,
+    pub amount: i128,
+    pub to: Address,
+    pub proposed_at: u64,
+}
+
+#[contractevent(topics = ["recovery", "proposed"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RecoveryProposed {
+    #[topic]
+    pub token: Address,
+    pub amount: i128,
+    pub to: Address,
+    pub executable_at: u64,
+}
+
+#[contractevent(topics = ["recovery", "executed"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RecoveryExecuted {
+    #[topic]
+    pub token: Address,
+    pub amount: i128,
+    pub to: Address,
+}
+
+#[contractevent(topics = ["recovery", "cancelled"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RecoveryCancelled {
+    #[topic]
+    pub token: Address,
+    pub amount: i128,
+}
+
+#[contractevent(topics = ["config", "queued"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ConfigChangeQueued {
+    pub change: ConfigChange,
+    pub executable_at: u64,
+}
+
+#[contractevent(topics = ["config", "applied"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ConfigChangeApplied {
+    pub change: ConfigChange,
+}
+
+#[contractevent(topics = ["config", "cancelled"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ConfigChangeCancelled {
+    pub change: ConfigChange,
+}
+
+/// A flight's occupancy climbed to `threshold_bps` or beyond
+#[contractevent(topics = ["flight", "occupancy"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OccupancyReached {
+    #[topic]
+    pub flight_id: BytesN<32>,
+    pub threshold_bps: u32,
+    pub occupancy_bps: u32,
+}
+
+#[contractevent(topics = ["flight", "status"])]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StatusChanged {
+    #[topic]
+    pub flight_id: BytesN<32>,
+    pub status: Symbol,
 }
 
 #[contracterror]
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum FlyStellarError {
     AlreadyInitialized = 1,
     Unauthorized = 2,
@@ -50,6 +941,38 @@ pub enum FlyStellarError {
     PassengerNotFound = 8,
     InvalidStatus = 9,
     NoPassengers = 10,
+    RouteNotFound = 11,
+    VoucherNotFound = 12,
+    VoucherExpired = 13,
+    VoucherExhausted = 14,
+    VoucherNotStackable = 15,
+    SeatTaken = 16,
+    StatusConflict = 17,
+    NoPendingRefund = 18,
+    OperatorLimitReached = 19,
+    LastAdmin = 20,
+    NotInitialized = 21,
+    CancellationClosed = 22,
+    HoldExists = 23,
+    RefundsPending = 24,
+    GlobalCapacityReached = 25,
+    InsufficientFunds = 26,
+    Cooldown = 27,
+    NoRecovery = 28,
+    RecoveryLocked = 29,
+    StaleStatus = 30,
+    TimelockRequired = 31,
+    ChangePending = 32,
+    NoPendingChange = 33,
+    ChangeLocked = 34,
+    AlreadyBoarded = 35,
+    PendingHolds = 36,
+    InvalidToken = 37,
+    FlightFrozen = 38,
+    NotSettled = 39,
+    InsufficientEscrow = 40,
+    PassengerListFull = 41,
+    TicketDisputed = 42,
 }
 
 #[contract]
@@ -57,354 +980,5820 @@ pub struct FlyStellar;
 
 #[contractimpl]
 impl FlyStellar {
-    pub fn get_admin(env: &Env) -> Address {
-        Address::from_str(
+    /// Build identifier of the deployed code, for ops tooling
+    pub fn version(_env: Env) -> u32 {
+        CONTRACT_VERSION
+    }
+
+    /// Set the first admin and the token used for fares, refunds and payouts
+    pub fn initialize(env: Env, admin: Address, token: Address) {
+        if env.storage().instance().has(&DataKey::Admins) {
+            panic_with_error!(&env, FlyStellarError::AlreadyInitialized);
+        }
+        admin.require_auth();
+        Self::require_token(&env, &token);
+
+        env.storage()
+            .instance()
+            .set(&DataKey::Admins, &Vec::from_array(&env, [admin]));
+        env.storage().instance().set(&DataKey::Token, &token);
+    }
+
+    /// Current admin set; empty until `initialize` has run
+    pub fn get_admins(env: Env) -> Vec<Address> {
+        env.storage()
+            .instance()
+            .get(&DataKey::Admins)
+            .unwrap_or(Vec::new(&env))
+    }
+
+    /// Any current admin may add another
+    pub fn add_admin(env: Env, admin: Address, who: Address) {
+        Self::require_admin(&env, &admin);
+        Self::audit(&env, AuditAction::AccessChanged, &admin, None, 0);
+
+        let mut admins = Self::get_admins(env.clone());
+        if !admins.contains(&who) {
+            admins.push_back(who);
+            env.storage().instance().set(&DataKey::Admins, &admins);
+        }
+    }
+
+    /// Any current admin may remove another, but never the last one
+    pub fn remove_admin(env: Env, admin: Address, who: Address) {
+        Self::require_admin(&env, &admin);
+        Self::audit(&env, AuditAction::AccessChanged, &admin, None, 0);
+
+        let mut admins = Self::get_admins(env.clone());
+        if let Some(i) = admins.first_index_of(&who) {
+            if admins.len() == 1 {
+                panic_with_error!(&env, FlyStellarError::LastAdmin);
+            }
+            admins.remove(i);
+            env.storage().instance().set(&DataKey::Admins, &admins);
+        }
+    }
+
+    /// Grant the operator role; adding an existing operator is a no-op
+    pub fn add_operator(env: Env, admin: Address, operator: Address) {
+        Self::require_admin(&env, &admin);
+        Self::audit(&env, AuditAction::AccessChanged, &admin, None, 0);
+
+        let mut operators = Self::operators(&env);
+        if operators.contains(&operator) {
+            return;
+        }
+        if operators.len() >= MAX_OPERATORS {
+            panic_with_error!(&env, FlyStellarError::OperatorLimitReached);
+        }
+        operators.push_back(operator);
+        env.storage()
+            .instance()
+            .set(&DataKey::OperatorList, &operators);
+    }
+
+    /// Revoke the operator role; removing a non-operator is a no-op
+    pub fn remove_operator(env: Env, admin: Address, operator: Address) {
+        Self::require_admin(&env, &admin);
+        Self::audit(&env, AuditAction::AccessChanged, &admin, None, 0);
+
+        let mut operators = Self::operators(&env);
+        if let Some(i) = operators.first_index_of(&operator) {
+            operators.remove(i);
+            env.storage()
+                .instance()
+                .set(&DataKey::OperatorList, &operators);
+        }
+    }
+
+    pub fn list_operators(env: Env, admin: Address) -> Vec<Address> {
+        Self::require_admin(&env, &admin);
+        Self::operators(&env)
+    }
+
+    pub fn is_operator(env: Env, who: Address) -> bool {
+        Self::operators(&env).contains(&who)
+    }
+
+    fn operators(env: &Env) -> Vec<Address> {
+        env.storage()
+            .instance()
+            .get(&DataKey::OperatorList)
+            .unwrap_or(Vec::new(env))
+    }
+
+    pub fn get_token(env: Env) -> Address {
+        env.storage()
+            .instance()
+            .get(&DataKey::Token)
+            .expect("Token not configured")
+    }
+
+    /// Write a persistent entry and renew its TTL
+    fn persist<V: IntoVal<Env, Val>>(env: &Env, key: &impl IntoVal<Env, Val>, val: &V) {
+        env.storage().persistent().set(key, val);
+        Self::bump_ttl(env, key);
+    }
+
+    /// Read a persistent entry, renewing its TTL when it exists
+    fn fetch<V: TryFromVal<Env, Val>>(env: &Env, key: &impl IntoVal<Env, Val>) -> Option<V> {
+        let val = env.storage().persistent().get(key);
+        if val.is_some() {
+            Self::bump_ttl(env, key);
+        }
+        val
+    }
+
+    /// The value under `key` as a list of zero or one entries, for contract
+    /// types that cannot hold an `Option` of another contract type
+    fn fetch_optional<V>(env: &Env, key: &impl IntoVal<Env, Val>) -> Vec<V>
+    where
+        V: IntoVal<Env, Val> + TryFromVal<Env, Val>,
+    {
+        let mut out = Vec::new(env);
+        if let Some(val) = Self::fetch(env, key) {
+            out.push_back(val);
+        }
+        out
+    }
+
+    fn bump_ttl(env: &Env, key: &impl IntoVal<Env, Val>) {
+        let config = Self::get_ttl_config(env.clone());
+        env.storage()
+            .persistent()
+            .extend_ttl(key, config.threshold, config.extend_to);
+    }
+
+    /// Tune how long persistent entries live without a redeploy
+    pub fn set_ttl_config(env: Env, admin: Address, threshold: u32, extend_to: u32) {
+        Self::require_admin(&env, &admin);
+        Self::audit(
             &env,
-            "GCB2UMHX2MZC6WRNIRVAHUKRXWZBYZ7SBZJXQH4XOYVZVU765MQGZR23",
-        )
+            AuditAction::ConfigChanged,
+            &admin,
+            None,
+            extend_to as i128,
+        );
+
+        if threshold == 0 || threshold > extend_to {
+            panic_with_error!(&env, FlyStellarError::InvalidInput);
+        }
+        env.storage().instance().set(
+            &DataKey::TtlConfig,
+            &TtlConfig {
+                threshold,
+                extend_to,
+            },
+        );
+    }
+
+    pub fn get_ttl_config(env: Env) -> TtlConfig {
+        env.storage()
+            .instance()
+            .get(&DataKey::TtlConfig)
+            .unwrap_or(TtlConfig {
+                threshold: DEFAULT_TTL_THRESHOLD,
+                extend_to: DEFAULT_TTL_EXTEND_TO,
+            })
+    }
+
+    fn audit(
+        env: &Env,
+        action: AuditAction,
+        actor: &Address,
+        flight_id: Option<BytesN<32>>,
+        value: i128,
+    ) {
+        let seq = Self::audit_count(env);
+        Self::persist(
+            env,
+            &DataKey::AuditLog(seq),
+            &AuditEntry {
+                action,
+                actor: actor.clone(),
+                flight_id,
+                timestamp: env.ledger().timestamp(),
+                value,
+            },
+        );
+        env.storage()
+            .instance()
+            .set(&DataKeyExt::AuditCount, &(seq + 1));
+    }
+
+    fn audit_count(env: &Env) -> u32 {
+        env.storage()
+            .instance()
+            .get(&DataKeyExt::AuditCount)
+            .unwrap_or(0)
+    }
+
+    /// Admin actions newest first; page 0 holds the most recent `limit` entries
+    pub fn get_audit_log(env: Env, admin: Address, page: u32, limit: u32) -> Vec<AuditEntry> {
+        Self::require_admin(&env, &admin);
+
+        let count = Self::audit_count(&env);
+        let limit = limit.min(MAX_PAGE_LIMIT);
+        let skip = page.saturating_mul(limit).min(count);
+        let end = count - skip;
+        let start = end.saturating_sub(limit);
+
+        let mut out: Vec<AuditEntry> = Vec::new(&env);
+        for i in (start..end).rev() {
+            if let Some(entry) = Self::fetch(&env, &DataKey::AuditLog(i)) {
+                out.push_back(entry);
+            }
+        }
+        out
+    }
+
+    /// Propose moving stranded funds out of the contract. Executable only after
+    /// `RECOVERY_DELAY_SECS`, so passengers see it coming; a new proposal
+    /// replaces the pending one and restarts the clock.
+    pub fn propose_recovery(env: Env, admin: Address, token: Address, amount: i128, to: Address) {
+        Self::require_admin(&env, &admin);
+        Self::audit(&env, AuditAction::FundsMoved, &admin, None, amount);
+
+        if amount <= 0 {
+            panic_with_error!(&env, FlyStellarError::InvalidInput);
+        }
+        let proposed_at = env.ledger().timestamp();
+        env.storage().instance().set(
+            &DataKeyExt::Recovery,
+            &RecoveryProposal {
+                token: token.clone(),
+                amount,
+                to: to.clone(),
+                proposed_at,
+            },
+        );
+        RecoveryProposed {
+            token,
+            amount,
+            to,
+            executable_at: proposed_at + RECOVERY_DELAY_SECS,
+        }
+        .publish(&env);
+    }
+
+    /// Carry out the pending recovery once its delay is over. A base-token
+    /// recovery fails with `InsufficientEscrow` if it would leave less than
+    /// `get_liabilities` behind.
+    pub fn execute_recovery(env: Env, admin: Address) {
+        Self::require_admin(&env, &admin);
+        Self::audit(&env, AuditAction::FundsMoved, &admin, None, 0);
+
+        let proposal = Self::require_recovery(&env);
+        if env.ledger().timestamp() < proposal.proposed_at + RECOVERY_DELAY_SECS {
+            panic_with_error!(&env, FlyStellarError::RecoveryLocked);
+        }
+        // Passengers' money is not stranded: only what exceeds it can leave
+        if proposal.token == Self::get_token(env.clone()) {
+            let held = Self::token_client(&env).balance(&env.current_contract_address());
+            if proposal.amount > held - Self::get_liabilities(env.clone()) {
+                panic_with_error!(&env, FlyStellarError::InsufficientEscrow);
+            }
+        } else {
+            let held =
+                token::Client::new(&env, &proposal.token).balance(&env.current_contract_address());
+            let owed: i128 = env
+                .storage()
+                .instance()
+                .get(&DataKeyExt::TokenLiabilities(proposal.token.clone()))
+                .unwrap_or(0);
+            if proposal.amount > held - owed {
+                panic_with_error!(&env, FlyStellarError::InsufficientEscrow);
+            }
+        }
+        env.storage().instance().remove(&DataKeyExt::Recovery);
+
+        token::Client::new(&env, &proposal.token).transfer(
+            &env.current_contract_address(),
+            &proposal.to,
+            &proposal.amount,
+        );
+        RecoveryExecuted {
+            token: proposal.token,
+            amount: proposal.amount,
+            to: proposal.to,
+        }
+        .publish(&env);
+    }
+
+    pub fn cancel_recovery(env: Env, admin: Address) {
+        Self::require_admin(&env, &admin);
+        Self::audit(&env, AuditAction::FundsMoved, &admin, None, 0);
+
+        let proposal = Self::require_recovery(&env);
+        env.storage().instance().remove(&DataKeyExt::Recovery);
+        RecoveryCancelled {
+            token: proposal.token,
+            amount: proposal.amount,
+        }
+        .publish(&env);
+    }
+
+    pub fn get_recovery(env: Env) -> Option<RecoveryProposal> {
+        env.storage().instance().get(&DataKeyExt::Recovery)
+    }
+
+    fn require_recovery(env: &Env) -> RecoveryProposal {
+        Self::get_recovery(env.clone())
+            .unwrap_or_else(|| panic_with_error!(env, FlyStellarError::NoRecovery))
+    }
+
+    /// Queue a sensitive parameter change; it can be applied once
+    /// `CONFIG_CHANGE_DELAY_SECS` have passed, giving users time to react
+    pub fn queue_config_change(env: Env, admin: Address, change: ConfigChange) {
+        Self::require_admin(&env, &admin);
+        Self::audit(&env, AuditAction::ConfigChanged, &admin, None, 0);
+
+        Self::check_change(&env, &change);
+        let key = DataKeyExt::PendingChange(change.param());
+        if env.storage().instance().has(&key) {
+            panic_with_error!(&env, FlyStellarError::ChangePending);
+        }
+        let executable_at = env.ledger().timestamp() + CONFIG_CHANGE_DELAY_SECS;
+        env.storage().instance().set(
+            &key,
+            &PendingChange {
+                change: change.clone(),
+                executable_at,
+            },
+        );
+        ConfigChangeQueued {
+            change,
+            executable_at,
+        }
+        .publish(&env);
+    }
+
+    pub fn apply_config_change(env: Env, admin: Address, param: ConfigParam) {
+        Self::require_admin(&env, &admin);
+        Self::audit(&env, AuditAction::ConfigChanged, &admin, None, 0);
+
+        let pending = Self::require_pending_change(&env, &param);
+        if env.ledger().timestamp() < pending.executable_at {
+            panic_with_error!(&env, FlyStellarError::ChangeLocked);
+        }
+        env.storage()
+            .instance()
+            .remove(&DataKeyExt::PendingChange(param));
+        Self::write_change(&env, &pending.change);
+        ConfigChangeApplied {
+            change: pending.change,
+        }
+        .publish(&env);
+    }
+
+    pub fn cancel_config_change(env: Env, admin: Address, param: ConfigParam) {
+        Self::require_admin(&env, &admin);
+        Self::audit(&env, AuditAction::ConfigChanged, &admin, None, 0);
+
+        let pending = Self::require_pending_change(&env, &param);
+        env.storage()
+            .instance()
+            .remove(&DataKeyExt::PendingChange(param));
+        ConfigChangeCancelled {
+            change: pending.change,
+        }
+        .publish(&env);
+    }
+
+    pub fn get_pending_change(env: Env, param: ConfigParam) -> Option<PendingChange> {
+        env.storage()
+            .instance()
+            .get(&DataKeyExt::PendingChange(param))
+    }
+
+    fn require_pending_change(env: &Env, param: &ConfigParam) -> PendingChange {
+        Self::get_pending_change(env.clone(), param.clone())
+            .unwrap_or_else(|| panic_with_error!(env, FlyStellarError::NoPendingChange))
+    }
+
+    /// Apply a change from one of the immediate setters, provided it only
+    /// makes the contract safer for passengers
+    fn apply_now(env: &Env, change: ConfigChange) {
+        Self::check_change(env, &change);
+        if !Self::is_safer(env, &change) {
+            panic_with_error!(env, FlyStellarError::TimelockRequired);
+        }
+        Self::write_change(env, &change);
+    }
+
+    fn check_change(env: &Env, change: &ConfigChange) {
+        let valid = match change {
+            ConfigChange::NoShowShare(bps) | ConfigChange::PlatformCommission(bps) => {
+                *bps <= BPS_DENOMINATOR
+            }
+            ConfigChange::CancelPolicy(_)
+            | ConfigChange::RemoveAcceptedToken(_)
+            | ConfigChange::RefundRounding(_) => true,
+            ConfigChange::MaxFare(max_fare) => *max_fare > 0,
+            ConfigChange::MaxFlights(max_flights) => *max_flights > 0,
+            ConfigChange::PassengerTypeFares(fares) => {
+                fares.child_bps <= BPS_DENOMINATOR && fares.infant_fee >= 0
+            }
+            ConfigChange::AcceptedToken(token, rate_num, rate_den) => {
+                Self::require_token(env, token);
+                *rate_num > 0 && *rate_den > 0 && *token != Self::get_token(env.clone())
+            }
+        };
+        if !valid {
+            panic_with_error!(env, FlyStellarError::InvalidInput);
+        }
+    }
+
+    /// Whether `change` only tightens limits, cuts prices or relaxes penalties
+    fn is_safer(env: &Env, change: &ConfigChange) -> bool {
+        match change {
+            ConfigChange::NoShowShare(_) | ConfigChange::AcceptedToken(..) => false,
+            ConfigChange::RemoveAcceptedToken(_) => true,
+            ConfigChange::CancelPolicy(policy) => {
+                let current = Self::get_cancel_policy(env.clone());
+                policy.cutoff_secs <= current.cutoff_secs && (!policy.forfeit || current.forfeit)
+            }
+            ConfigChange::MaxFare(max_fare) => *max_fare <= Self::get_max_fare(env.clone()),
+            ConfigChange::MaxFlights(max_flights) => {
+                *max_flights <= Self::get_max_flights(env.clone())
+            }
+            ConfigChange::PassengerTypeFares(fares) => {
+                let current = Self::get_passenger_type_fares(env.clone());
+                fares.child_bps <= current.child_bps && fares.infant_fee <= current.infant_fee
+            }
+            // Floor < Nearest < Ceil in what passengers get back
+            ConfigChange::RefundRounding(mode) => *mode >= Self::get_refund_rounding(env.clone()),
+            ConfigChange::PlatformCommission(bps) => {
+                *bps <= Self::get_platform_commission(env.clone())
+            }
+        }
+    }
+
+    fn write_change(env: &Env, change: &ConfigChange) {
+        let storage = env.storage().instance();
+        match change {
+            ConfigChange::NoShowShare(bps) => storage.set(&DataKey::NoShowShareBps, bps),
+            ConfigChange::CancelPolicy(policy) => storage.set(&DataKey::CancelPolicy, policy),
+            ConfigChange::MaxFare(max_fare) => storage.set(&DataKey::MaxFare, max_fare),
+            ConfigChange::MaxFlights(max_flights) => storage.set(&DataKey::MaxFlights, max_flights),
+            ConfigChange::PassengerTypeFares(fares) => {
+                storage.set(&DataKey::PassengerTypeFares, fares)
+            }
+            ConfigChange::AcceptedToken(token, rate_num, rate_den) => Self::persist(
+                env,
+                &DataKey::AcceptedToken(token.clone()),
+                &(*rate_num, *rate_den),
+            ),
+            ConfigChange::RemoveAcceptedToken(token) => env
+                .storage()
+                .persistent()
+                .remove(&DataKey::AcceptedToken(token.clone())),
+            ConfigChange::RefundRounding(mode) => storage.set(&DataKeyExt::RefundRounding, mode),
+            ConfigChange::PlatformCommission(bps) => {
+                storage.set(&DataKeyExt::PlatformCommission, bps)
+            }
+        }
+    }
+
+    fn token_client(env: &Env) -> token::Client<'_> {
+        token::Client::new(env, &Self::get_token(env.clone()))
+    }
+
+    /// Return `amount` (in base-token units) of a booking to `to`, in the asset
+    /// and at the rate the booking was paid with
+    fn refund_record(env: &Env, rec: &PassengerRecord, amount: i128, to: &PassengerId) {
+        if amount <= 0 {
+            return;
+        }
+        if let Some(passenger) = rec.passenger.address() {
+            Self::add_lifetime(env, &DataKeyExt::LifetimeRefund(passenger), amount);
+        }
+        match &rec.pay_token {
+            None => Self::pay_id(env, to, amount),
+            Some(pay_token) => {
+                // Only base-token bookings are ever stored hashed
+                let to = to
+                    .address()
+                    .unwrap_or_else(|| panic_with_error!(env, FlyStellarError::InvalidToken));
+                let converted = Self::in_pay_token(rec, amount);
+                if converted > 0 {
+                    token::Client::new(env, pay_token).transfer(
+                        &env.current_contract_address(),
+                        &to,
+                        &converted,
+                    );
+                }
+            }
+        }
+    }
+
+    /// Pay `amount` of base token to a stored id. A hashed id's address is
+    /// unknown, so it is credited for `claim_private_refund` instead.
+    fn pay_id(env: &Env, to: &PassengerId, amount: i128) {
+        match to {
+            PassengerId::Plain(address) => {
+                Self::token_client(env).transfer(&env.current_contract_address(), address, &amount)
+            }
+            PassengerId::Hashed(hash) => Self::credit_private(env, hash, amount),
+        }
+    }
+
+    /// Stop accepting `token` for new fares. Takes effect at once; adding a token or
+    /// changing its rate goes through `queue_config_change`.
+    pub fn remove_accepted_token(env: Env, admin: Address, token: Address) {
+        Self::require_admin(&env, &admin);
+        Self::audit(&env, AuditAction::FeeChanged, &admin, None, 0);
+
+        Self::apply_now(&env, ConfigChange::RemoveAcceptedToken(token));
+    }
+
+    pub fn get_accepted_token(env: Env, token: Address) -> Option<(i128, i128)> {
+        Self::fetch(&env, &DataKey::AcceptedToken(token))
+    }
+
+    /// Apply fund movements to a flight's escrow accounting. Fails with
+    /// `InsufficientEscrow` rather than pay out more than the flight still
+    /// holds, which can happen once `release_escrow` has drawn it down.
+    fn record_flows(
+        env: &Env,
+        flight_id: &BytesN<32>,
+        collected: i128,
+        refunded: i128,
+        fees: i128,
+    ) {
+        let balance_key = DataKey::Balance(flight_id.clone());
+        let mut balance: FlightBalance = Self::fetch(env, &balance_key).unwrap_or_default();
+        balance.collected += collected;
+        balance.refunded += refunded;
+        balance.fees += fees;
+        balance.net_held = balance.collected
+            - balance.refunded
+            - balance.fees
+            - balance.paid_out
+            - balance.transferred;
+        if balance.net_held < 0 {
+            panic_with_error!(env, FlyStellarError::InsufficientEscrow);
+        }
+        Self::persist(env, &balance_key, &balance);
+
+        let departed = Self::fetch::<FlightState>(env, &DataKey::FlightState(flight_id.clone()))
+            .is_some_and(|state| state.status == Symbol::new(env, "takeoff"));
+        if !departed {
+            Self::add_liabilities(env, collected - refunded - fees);
+        }
+    }
+
+    /// `amount` of a booking's fare, in base-token units, converted into the
+    /// token and at the rate it was paid with
+    fn in_pay_token(rec: &PassengerRecord, amount: i128) -> i128 {
+        if rec.paid == 0 {
+            0
+        } else {
+            amount * rec.token_paid / rec.paid
+        }
+    }
+
+    /// Escrow accounting for fares paid in an accepted token, kept in that
+    /// token's own units apart from the base-token `FlightBalance`. `held` is
+    /// the change in what the flight holds; `fees` go to the token's fee pot.
+    fn record_token_flows(
+        env: &Env,
+        flight_id: &BytesN<32>,
+        token: &Address,
+        held: i128,
+        fees: i128,
+    ) {
+        let escrow_key = DataKeyExt::TokenEscrow(flight_id.clone());
+        let mut escrow: Map<Address, i128> = Self::fetch(env, &escrow_key).unwrap_or(Map::new(env));
+        let total = escrow.get(token.clone()).unwrap_or(0) + held;
+        if total < 0 {
+            panic_with_error!(env, FlyStellarError::InsufficientEscrow);
+        }
+        if total == 0 {
+            escrow.remove(token.clone());
+        } else {
+            escrow.set(token.clone(), total);
+        }
+        Self::persist(env, &escrow_key, &escrow);
+
+        let storage = env.storage().instance();
+        let owed_key = DataKeyExt::TokenLiabilities(token.clone());
+        let owed: i128 = storage.get(&owed_key).unwrap_or(0);
+        storage.set(&owed_key, &(owed + held));
+        if fees != 0 {
+            let pot_key = DataKeyExt::TokenFeePot(token.clone());
+            let pot: i128 = storage.get(&pot_key).unwrap_or(0);
+            storage.set(&pot_key, &(pot + fees));
+        }
+    }
+
+    /// Book a booking's `refund` and `fee`, in base-token units, against the
+    /// accepted token it was paid in. Returns false for base-token bookings,
+    /// which the caller books through `record_flows` instead.
+    fn record_paid_in_token(
+        env: &Env,
+        flight_id: &BytesN<32>,
+        rec: &PassengerRecord,
+        refund: i128,
+        fee: i128,
+    ) -> bool {
+        let Some(token) = &rec.pay_token else {
+            return false;
+        };
+        let out = Self::in_pay_token(rec, refund + fee);
+        let fee = out - Self::in_pay_token(rec, refund);
+        Self::record_token_flows(env, flight_id, token, -out, fee);
+        true
+    }
+
+    /// Fares a flight holds in accepted tokens, by token
+    pub fn get_token_escrow(env: Env, flight_id: BytesN<32>) -> Map<Address, i128> {
+        Self::fetch(&env, &DataKeyExt::TokenEscrow(flight_id)).unwrap_or(Map::new(&env))
+    }
+
+    /// Fees kept in an accepted token
+    pub fn get_token_fee_pot(env: Env, token: Address) -> i128 {
+        env.storage()
+            .instance()
+            .get(&DataKeyExt::TokenFeePot(token))
+            .unwrap_or(0)
+    }
+
+    fn add_liabilities(env: &Env, amount: i128) {
+        if amount == 0 {
+            return;
+        }
+        let total = Self::get_liabilities(env.clone());
+        env.storage()
+            .instance()
+            .set(&DataKeyExt::Liabilities, &(total + amount));
+    }
+
+    /// What the contract owes passengers in the base token: fares held for
+    /// flights that have not departed, plus refund credits not yet claimed
+    pub fn get_liabilities(env: Env) -> i128 {
+        env.storage()
+            .instance()
+            .get(&DataKeyExt::Liabilities)
+            .unwrap_or(0)
+    }
+
+    /// Fares a flight still holds on its passengers' behalf. Once it departs
+    /// they are the airline's and the flight owes nothing.
+    pub fn get_flight_liabilities(env: Env, flight_id: BytesN<32>) -> i128 {
+        let flight = Self::load_flight(&env, &flight_id)
+            .unwrap_or_else(|| panic_with_error!(&env, FlyStellarError::FlightNotFound));
+        Self::flight_liabilities(&env, &flight)
+    }
+
+    fn flight_liabilities(env: &Env, flight: &FlightDetails) -> i128 {
+        if flight.status == Symbol::new(env, "takeoff") {
+            return 0;
+        }
+        Self::fetch::<FlightBalance>(env, &DataKey::Balance(flight.id.clone()))
+            .unwrap_or_default()
+            .net_held
+    }
+
+    /// Move escrow between two open flights without counting it as collected or
+    /// refunded on either; the contract's liabilities are unchanged
+    fn record_transfer(env: &Env, from: &BytesN<32>, to: &BytesN<32>, amount: i128) {
+        for (flight_id, moved) in [(from, amount), (to, -amount)] {
+            let balance_key = DataKey::Balance(flight_id.clone());
+            let mut balance: FlightBalance = Self::fetch(env, &balance_key).unwrap_or_default();
+            balance.transferred += moved;
+            balance.net_held -= moved;
+            if balance.net_held < 0 {
+                panic_with_error!(env, FlyStellarError::InsufficientEscrow);
+            }
+            Self::persist(env, &balance_key, &balance);
+        }
+    }
+
+    fn record_compensation(env: &Env, flight_id: &BytesN<32>, amount: i128) {
+        let balance_key = DataKey::Balance(flight_id.clone());
+        let mut balance: FlightBalance = Self::fetch(env, &balance_key).unwrap_or_default();
+        balance.compensation += amount;
+        Self::persist(env, &balance_key, &balance);
+    }
+
+    fn add_to_fee_pot(env: &Env, amount: i128) {
+        let pot: i128 = env.storage().instance().get(&DataKey::FeePot).unwrap_or(0);
+        env.storage()
+            .instance()
+            .set(&DataKey::FeePot, &(pot + amount));
+    }
+
+    /// Probe `token` with a harmless read so a non-token address is refused up
+    /// front rather than breaking every later transfer
+    fn require_token(env: &Env, token: &Address) {
+        if !matches!(token::Client::new(env, token).try_decimals(), Ok(Ok(_))) {
+            panic_with_error!(env, FlyStellarError::InvalidToken);
+        }
+    }
+
+    /// Fail early on a contract that has not been through `initialize`
+    fn require_initialized(env: &Env) {
+        if !env.storage().instance().has(&DataKey::Admins) {
+            panic_with_error!(env, FlyStellarError::NotInitialized);
+        }
+    }
+
+    /// Require that `admin` is in the admin set and authorized this call
+    fn require_admin(env: &Env, admin: &Address) {
+        Self::require_initialized(env);
+        if !Self::get_admins(env.clone()).contains(admin) {
+            panic_with_error!(env, FlyStellarError::Unauthorized);
+        }
+        admin.require_auth();
+    }
+
+    /// Require that `caller` is an admin or an operator and authorized this call
+    fn require_admin_or_operator(env: &Env, caller: &Address) {
+        Self::require_initialized(env);
+        if !Self::get_admins(env.clone()).contains(caller) && !Self::operators(env).contains(caller)
+        {
+            panic_with_error!(env, FlyStellarError::Unauthorized);
+        }
+        caller.require_auth();
+    }
+}
+
+/// `create_flight` sits in its own module: the argument helper the SDK
+/// generates for it takes eight arguments and cannot carry an allow itself.
+mod schedule {
+    #![allow(clippy::too_many_arguments)]
+
+    use super::*;
+
+    #[contractimpl]
+    impl FlyStellar {
+        /// Schedule a new flight. Admins and operators may create flights; the caller
+        /// is recorded as `created_by`.
+        pub fn create_flight(
+            env: Env,
+            caller: Address,
+            flight_id: BytesN<32>,
+            flight_number: Symbol,
+            max_passengers: u32,
+            distance: i128,
+            src: Symbol,
+            dest: Symbol,
+            depart_at: u64,
+        ) {
+            debug_log!(&env, "🟦 [START] create_flight called");
+
+            // Step 1: Caller authentication
+            debug_log!(&env, "🔐 Checking admin/operator auth...");
+            Self::require_admin_or_operator(&env, &caller);
+            debug_log!(&env, "✅ Caller authenticated successfully");
+
+            Self::register_flight(
+                env,
+                caller,
+                flight_id,
+                flight_number,
+                max_passengers,
+                distance,
+                src,
+                dest,
+                depart_at,
+            );
+        }
+    }
+}
+
+#[contractimpl]
+impl FlyStellar {
+    /// `sha256(airline || flight_number || departure_day)`, the id
+    /// `create_flight_derived` gives a flight. `departure_day` counts days
+    /// since the Unix epoch.
+    pub fn derive_flight_id(
+        env: Env,
+        airline: Address,
+        flight_number: Symbol,
+        departure_day: u64,
+    ) -> BytesN<32> {
+        let mut preimage = airline.to_xdr(&env);
+        preimage.append(&flight_number.to_xdr(&env));
+        preimage.append(&departure_day.to_xdr(&env));
+        env.crypto().sha256(&preimage).into()
+    }
+
+    /// `passenger` as matched against the ids stored on a flight
+    fn holder(env: &Env, flight_id: &BytesN<32>, passenger: &Address) -> Holder {
+        Holder {
+            address: Some(passenger.clone()),
+            hash: Self::passenger_hash(env.clone(), flight_id.clone(), passenger.clone()),
+        }
+    }
+
+    /// Whoever a stored id names, for matching the rest of their records
+    fn holder_of(env: &Env, flight_id: &BytesN<32>, id: &PassengerId) -> Holder {
+        match id {
+            PassengerId::Plain(address) => Self::holder(env, flight_id, address),
+            PassengerId::Hashed(hash) => Holder {
+                address: None,
+                hash: hash.clone(),
+            },
+        }
+    }
+
+    /// The id a new booking stores for `holder`: the one their existing
+    /// bookings on the flight use, otherwise the hash while privacy mode is on
+    fn stored_id(env: &Env, pass_list: &Vec<PassengerRecord>, holder: &Holder) -> PassengerId {
+        if let Some(rec) = pass_list.iter().find(|rec| holder.is(&rec.passenger)) {
+            return rec.passenger;
+        }
+        match &holder.address {
+            Some(address) if !Self::get_privacy_mode(env.clone()) => {
+                PassengerId::Plain(address.clone())
+            }
+            _ => PassengerId::Hashed(holder.hash.clone()),
+        }
+    }
+
+    /// Who an event about `id` may name: nobody in privacy mode, and never a
+    /// hashed id, whose address is unknown
+    fn public_address(env: &Env, id: &PassengerId) -> Option<Address> {
+        if Self::get_privacy_mode(env.clone()) {
+            return None;
+        }
+        id.address()
+    }
+
+    /// Key of the passenger's ticket, under whichever id they booked with
+    fn ticket_key(env: &Env, flight_id: &BytesN<32>, holder: &Holder) -> DataKeyExt {
+        if let Some(address) = &holder.address {
+            let key = DataKeyExt::Ticket(flight_id.clone(), PassengerId::Plain(address.clone()));
+            if env.storage().persistent().has(&key) {
+                return key;
+            }
+        }
+        DataKeyExt::Ticket(flight_id.clone(), PassengerId::Hashed(holder.hash.clone()))
+    }
+
+    /// Schedule a new flight under an id derived from the caller, the flight
+    /// number and the departure day, and return that id. Creating the same
+    /// flight twice fails with `FlightAlreadyExists`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_flight_derived(
+        env: Env,
+        caller: Address,
+        flight_number: Symbol,
+        max_passengers: u32,
+        distance: i128,
+        src: Symbol,
+        dest: Symbol,
+        depart_at: u64,
+    ) -> BytesN<32> {
+        Self::require_admin_or_operator(&env, &caller);
+
+        let flight_id = Self::derive_flight_id(
+            env.clone(),
+            caller.clone(),
+            flight_number.clone(),
+            depart_at / SECS_PER_DAY,
+        );
+        Self::register_flight(
+            env,
+            caller,
+            flight_id.clone(),
+            flight_number,
+            max_passengers,
+            distance,
+            src,
+            dest,
+            depart_at,
+        );
+        flight_id
+    }
+
+    /// Everything `create_flight` does after authenticating `caller`
+    #[allow(clippy::too_many_arguments)]
+    fn register_flight(
+        env: Env,
+        caller: Address,
+        flight_id: BytesN<32>,
+        flight_number: Symbol,
+        max_passengers: u32,
+        distance: i128,
+        src: Symbol,
+        dest: Symbol,
+        depart_at: u64,
+    ) {
+        Self::audit(
+            &env,
+            AuditAction::FlightCreated,
+            &caller,
+            Some(flight_id.clone()),
+            max_passengers as i128,
+        );
+
+        // Step 2: Input validation
+        debug_log!(
+            &env,
+            "📥 Inputs => max_passengers={}, distance={}, src={}, dest={}, depart_at={}",
+            max_passengers,
+            distance,
+            src,
+            dest,
+            depart_at
+        );
+
+        if max_passengers == 0
+            || distance <= 0
+            || distance > MAX_DISTANCE_KM
+            || depart_at <= env.ledger().timestamp()
+            || flight_number == Symbol::new(&env, "")
+        {
+            debug_log!(
+                &env,
+                "❌ Invalid input: max_passengers={} distance={} depart_at={}",
+                max_passengers,
+                distance,
+                depart_at
+            );
+            panic_with_error!(&env, FlyStellarError::InvalidInput);
+        }
+
+        // Step 3: Check if flight already exists, live or archived
+        let storage = env.storage().persistent();
+        if storage.has(&DataKey::Flight(flight_id.clone()))
+            || storage.has(&DataKeyExt::Archive(flight_id.clone()))
+        {
+            debug_log!(&env, "⚠️ Flight already exists with ID {:?}", flight_id);
+            panic_with_error!(&env, FlyStellarError::FlightAlreadyExists);
+        }
+        debug_log!(&env, "🆕 Flight key {:?} is new, proceeding...", flight_id);
+
+        // The flight number must be free on the departure day
+        let number_key = DataKey::FlightNumber(flight_number.clone());
+        let mut days: Map<u64, BytesN<32>> =
+            Self::fetch(&env, &number_key).unwrap_or(Map::new(&env));
+        let day = depart_at / SECS_PER_DAY;
+        if days.contains_key(day) {
+            debug_log!(
+                &env,
+                "⚠️ Flight number {} already used that day",
+                flight_number
+            );
+            panic_with_error!(&env, FlyStellarError::FlightAlreadyExists);
+        }
+        // Forget days that are already over
+        let today = env.ledger().timestamp() / SECS_PER_DAY;
+        for old in days.keys() {
+            if old < today {
+                days.remove(old);
+            }
+        }
+        days.set(day, flight_id.clone());
+        Self::persist(&env, &number_key, &days);
+
+        // Step 4: Resolve the fare and calculate escrow
+        let fare = Self::distance_fare(&env, distance);
+        if fare <= 0 || fare > Self::get_max_fare(env.clone()) {
+            debug_log!(&env, "❌ Fare {} out of range", fare);
+            panic_with_error!(&env, FlyStellarError::InvalidInput);
+        }
+        debug_log!(
+            &env,
+            "💰 Calculating escrow = max_passengers({}) * fare({})",
+            max_passengers,
+            fare
+        );
+        let escrow = (max_passengers as i128)
+            .checked_mul(fare)
+            .expect("escrow overflow");
+        debug_log!(&env, "✅ Escrow amount calculated: {}", escrow);
+
+        // Step 5: Create flight details struct
+        let details = FlightDetails {
+            id: flight_id.clone(),
+            flight_number: flight_number.clone(),
+            max_passengers,
+            distance,
+            fare,
+            src: src.clone(),
+            dest: dest.clone(),
+            status: Symbol::new(&env, "booking"),
+            escrow_amount: escrow,
+            passenger_count: 0,
+            overbook_bps: 0,
+            promotional: false,
+            depart_at,
+            delay_secs: 0,
+            charter_price: None,
+            charter_holder: None,
+            created_by: caller,
+            arrive_at: None,
+            status_changed_at: env.ledger().timestamp(),
+        };
+        debug_log!(&env, "🧱 FlightDetails struct created successfully");
+
+        // Step 6: Save to storage
+        Self::save_flight(&env, &details);
+        debug_log!(&env, "💾 Stored FlightDetails in persistent storage");
+
+        // Step 7: Add to route registry
+        let route_key = DataKey::RouteRegistry(src.clone(), dest.clone());
+        debug_log!(
+            &env,
+            "🔍 Fetching existing route registry for {} -> {}",
+            src,
+            dest
+        );
+        if !env.storage().persistent().has(&route_key) {
+            Self::push_route(&env, &src, &dest);
+        }
+        let mut registry: Vec<BytesN<32>> = Self::fetch(&env, &route_key).unwrap_or(Vec::new(&env));
+        registry.push_back(flight_id.clone());
+        Self::persist(&env, &route_key, &registry);
+        debug_log!(&env, "🗺️ Updated route registry for {} -> {}", src, dest);
+
+        // Step 8: Add to global registry. Until the legacy vector is migrated
+        // it stays authoritative and keeps receiving new flights.
+        let legacy: Option<Vec<BytesN<32>>> = Self::fetch(&env, &DataKey::GlobalRegistry);
+        let total = match &legacy {
+            Some(global) => global.len(),
+            None => Self::total_flights(&env),
+        };
+        if total >= Self::get_max_flights(env.clone()) {
+            debug_log!(&env, "⚠️ Global registry is full ({} flights)", total);
+            panic_with_error!(&env, FlyStellarError::GlobalCapacityReached);
+        }
+        match legacy {
+            Some(mut global) => {
+                global.push_back(flight_id.clone());
+                Self::persist(&env, &DataKey::GlobalRegistry, &global);
+            }
+            None => {
+                Self::persist(&env, &DataKey::FlightIndex(total), &flight_id);
+                env.storage()
+                    .instance()
+                    .set(&DataKey::TotalFlights, &(total + 1));
+            }
+        }
+        debug_log!(
+            &env,
+            "🌍 Global registry updated with new flight {:?}",
+            flight_id
+        );
+
+        // Step 9: Completion
+        FlightCreated {
+            flight_id,
+            flight_number,
+            depart_at,
+        }
+        .publish(&env);
+        debug_log!(&env, "✅ [END] Flight successfully created!");
+    }
+
+    /// Issue a promo voucher redeemable at purchase
+    pub fn create_voucher(
+        env: Env,
+        admin: Address,
+        code: BytesN<32>,
+        kind: VoucherKind,
+        value: i128,
+        max_uses: u32,
+        expiry: u64,
+    ) {
+        Self::require_admin(&env, &admin);
+        Self::audit(&env, AuditAction::FeeChanged, &admin, None, value);
+
+        if value <= 0 || max_uses == 0 || expiry <= env.ledger().timestamp() {
+            panic_with_error!(&env, FlyStellarError::InvalidInput);
+        }
+        if kind == VoucherKind::Percent && value > 100 {
+            panic_with_error!(&env, FlyStellarError::InvalidInput);
+        }
+
+        let voucher_key = DataKey::Voucher(code);
+        if env.storage().persistent().has(&voucher_key) {
+            panic_with_error!(&env, FlyStellarError::InvalidInput);
+        }
+
+        let voucher = Voucher {
+            kind,
+            value,
+            uses_left: max_uses,
+            expiry,
+        };
+        Self::persist(&env, &voucher_key, &voucher);
+    }
+
+    /// Withdraw a voucher so it can no longer be redeemed
+    pub fn revoke_voucher(env: Env, admin: Address, code: BytesN<32>) {
+        Self::require_admin(&env, &admin);
+        Self::audit(&env, AuditAction::FeeChanged, &admin, None, 0);
+
+        let voucher_key = DataKey::Voucher(code);
+        if !env.storage().persistent().has(&voucher_key) {
+            panic_with_error!(&env, FlyStellarError::VoucherNotFound);
+        }
+        env.storage().persistent().remove(&voucher_key);
+    }
+
+    pub fn get_voucher(env: Env, code: BytesN<32>) -> Option<Voucher> {
+        Self::fetch(&env, &DataKey::Voucher(code))
+    }
+
+    /// Validate a voucher, consume one use and return the discounted fare
+    fn redeem_voucher(env: &Env, code: &BytesN<32>, fare: i128) -> i128 {
+        let voucher_key = DataKey::Voucher(code.clone());
+        let mut voucher: Voucher = Self::fetch(env, &voucher_key)
+            .unwrap_or_else(|| panic_with_error!(env, FlyStellarError::VoucherNotFound));
+
+        if env.ledger().timestamp() > voucher.expiry {
+            panic_with_error!(env, FlyStellarError::VoucherExpired);
+        }
+        if voucher.uses_left == 0 {
+            panic_with_error!(env, FlyStellarError::VoucherExhausted);
+        }
+
+        let discount = match voucher.kind {
+            VoucherKind::Fixed => voucher.value,
+            VoucherKind::Percent => fare * voucher.value / 100,
+        };
+
+        voucher.uses_left -= 1;
+        Self::persist(env, &voucher_key, &voucher);
+
+        (fare - discount).max(0)
+    }
+
+    /// Stitch a flight's static and mutable entries back into `FlightDetails`
+    fn load_flight(env: &Env, flight_id: &BytesN<32>) -> Option<FlightDetails> {
+        let fixed: FlightStatic = Self::fetch(env, &DataKey::Flight(flight_id.clone()))?;
+        let state: FlightState = Self::fetch(env, &DataKey::FlightState(flight_id.clone()))?;
+        Some(FlightDetails {
+            id: fixed.id,
+            flight_number: fixed.flight_number,
+            max_passengers: fixed.max_passengers,
+            distance: fixed.distance,
+            fare: fixed.fare,
+            src: fixed.src,
+            dest: fixed.dest,
+            status: state.status,
+            escrow_amount: fixed.escrow_amount,
+            passenger_count: state.passenger_count,
+            overbook_bps: fixed.overbook_bps,
+            promotional: fixed.promotional,
+            depart_at: fixed.depart_at,
+            delay_secs: fixed.delay_secs,
+            charter_price: fixed.charter_price,
+            charter_holder: state.charter_holder,
+            created_by: fixed.created_by,
+            arrive_at: fixed.arrive_at,
+            status_changed_at: state.status_changed_at,
+        })
+    }
+
+    /// Persist only the fields bookings and status changes touch, keeping the
+    /// seats-sold total in step with the flight's passenger count
+    fn save_flight_state(env: &Env, flight: &FlightDetails) {
+        let key = DataKey::FlightState(flight.id.clone());
+        let before = Self::fetch::<FlightState>(env, &key)
+            .map(|state| state.passenger_count)
+            .unwrap_or(0);
+        if before != flight.passenger_count {
+            // Saturating, so repairing a drifted count cannot underflow the total
+            let sold = (Self::total_seats_sold(env.clone()) + flight.passenger_count)
+                .saturating_sub(before);
+            env.storage().instance().set(&DataKey::SeatsSold, &sold);
+        }
+
+        let state = FlightState {
+            status: flight.status.clone(),
+            passenger_count: flight.passenger_count,
+            charter_holder: flight.charter_holder.clone(),
+            status_changed_at: flight.status_changed_at,
+        };
+        Self::persist(env, &key, &state);
+    }
+
+    /// Persist both halves of a flight
+    fn save_flight(env: &Env, flight: &FlightDetails) {
+        let fixed = FlightStatic {
+            id: flight.id.clone(),
+            flight_number: flight.flight_number.clone(),
+            max_passengers: flight.max_passengers,
+            distance: flight.distance,
+            fare: flight.fare,
+            src: flight.src.clone(),
+            dest: flight.dest.clone(),
+            escrow_amount: flight.escrow_amount,
+            overbook_bps: flight.overbook_bps,
+            promotional: flight.promotional,
+            depart_at: flight.depart_at,
+            delay_secs: flight.delay_secs,
+            charter_price: flight.charter_price,
+            created_by: flight.created_by.clone(),
+            arrive_at: flight.arrive_at,
+        };
+        Self::persist(env, &DataKey::Flight(flight.id.clone()), &fixed);
+        Self::save_flight_state(env, flight);
+    }
+
+    /// Number of bookings a flight accepts, including its overbooking allowance
+    fn booking_limit(flight: &FlightDetails) -> u32 {
+        let limit = flight.max_passengers as u64 * (BPS_DENOMINATOR + flight.overbook_bps) as u64
+            / BPS_DENOMINATOR as u64;
+        limit as u32
+    }
+
+    /// Allow a flight to sell up to `overbook_bps` beyond its physical seats
+    pub fn set_overbook(env: Env, admin: Address, flight_id: BytesN<32>, overbook_bps: u32) {
+        Self::require_admin(&env, &admin);
+        Self::audit(
+            &env,
+            AuditAction::FlightEdited,
+            &admin,
+            Some(flight_id.clone()),
+            overbook_bps as i128,
+        );
+
+        if overbook_bps > MAX_OVERBOOK_BPS {
+            panic_with_error!(&env, FlyStellarError::InvalidInput);
+        }
+
+        let mut flight: FlightDetails = Self::load_flight(&env, &flight_id)
+            .unwrap_or_else(|| panic_with_error!(&env, FlyStellarError::FlightNotFound));
+        flight.overbook_bps = overbook_bps;
+        Self::save_flight(&env, &flight);
+    }
+
+    /// Mark a flight as a free promotional flight, or back to a paid one
+    pub fn set_promotional(env: Env, admin: Address, flight_id: BytesN<32>, promotional: bool) {
+        Self::require_admin(&env, &admin);
+        Self::audit(
+            &env,
+            AuditAction::FeeChanged,
+            &admin,
+            Some(flight_id.clone()),
+            promotional as i128,
+        );
+
+        let mut flight: FlightDetails = Self::load_flight(&env, &flight_id)
+            .unwrap_or_else(|| panic_with_error!(&env, FlyStellarError::FlightNotFound));
+        flight.promotional = promotional;
+        Self::save_flight(&env, &flight);
+    }
+
+    /// Fix the price of chartering a flight's remaining seats; `None` reverts to
+    /// the per-seat fare
+    pub fn set_charter_price(env: Env, admin: Address, flight_id: BytesN<32>, price: Option<i128>) {
+        Self::require_admin(&env, &admin);
+        Self::audit(
+            &env,
+            AuditAction::FeeChanged,
+            &admin,
+            Some(flight_id.clone()),
+            price.unwrap_or(0),
+        );
+
+        if price.is_some_and(|p| p <= 0) {
+            panic_with_error!(&env, FlyStellarError::InvalidInput);
+        }
+        let mut flight: FlightDetails = Self::load_flight(&env, &flight_id)
+            .unwrap_or_else(|| panic_with_error!(&env, FlyStellarError::FlightNotFound));
+        flight.charter_price = price;
+        Self::save_flight(&env, &flight);
+    }
+
+    /// Buy every remaining seat on a flight. Tickets sold before the charter stay
+    /// valid; no further individual bookings are accepted afterwards.
+    pub fn charter_flight(env: Env, flight_id: BytesN<32>, buyer: Address) {
+        Self::require_initialized(&env);
+        buyer.require_auth();
+
+        let mut flight: FlightDetails = Self::load_flight(&env, &flight_id)
+            .unwrap_or_else(|| panic_with_error!(&env, FlyStellarError::FlightNotFound));
+        if flight.status != Symbol::new(&env, "booking") || flight.charter_holder.is_some() {
+            panic_with_error!(&env, FlyStellarError::InvalidStatus);
+        }
+
+        let taken = flight.passenger_count + Self::active_holds(&env, &flight_id, None);
+        let seats = flight.max_passengers.saturating_sub(taken);
+        if seats == 0 {
+            panic_with_error!(&env, FlyStellarError::FlightFull);
+        }
+        let price = match flight.charter_price {
+            Some(price) => price,
+            None => Self::base_fare(&env, &flight) * seats as i128,
+        };
+        if price <= 0 && !flight.promotional {
+            panic_with_error!(&env, FlyStellarError::InvalidFare);
+        }
+
+        if price > 0 {
+            Self::token_client(&env).transfer(&buyer, env.current_contract_address(), &price);
+            Self::record_flows(&env, &flight_id, price, 0, 0);
+        }
+        flight.passenger_count += seats;
+        flight.charter_holder = Some(buyer.clone());
+        Self::save_flight_state(&env, &flight);
+        Self::announce_occupancy(&env, &flight);
+        Self::persist(
+            &env,
+            &DataKey::Charter(flight_id),
+            &Charter {
+                holder: buyer,
+                seats,
+                paid: price,
+            },
+        );
+    }
+
+    /// Give up a charter under the same refund rules as `cancel_ticket`, freeing
+    /// its seats for individual sale again
+    pub fn cancel_charter(env: Env, flight_id: BytesN<32>, holder: Address) {
+        Self::require_initialized(&env);
+        holder.require_auth();
+
+        let mut flight: FlightDetails = Self::load_flight(&env, &flight_id)
+            .unwrap_or_else(|| panic_with_error!(&env, FlyStellarError::FlightNotFound));
+        if flight.charter_holder.as_ref() != Some(&holder) {
+            panic_with_error!(&env, FlyStellarError::Unauthorized);
+        }
+        let charter_key = DataKey::Charter(flight_id.clone());
+        let charter: Charter = Self::fetch(&env, &charter_key).unwrap();
+
+        let refund = Self::refund_share(&env, charter.paid, Self::cancel_refund_bps(&env, &flight));
+        let fee = charter.paid - refund;
+        if refund > 0 {
+            Self::token_client(&env).transfer(&env.current_contract_address(), &holder, &refund);
+        }
+        Self::add_to_fee_pot(&env, fee);
+        Self::add_flight_fees(&env, &flight_id, fee);
+        Self::record_flows(&env, &flight_id, 0, refund, fee);
+
+        flight.passenger_count = flight.passenger_count.saturating_sub(charter.seats);
+        flight.charter_holder = None;
+        Self::save_flight_state(&env, &flight);
+        Self::announce_occupancy(&env, &flight);
+        env.storage().persistent().remove(&charter_key);
+    }
+
+    pub fn get_charter(env: Env, flight_id: BytesN<32>) -> Option<Charter> {
+        Self::fetch(&env, &DataKey::Charter(flight_id))
+    }
+
+    /// Set a market fare for a route, charged instead of the distance-derived fare
+    pub fn set_route_fare(env: Env, admin: Address, src: Symbol, dest: Symbol, fare: i128) {
+        Self::require_admin(&env, &admin);
+        Self::audit(&env, AuditAction::FeeChanged, &admin, None, fare);
+
+        if fare <= 0 || fare > Self::get_max_fare(env.clone()) {
+            panic_with_error!(&env, FlyStellarError::InvalidInput);
+        }
+        Self::persist(&env, &DataKey::RouteFare(src, dest), &fare);
+    }
+
+    /// Lower the highest per-seat fare `create_flight` and `set_route_fare` accept;
+    /// raising it goes through `queue_config_change`
+    pub fn set_max_fare(env: Env, admin: Address, max_fare: i128) {
+        Self::require_admin(&env, &admin);
+        Self::audit(&env, AuditAction::FeeChanged, &admin, None, max_fare);
+
+        Self::apply_now(&env, ConfigChange::MaxFare(max_fare));
+    }
+
+    pub fn get_max_fare(env: Env) -> i128 {
+        env.storage()
+            .instance()
+            .get(&DataKey::MaxFare)
+            .unwrap_or(DEFAULT_MAX_FARE)
+    }
+
+    /// Seats currently booked across every flight
+    pub fn total_seats_sold(env: Env) -> u32 {
+        env.storage()
+            .instance()
+            .get(&DataKey::SeatsSold)
+            .unwrap_or(0)
+    }
+
+    /// Tighten the cap on flights `create_flight` will register in total;
+    /// loosening it goes through `queue_config_change`
+    pub fn set_max_flights(env: Env, admin: Address, max_flights: u32) {
+        Self::require_admin(&env, &admin);
+        Self::audit(
+            &env,
+            AuditAction::CapacityChanged,
+            &admin,
+            None,
+            max_flights as i128,
+        );
+
+        Self::apply_now(&env, ConfigChange::MaxFlights(max_flights));
+    }
+
+    pub fn get_max_flights(env: Env) -> u32 {
+        env.storage()
+            .instance()
+            .get(&DataKey::MaxFlights)
+            .unwrap_or(DEFAULT_MAX_FLIGHTS)
+    }
+
+    pub fn get_route_fare(env: Env, src: Symbol, dest: Symbol) -> Option<i128> {
+        Self::fetch(&env, &DataKey::RouteFare(src, dest))
+    }
+
+    /// Fare before any voucher: free on promotional flights, otherwise the route
+    /// fare when one is set, falling back to the flight's own fare
+    fn base_fare(env: &Env, flight: &FlightDetails) -> i128 {
+        if flight.promotional {
+            return 0;
+        }
+        Self::fetch(
+            env,
+            &DataKey::RouteFare(flight.src.clone(), flight.dest.clone()),
+        )
+        .unwrap_or(flight.fare)
+    }
+
+    /// Offer a promo fare on a flight that is open for booking, replacing any
+    /// earlier promo. The promo fare must undercut the flight's normal fare.
+    pub fn set_promo(env: Env, admin: Address, flight_id: BytesN<32>, promo: Promo) {
+        Self::require_admin(&env, &admin);
+        Self::audit(
+            &env,
+            AuditAction::FeeChanged,
+            &admin,
+            Some(flight_id.clone()),
+            promo.fare,
+        );
+
+        let flight = Self::load_flight(&env, &flight_id)
+            .unwrap_or_else(|| panic_with_error!(&env, FlyStellarError::FlightNotFound));
+        if flight.status != Symbol::new(&env, "booking") {
+            panic_with_error!(&env, FlyStellarError::InvalidStatus);
+        }
+        if promo.fare <= 0 || promo.seats_left == 0 || promo.expires_at <= env.ledger().timestamp()
+        {
+            panic_with_error!(&env, FlyStellarError::InvalidInput);
+        }
+        if promo.fare >= Self::base_fare(&env, &flight)
+            || promo.fare > Self::get_max_fare(env.clone())
+        {
+            panic_with_error!(&env, FlyStellarError::InvalidFare);
+        }
+        Self::persist(&env, &DataKeyExt::Promo(flight_id), &promo);
+    }
+
+    /// Withdraw a flight's promo, live, expired or used up
+    pub fn clear_promo(env: Env, admin: Address, flight_id: BytesN<32>) {
+        Self::require_admin(&env, &admin);
+        Self::audit(
+            &env,
+            AuditAction::FeeChanged,
+            &admin,
+            Some(flight_id.clone()),
+            0,
+        );
+
+        env.storage()
+            .persistent()
+            .remove(&DataKeyExt::Promo(flight_id));
+    }
+
+    /// The flight's promo as set, whether or not it still applies
+    pub fn get_promo(env: Env, flight_id: BytesN<32>) -> Option<Promo> {
+        Self::fetch(&env, &DataKeyExt::Promo(flight_id))
+    }
+
+    /// The promo a seated booking would get right now. Free promotional
+    /// flights have nothing to discount.
+    fn live_promo(env: &Env, flight: &FlightDetails) -> Option<Promo> {
+        if flight.promotional {
+            return None;
+        }
+        let promo: Promo = Self::fetch(env, &DataKeyExt::Promo(flight.id.clone()))?;
+        (promo.seats_left > 0 && env.ledger().timestamp() < promo.expires_at).then_some(promo)
+    }
+
+    /// Move `seats` promo seats into (positive) or out of (negative) the quota
+    fn adjust_promo_quota(env: &Env, flight_id: &BytesN<32>, seats: i32) {
+        let key = DataKeyExt::Promo(flight_id.clone());
+        if let Some(mut promo) = Self::fetch::<Promo>(env, &key) {
+            promo.seats_left = promo.seats_left.saturating_add_signed(seats);
+            Self::persist(env, &key, &promo);
+        }
+    }
+
+    /// Load factor in basis points; above 10_000 when overbooked
+    pub fn get_occupancy(env: Env, flight_id: BytesN<32>) -> u32 {
+        let flight = Self::load_flight(&env, &flight_id)
+            .unwrap_or_else(|| panic_with_error!(&env, FlyStellarError::FlightNotFound));
+        Self::occupancy_bps(&flight)
+    }
+
+    fn occupancy_bps(flight: &FlightDetails) -> u32 {
+        (flight.passenger_count as u64 * BPS_DENOMINATOR as u64 / flight.max_passengers as u64)
+            as u32
+    }
+
+    /// Occupancy levels, in strictly increasing basis points up to 10_000, at
+    /// which bookings publish `OccupancyReached`
+    pub fn set_occupancy_thresholds(env: Env, admin: Address, thresholds: Vec<u32>) {
+        Self::require_admin(&env, &admin);
+        Self::audit(
+            &env,
+            AuditAction::ConfigChanged,
+            &admin,
+            None,
+            thresholds.len() as i128,
+        );
+
+        let mut prev = 0;
+        for bps in thresholds.iter() {
+            if bps <= prev || bps > BPS_DENOMINATOR {
+                panic_with_error!(&env, FlyStellarError::InvalidInput);
+            }
+            prev = bps;
+        }
+        env.storage()
+            .instance()
+            .set(&DataKeyExt::OccupancyThresholds, &thresholds);
+    }
+
+    pub fn get_occupancy_thresholds(env: Env) -> Vec<u32> {
+        env.storage()
+            .instance()
+            .get(&DataKeyExt::OccupancyThresholds)
+            .unwrap_or(Vec::from_array(&env, DEFAULT_OCCUPANCY_THRESHOLDS))
+    }
+
+    /// Publish each threshold the flight's occupancy has climbed past since it
+    /// last sat below it. The mark follows occupancy back down on cancellations,
+    /// so a threshold dipped under and then crossed again is announced again.
+    /// Call after every change to the seat count or capacity.
+    fn announce_occupancy(env: &Env, flight: &FlightDetails) {
+        let occupancy_bps = Self::occupancy_bps(flight);
+        let mark_key = DataKeyExt::OccupancyMark(flight.id.clone());
+        let mark: u32 = Self::fetch(env, &mark_key).unwrap_or(0);
+        let mut new_mark = 0;
+        for threshold_bps in Self::get_occupancy_thresholds(env.clone()).iter() {
+            if threshold_bps > occupancy_bps {
+                break;
+            }
+            if threshold_bps > mark {
+                OccupancyReached {
+                    flight_id: flight.id.clone(),
+                    threshold_bps,
+                    occupancy_bps,
+                }
+                .publish(env);
+            }
+            new_mark = threshold_bps;
+        }
+        if new_mark != mark {
+            Self::persist(env, &mark_key, &new_mark);
+        }
+    }
+
+    /// Flat service fee added to every booking from now on and kept on
+    /// cancellation; 0 disables it
+    pub fn set_booking_fee(env: Env, admin: Address, fee: i128) {
+        Self::require_admin(&env, &admin);
+        Self::audit(&env, AuditAction::FeeChanged, &admin, None, fee);
+
+        if fee < 0 {
+            panic_with_error!(&env, FlyStellarError::InvalidInput);
+        }
+        env.storage().instance().set(&DataKeyExt::BookingFee, &fee);
+    }
+
+    pub fn get_booking_fee(env: Env) -> i128 {
+        env.storage()
+            .instance()
+            .get(&DataKeyExt::BookingFee)
+            .unwrap_or(0)
+    }
+
+    /// Set the fare charged per km of distance for flights created from now on,
+    /// in base-token units scaled by `FARE_SCALE`
+    pub fn set_fare_per_km(env: Env, admin: Address, fare_per_km: i128) {
+        Self::require_admin(&env, &admin);
+        Self::audit(&env, AuditAction::FeeChanged, &admin, None, fare_per_km);
+
+        if fare_per_km <= 0 || MAX_DISTANCE_KM.checked_mul(fare_per_km).is_none() {
+            panic_with_error!(&env, FlyStellarError::InvalidInput);
+        }
+        env.storage()
+            .instance()
+            .set(&DataKeyExt::FarePerKm, &fare_per_km);
+    }
+
+    pub fn get_fare_per_km(env: Env) -> i128 {
+        env.storage()
+            .instance()
+            .get(&DataKeyExt::FarePerKm)
+            .unwrap_or(FARE_SCALE)
+    }
+
+    /// `distance * fare_per_km / FARE_SCALE`, rounded down
+    fn distance_fare(env: &Env, distance: i128) -> i128 {
+        distance
+            .checked_mul(Self::get_fare_per_km(env.clone()))
+            .unwrap_or_else(|| panic_with_error!(env, FlyStellarError::InvalidInput))
+            / FARE_SCALE
+    }
+
+    /// Price of the next seat on a flight, before any voucher. Shared by
+    /// `buy_ticket` and `get_fare` so a quote always matches the charge.
+    fn quote(
+        env: &Env,
+        flight: &FlightDetails,
+        passenger: Option<&Address>,
+        class: &Option<Symbol>,
+        passenger_type: PassengerType,
+    ) -> i128 {
+        Self::check_quote(env, flight, passenger, class, passenger_type)
+            .unwrap_or_else(|err| panic_with_error!(env, err))
+    }
+
+    fn check_quote(
+        env: &Env,
+        flight: &FlightDetails,
+        passenger: Option<&Address>,
+        class: &Option<Symbol>,
+        passenger_type: PassengerType,
+    ) -> Result<i128, FlyStellarError> {
+        // Validate flight status and capacity. Other passengers' holds count as
+        // taken; the passenger's own hold is what they are about to use.
+        // A chartered flight sells nothing more, and nothing sells once the
+        // departure time has passed, whatever the status says.
+        if flight.status != Symbol::new(env, "booking")
+            || flight.charter_holder.is_some()
+            || env.ledger().timestamp() >= flight.depart_at
+        {
+            return Err(FlyStellarError::InvalidStatus);
+        }
+        let held = Self::active_holds(env, &flight.id, passenger);
+        if passenger_type != PassengerType::Infant
+            && flight.passenger_count + held >= Self::booking_limit(flight)
+        {
+            return Err(FlyStellarError::FlightFull);
+        }
+        // Only the default cabin is sold for now
+        if class.is_some() {
+            return Err(FlyStellarError::InvalidInput);
+        }
+
+        let promo = match passenger_type {
+            PassengerType::Infant => None,
+            _ => Self::live_promo(env, flight),
+        };
+        let fare = promo.map_or_else(|| Self::base_fare(env, flight), |promo| promo.fare);
+        if fare <= 0 && !flight.promotional {
+            return Err(FlyStellarError::InvalidFare);
+        }
+        let fares = Self::get_passenger_type_fares(env.clone());
+        // No surge pricing is configured yet
+        let occupancy = (flight.passenger_count, flight.max_passengers);
+        Ok(match passenger_type {
+            PassengerType::Adult => compute_fare(fare, BPS_DENOMINATOR, 0, occupancy),
+            PassengerType::Child => compute_fare(fare, fares.child_bps, 0, occupancy),
+            PassengerType::Infant if flight.promotional => 0,
+            PassengerType::Infant => fares.infant_fee,
+        })
+    }
+
+    /// Whether an adult booking by `passenger` would go through right now, and
+    /// at what fare. Runs the same checks as `buy_ticket` without writing
+    /// anything, so wallets can explain a refusal before submitting.
+    pub fn can_book(env: Env, flight_id: BytesN<32>, passenger: Address) -> BookCheck {
+        let fare = match Self::price_booking(&env, &flight_id, &passenger) {
+            Ok(fare) => fare,
+            Err(reason) => {
+                return BookCheck {
+                    ok: false,
+                    reason,
+                    fare: 0,
+                }
+            }
+        };
+        let charge = fare + Self::get_booking_fee(env.clone());
+        let reason = match Self::check_funds(&Self::token_client(&env), &passenger, charge) {
+            Ok(()) => BookReason::Ok,
+            Err(_) => BookReason::InsufficientFunds,
+        };
+        BookCheck {
+            ok: reason == BookReason::Ok,
+            reason,
+            fare,
+        }
+    }
+
+    /// Whether the flight takes bookings right now: it exists, is not frozen,
+    /// is open for sale and has not departed, and has a seat that is neither
+    /// sold nor held. Passenger-specific checks are left to `can_book`.
+    pub fn is_bookable(env: Env, flight_id: BytesN<32>) -> bool {
+        let Some(flight) = Self::load_flight(&env, &flight_id) else {
+            return false;
+        };
+        !Self::is_flight_frozen(env.clone(), flight_id)
+            && Self::check_quote(&env, &flight, None, &None, PassengerType::Adult).is_ok()
+    }
+
+    fn price_booking(
+        env: &Env,
+        flight_id: &BytesN<32>,
+        passenger: &Address,
+    ) -> Result<i128, BookReason> {
+        let flight = Self::load_flight(env, flight_id).ok_or(BookReason::FlightNotFound)?;
+        if Self::is_flight_frozen(env.clone(), flight_id.clone()) {
+            return Err(BookReason::BookingClosed);
+        }
+        Self::check_cooldown(env, passenger).map_err(|_| BookReason::PassengerBlocked)?;
+        Self::check_quote(env, &flight, Some(passenger), &None, PassengerType::Adult).map_err(
+            |err| match err {
+                FlyStellarError::FlightFull => BookReason::FlightFull,
+                _ => BookReason::BookingClosed,
+            },
+        )
+    }
+
+    /// Friendlier than the token's own error; the transfer still decides. A
+    /// balance the token won't report counts as insufficient.
+    fn check_funds(
+        token: &token::Client,
+        payer: &Address,
+        amount: i128,
+    ) -> Result<(), FlyStellarError> {
+        if amount <= 0 {
+            return Ok(());
+        }
+        match token.try_balance(payer) {
+            Ok(Ok(balance)) if balance >= amount => Ok(()),
+            _ => Err(FlyStellarError::InsufficientFunds),
+        }
+    }
+
+    /// Cut child and infant pricing; raising either goes through `queue_config_change`
+    pub fn set_passenger_type_fares(env: Env, admin: Address, child_bps: u32, infant_fee: i128) {
+        Self::require_admin(&env, &admin);
+        Self::audit(&env, AuditAction::FeeChanged, &admin, None, infant_fee);
+
+        Self::apply_now(
+            &env,
+            ConfigChange::PassengerTypeFares(PassengerTypeFares {
+                child_bps,
+                infant_fee,
+            }),
+        );
+    }
+
+    pub fn get_passenger_type_fares(env: Env) -> PassengerTypeFares {
+        env.storage()
+            .instance()
+            .get(&DataKey::PassengerTypeFares)
+            .unwrap_or(PassengerTypeFares {
+                child_bps: DEFAULT_CHILD_FARE_BPS,
+                infant_fee: DEFAULT_INFANT_FEE,
+            })
+    }
+
+    /// Amount `buy_ticket` would charge right now, without vouchers. Pass the
+    /// passenger so their own seat hold is taken into account.
+    pub fn get_fare(
+        env: Env,
+        flight_id: BytesN<32>,
+        passenger: Option<Address>,
+        class: Option<Symbol>,
+        passenger_type: PassengerType,
+    ) -> i128 {
+        let flight: FlightDetails = Self::load_flight(&env, &flight_id)
+            .unwrap_or_else(|| panic_with_error!(&env, FlyStellarError::FlightNotFound));
+        Self::quote(&env, &flight, passenger.as_ref(), &class, passenger_type)
+    }
+
+    /// Whether the passenger's balance covers an adult fare on the flight plus
+    /// the booking fee right now. False, rather than an error, when the flight
+    /// is missing or not bookable or the fare token cannot be read.
+    pub fn can_afford(env: Env, flight_id: BytesN<32>, passenger: Address) -> bool {
+        let Some(token) = env.storage().instance().get::<_, Address>(&DataKey::Token) else {
+            return false;
+        };
+        let Some(flight) = Self::load_flight(&env, &flight_id) else {
+            return false;
+        };
+        let Ok(fare) =
+            Self::check_quote(&env, &flight, Some(&passenger), &None, PassengerType::Adult)
+        else {
+            return false;
+        };
+        let charge = fare + Self::get_booking_fee(env.clone());
+        Self::check_funds(&token::Client::new(&env, &token), &passenger, charge).is_ok()
+    }
+
+    /// Seconds a passenger must wait after cancelling before booking again;
+    /// 0 disables the cooldown
+    pub fn set_cancel_cooldown(env: Env, admin: Address, secs: u64) {
+        Self::require_admin(&env, &admin);
+        Self::audit(&env, AuditAction::ConfigChanged, &admin, None, secs as i128);
+
+        env.storage().instance().set(&DataKey::CooldownSecs, &secs);
+    }
+
+    pub fn get_cancel_cooldown(env: Env) -> u64 {
+        env.storage()
+            .instance()
+            .get(&DataKey::CooldownSecs)
+            .unwrap_or(0)
+    }
+
+    fn require_no_cooldown(env: &Env, passenger: &Address) {
+        if let Err(err) = Self::check_cooldown(env, passenger) {
+            panic_with_error!(env, err);
+        }
+    }
+
+    fn check_cooldown(env: &Env, passenger: &Address) -> Result<(), FlyStellarError> {
+        let cancelled_at: u64 = match Self::fetch(env, &DataKey::CancelCooldown(passenger.clone()))
+        {
+            Some(at) => at,
+            None => return Ok(()),
+        };
+        let until = cancelled_at.saturating_add(Self::get_cancel_cooldown(env.clone()));
+        if env.ledger().timestamp() < until {
+            return Err(FlyStellarError::Cooldown);
+        }
+        Ok(())
+    }
+
+    /// Set how many ledgers a seat hold lasts
+    pub fn set_hold_ledgers(env: Env, admin: Address, ledgers: u32) {
+        Self::require_admin(&env, &admin);
+        Self::audit(
+            &env,
+            AuditAction::ConfigChanged,
+            &admin,
+            None,
+            ledgers as i128,
+        );
+
+        if ledgers == 0 {
+            panic_with_error!(&env, FlyStellarError::InvalidInput);
+        }
+        env.storage()
+            .instance()
+            .set(&DataKey::HoldLedgers, &ledgers);
+    }
+
+    /// Reserve one place on a flight while the passenger signs the payment.
+    /// Returns the last ledger the hold is valid for; after that it lapses on
+    /// its own and the place is released.
+    pub fn hold_seat(env: Env, flight_id: BytesN<32>, passenger: Address) -> u32 {
+        Self::require_initialized(&env);
+        passenger.require_auth();
+
+        let flight: FlightDetails = Self::load_flight(&env, &flight_id)
+            .unwrap_or_else(|| panic_with_error!(&env, FlyStellarError::FlightNotFound));
+        if Self::hold_expiry(&env, &flight_id, &passenger).is_some() {
+            panic_with_error!(&env, FlyStellarError::HoldExists);
+        }
+        if flight.status != Symbol::new(&env, "booking") || flight.charter_holder.is_some() {
+            panic_with_error!(&env, FlyStellarError::InvalidStatus);
+        }
+
+        // Drop lapsed holders while checking capacity
+        let list_key = DataKey::HoldList(flight_id.clone());
+        let holders: Vec<Address> = Self::fetch(&env, &list_key).unwrap_or(Vec::new(&env));
+        let mut live: Vec<Address> = Vec::new(&env);
+        for holder in holders.iter() {
+            if Self::hold_expiry(&env, &flight_id, &holder).is_some() {
+                live.push_back(holder);
+            }
+        }
+        if flight.passenger_count + live.len() >= Self::booking_limit(&flight) {
+            panic_with_error!(&env, FlyStellarError::FlightFull);
+        }
+
+        let ledgers: u32 = env
+            .storage()
+            .instance()
+            .get(&DataKey::HoldLedgers)
+            .unwrap_or(DEFAULT_HOLD_LEDGERS);
+        let expires_at = env.ledger().sequence() + ledgers;
+        let hold_key = DataKey::Hold(flight_id, passenger.clone());
+        env.storage().temporary().set(&hold_key, &expires_at);
+        env.storage()
+            .temporary()
+            .extend_ttl(&hold_key, ledgers, ledgers);
+
+        live.push_back(passenger);
+        Self::persist(&env, &list_key, &live);
+        expires_at
+    }
+
+    /// Give up a seat hold before it lapses
+    pub fn release_hold(env: Env, flight_id: BytesN<32>, passenger: Address) {
+        Self::require_initialized(&env);
+        passenger.require_auth();
+        Self::clear_hold(&env, &flight_id, &passenger);
+    }
+
+    /// Expiry ledger of the passenger's hold, if one is still live
+    fn hold_expiry(env: &Env, flight_id: &BytesN<32>, passenger: &Address) -> Option<u32> {
+        let expires_at: u32 = env
+            .storage()
+            .temporary()
+            .get(&DataKey::Hold(flight_id.clone(), passenger.clone()))?;
+        if expires_at < env.ledger().sequence() {
+            return None;
+        }
+        Some(expires_at)
+    }
+
+    /// Live holds on a flight, not counting `skip`'s own
+    fn active_holds(env: &Env, flight_id: &BytesN<32>, skip: Option<&Address>) -> u32 {
+        let holders: Vec<Address> =
+            Self::fetch(env, &DataKey::HoldList(flight_id.clone())).unwrap_or(Vec::new(env));
+        let mut count = 0;
+        for holder in holders.iter() {
+            if Some(&holder) != skip && Self::hold_expiry(env, flight_id, &holder).is_some() {
+                count += 1;
+            }
+        }
+        count
+    }
+
+    fn clear_hold(env: &Env, flight_id: &BytesN<32>, passenger: &Address) {
+        env.storage()
+            .temporary()
+            .remove(&DataKey::Hold(flight_id.clone(), passenger.clone()));
+
+        let list_key = DataKey::HoldList(flight_id.clone());
+        let mut holders: Vec<Address> = Self::fetch(env, &list_key).unwrap_or(Vec::new(env));
+        if let Some(i) = holders.first_index_of(passenger) {
+            holders.remove(i);
+            Self::persist(env, &list_key, &holders);
+        }
+    }
+
+    /// Drop every hold on a flight, announcing the ones still live unless
+    /// privacy mode is on
+    fn void_holds(env: &Env, flight_id: &BytesN<32>) {
+        let private = Self::get_privacy_mode(env.clone());
+        let list_key = DataKey::HoldList(flight_id.clone());
+        let holders: Vec<Address> = Self::fetch(env, &list_key).unwrap_or(Vec::new(env));
+        for holder in holders.iter() {
+            if Self::hold_expiry(env, flight_id, &holder).is_some() && !private {
+                HoldVoided {
+                    flight_id: flight_id.clone(),
+                    passenger: holder.clone(),
+                }
+                .publish(env);
+            }
+            env.storage()
+                .temporary()
+                .remove(&DataKey::Hold(flight_id.clone(), holder));
+        }
+        env.storage().persistent().remove(&list_key);
+    }
+
+    /// Reject an empty passenger note
+    fn require_valid_details(env: &Env, details: &Symbol) {
+        if *details == Symbol::new(env, "") {
+            panic_with_error!(env, FlyStellarError::InvalidInput);
+        }
+    }
+
+    /// Buy a ticket for a specific seat (`1..=max_passengers`), optionally redeeming a promo voucher.
+    /// `pay_token` pays in an accepted token instead of the base token.
+    /// Returns a receipt of the fare charged and the seat assigned.
+    #[allow(clippy::too_many_arguments)]
+    pub fn buy_ticket(
+        env: Env,
+        flight_id: BytesN<32>,
+        passenger: Address,
+        details: Symbol,
+        seat: u32,
+        voucher: Option<BytesN<32>>,
+        passenger_type: PassengerType,
+        pay_token: Option<Address>,
+    ) -> Receipt {
+        Self::require_initialized(&env);
+        // Passenger must authorize this action
+        passenger.require_auth();
+        Self::book(
+            &env,
+            flight_id,
+            &passenger,
+            &passenger,
+            details,
+            seat,
+            voucher,
+            passenger_type,
+            pay_token,
+        )
+    }
+
+    /// Let `agent` book and pay for tickets on the passenger's behalf
+    pub fn authorize_agent(env: Env, passenger: Address, agent: Address) {
+        Self::require_initialized(&env);
+        passenger.require_auth();
+        Self::persist(&env, &DataKey::Agent(passenger, agent), &true);
+    }
+
+    /// Withdraw a delegation granted with `authorize_agent`
+    pub fn revoke_agent(env: Env, passenger: Address, agent: Address) {
+        Self::require_initialized(&env);
+        passenger.require_auth();
+        env.storage()
+            .persistent()
+            .remove(&DataKey::Agent(passenger, agent));
+    }
+
+    /// Book `passenger` on every leg of a connecting journey at once. Each leg
+    /// must leave from where the previous one lands and depart after it; the
+    /// first free seat is taken on each. If any leg cannot be booked nothing is.
+    /// Returns the itinerary's index for `get_itinerary` and a receipt per leg.
+    pub fn book_itinerary(
+        env: Env,
+        flight_ids: Vec<BytesN<32>>,
+        passenger: Address,
+        details: Symbol,
+    ) -> (u64, Vec<Receipt>) {
+        Self::require_initialized(&env);
+        passenger.require_auth();
+
+        if flight_ids.is_empty() {
+            panic_with_error!(&env, FlyStellarError::InvalidInput);
+        }
+        let mut prev: Option<FlightDetails> = None;
+        let mut receipts: Vec<Receipt> = Vec::new(&env);
+        for flight_id in flight_ids.iter() {
+            let flight = Self::load_flight(&env, &flight_id)
+                .unwrap_or_else(|| panic_with_error!(&env, FlyStellarError::FlightNotFound));
+            if let Some(prev) = &prev {
+                if prev.dest != flight.src || prev.depart_at >= flight.depart_at {
+                    panic_with_error!(&env, FlyStellarError::InvalidInput);
+                }
+            }
+
+            let seat_map: Map<u32, PassengerId> =
+                Self::fetch(&env, &DataKey::SeatMap(flight_id.clone())).unwrap_or(Map::new(&env));
+            let seat = Self::first_free_seat(&seat_map, flight.max_passengers);
+            receipts.push_back(Self::book(
+                &env,
+                flight_id,
+                &passenger,
+                &passenger,
+                details.clone(),
+                seat,
+                None,
+                PassengerType::Adult,
+                None,
+            ));
+            prev = Some(flight);
+        }
+
+        let count_key = DataKey::ItineraryCount(passenger.clone());
+        let index: u64 = Self::fetch(&env, &count_key).unwrap_or(0);
+        Self::persist(&env, &DataKey::Itinerary(passenger, index), &flight_ids);
+        Self::persist(&env, &count_key, &(index + 1));
+        (index, receipts)
+    }
+
+    pub fn get_itinerary(env: Env, passenger: Address, index: u64) -> Option<Vec<BytesN<32>>> {
+        Self::require_private_read(&env, &passenger);
+        Self::fetch(&env, &DataKey::Itinerary(passenger, index))
+    }
+
+    /// Lowest unassigned seat number, or 0 when every physical seat is taken
+    fn first_free_seat(seat_map: &Map<u32, PassengerId>, max_passengers: u32) -> u32 {
+        (1..=max_passengers)
+            .find(|s| !seat_map.contains_key(*s))
+            .unwrap_or(0)
+    }
+
+    /// Book on behalf of a passenger who has authorized `agent`. The agent pays,
+    /// but the ticket, registry entry and cancellation rights are the passenger's.
+    /// Returns the receipt `buy_ticket` would.
+    #[allow(clippy::too_many_arguments)]
+    pub fn buy_ticket_for(
+        env: Env,
+        flight_id: BytesN<32>,
+        agent: Address,
+        passenger: Address,
+        details: Symbol,
+        seat: u32,
+        voucher: Option<BytesN<32>>,
+        passenger_type: PassengerType,
+    ) -> Receipt {
+        Self::require_initialized(&env);
+        agent.require_auth();
+        let delegated = env
+            .storage()
+            .persistent()
+            .has(&DataKey::Agent(passenger.clone(), agent.clone()));
+        if !delegated {
+            panic_with_error!(&env, FlyStellarError::Unauthorized);
+        }
+        Self::book(
+            &env,
+            flight_id,
+            &agent,
+            &passenger,
+            details,
+            seat,
+            voucher,
+            passenger_type,
+            None,
+        )
+    }
+
+    /// Shared booking path: `payer` funds the fare, `passenger` owns the ticket.
+    /// Infants are booked under the accompanying adult's address, one per adult.
+    #[allow(clippy::too_many_arguments)]
+    fn book(
+        env: &Env,
+        flight_id: BytesN<32>,
+        payer: &Address,
+        passenger: &Address,
+        details: Symbol,
+        seat: u32,
+        voucher: Option<BytesN<32>>,
+        passenger_type: PassengerType,
+        pay_token: Option<Address>,
+    ) -> Receipt {
+        Self::require_valid_details(env, &details);
+        Self::require_no_cooldown(env, passenger);
+        Self::require_not_frozen(env, &flight_id);
+
+        // Get flight details
+        let mut flight: FlightDetails =
+            Self::load_flight(env, &flight_id).expect("Flight not found");
+
+        let mut fare = Self::quote(env, &flight, Some(passenger), &None, passenger_type);
+        let infant = passenger_type == PassengerType::Infant;
+        // `quote` already priced the booking at the promo fare if one is live
+        let promo = !infant && Self::live_promo(env, &flight).is_some();
+        if promo {
+            Self::adjust_promo_quota(env, &flight_id, -1);
+        }
+
+        // Reserve the requested seat. Seat 0 books without a seat and is only
+        // accepted for overbooked places once every physical seat is sold.
+        // Infants never take a seat.
+        let overbooked = flight.passenger_count >= flight.max_passengers;
+        if infant {
+            if seat != 0 {
+                panic_with_error!(env, FlyStellarError::InvalidInput);
+            }
+        } else if seat > flight.max_passengers || (seat == 0 && !overbooked) {
+            panic_with_error!(env, FlyStellarError::InvalidInput);
+        }
+
+        let pass_list_key = DataKey::PassengerList(flight_id.clone());
+        let mut pass_list: Vec<PassengerRecord> =
+            Self::fetch(env, &pass_list_key).unwrap_or(Vec::new(env));
+        let holder = Self::holder(env, &flight_id, passenger);
+        let passenger_id = Self::stored_id(env, &pass_list, &holder);
+        let payer_id = if payer == passenger {
+            passenger_id.clone()
+        } else {
+            Self::stored_id(env, &pass_list, &Self::holder(env, &flight_id, payer))
+        };
+        // Refunds to a hashed id are credited in the base token
+        if pay_token.is_some() && (passenger_id.address().is_none() || payer_id.address().is_none())
+        {
+            panic_with_error!(env, FlyStellarError::InvalidToken);
+        }
+
+        if seat != 0 {
+            let seat_map_key = DataKey::SeatMap(flight_id.clone());
+            let mut seat_map: Map<u32, PassengerId> =
+                Self::fetch(env, &seat_map_key).unwrap_or(Map::new(env));
+            if seat_map.contains_key(seat) {
+                panic_with_error!(env, FlyStellarError::SeatTaken);
+            }
+            seat_map.set(seat, passenger_id.clone());
+            Self::persist(env, &seat_map_key, &seat_map);
+        }
+
+        if infant {
+            Self::require_infant_room(env, &pass_list, &holder);
+        }
+
+        // Apply voucher discount; only one voucher per passenger per flight
+        if let Some(code) = &voucher {
+            for rec in pass_list.iter() {
+                if holder.is(&rec.passenger) && rec.voucher.is_some() {
+                    panic_with_error!(env, FlyStellarError::VoucherNotStackable);
+                }
+            }
+            fare = Self::redeem_voucher(env, code, fare);
+        }
+
+        // Move the fare into contract escrow and the booking fee into the fee
+        // pot, both converted into the chosen token
+        let fee = Self::get_booking_fee(env.clone());
+        let (token, token_paid, token_fee) = match &pay_token {
+            None => (Self::token_client(env), fare, fee),
+            Some(pay_token) => {
+                let (num, den) = Self::get_accepted_token(env.clone(), pay_token.clone())
+                    .unwrap_or_else(|| panic_with_error!(env, FlyStellarError::InvalidInput));
+                (
+                    token::Client::new(env, pay_token),
+                    fare * num / den,
+                    fee * num / den,
+                )
+            }
+        };
+        let charge = token_paid + token_fee;
+        if let Err(err) = Self::check_funds(&token, payer, charge) {
+            panic_with_error!(env, err);
+        }
+        if charge > 0 {
+            token.transfer(payer, env.current_contract_address(), &charge);
+        }
+        match &pay_token {
+            None => {
+                if fare > 0 {
+                    Self::record_flows(env, &flight_id, fare, 0, 0);
+                }
+                if fee > 0 {
+                    Self::add_to_fee_pot(env, fee);
+                }
+            }
+            Some(pay_token) => {
+                Self::record_token_flows(env, &flight_id, pay_token, token_paid, token_fee)
+            }
+        }
+        Self::add_lifetime(env, &DataKeyExt::LifetimeSpend(passenger.clone()), fare);
+
+        let mut index = Self::booking_index(env, &flight_id);
+        let booking_seq = index.assign(&passenger_id);
+        Self::persist(env, &DataKeyExt::BookingIndex(flight_id.clone()), &index);
+
+        // Create passenger record
+        let record = PassengerRecord {
+            passenger: passenger_id.clone(),
+            payer: payer_id,
+            paid: fare,
+            details,
+            seat,
+            voucher,
+            checked_in: false,
+            settled: false,
+            compensation: 0,
+            booked_at: env.ledger().timestamp(),
+            passenger_type,
+            pay_token,
+            token_paid,
+            fee_paid: fee,
+            booking_seq,
+            dispute: None,
+            promo,
+        };
+
+        Self::require_list_room(env, &pass_list, 1);
+        pass_list.push_back(record);
+        Self::persist(env, &pass_list_key, &pass_list);
+        // The accompanying adult's booking already covers an infant's registry
+        // entry and count
+        if !infant {
+            // Add to passenger's flight registry and use up any seat hold
+            Self::add_to_registry(env, passenger, &flight_id);
+            Self::add_tickets(env, &flight_id, &passenger_id, 1);
+            Self::clear_hold(env, &flight_id, passenger);
+
+            // Update passenger count
+            flight.passenger_count = flight
+                .passenger_count
+                .checked_add(1)
+                .expect("passenger count overflow");
+            Self::save_flight_state(env, &flight);
+            Self::announce_occupancy(env, &flight);
+        }
+
+        // A topic keyed by address would undo privacy mode
+        if !Self::get_privacy_mode(env.clone()) {
+            TicketBooked {
+                passenger: passenger.clone(),
+                flight_id: flight_id.clone(),
+                paid: fare,
+                seat,
+            }
+            .publish(env);
+        }
+        Self::notify_hook(env, "on_booking", &flight_id, passenger, fare);
+        Receipt {
+            flight_id,
+            paid: fare,
+            seat,
+            booked_at: env.ledger().timestamp(),
+        }
+    }
+
+    /// Fail with `PassengerListFull` unless `adding` more records fit
+    fn require_list_room(env: &Env, pass_list: &Vec<PassengerRecord>, adding: u32) {
+        if pass_list.len().saturating_add(adding) > MAX_PASSENGER_LIST {
+            panic_with_error!(env, FlyStellarError::PassengerListFull);
+        }
+    }
+
+    fn booking_index(env: &Env, flight_id: &BytesN<32>) -> BookingIndex {
+        Self::fetch(env, &DataKeyExt::BookingIndex(flight_id.clone())).unwrap_or(BookingIndex {
+            next_seq: 1,
+            holders: Map::new(env),
+        })
+    }
+
+    /// Add a flight to the passenger's active registry, once however many
+    /// seats they hold on it
+    fn add_to_registry(env: &Env, passenger: &Address, flight_id: &BytesN<32>) {
+        let pass_reg_key = DataKey::PassengerRegistry(passenger.clone());
+        let mut pass_registry: Vec<BytesN<32>> =
+            Self::fetch(env, &pass_reg_key).unwrap_or(Vec::new(env));
+        if pass_registry.contains(flight_id) {
+            return;
+        }
+        pass_registry.push_back(flight_id.clone());
+        Self::persist(env, &pass_reg_key, &pass_registry);
+    }
+
+    fn add_tickets(env: &Env, flight_id: &BytesN<32>, passenger: &PassengerId, seats: u32) {
+        let key = DataKeyExt::Ticket(flight_id.clone(), passenger.clone());
+        let mut ticket: Ticket = Self::fetch(env, &key).unwrap_or(Ticket {
+            seats: 0,
+            boarded: 0,
+            disputed: false,
+        });
+        ticket.seats += seats;
+        Self::persist(env, &key, &ticket);
+    }
+
+    fn set_ticket_disputed(
+        env: &Env,
+        flight_id: &BytesN<32>,
+        passenger: &PassengerId,
+        disputed: bool,
+    ) {
+        let key = DataKeyExt::Ticket(flight_id.clone(), passenger.clone());
+        if let Some(mut ticket) = Self::fetch::<Ticket>(env, &key) {
+            ticket.disputed = disputed;
+            Self::persist(env, &key, &ticket);
+        }
+    }
+
+    fn remove_tickets(env: &Env, flight_id: &BytesN<32>, passenger: &PassengerId, seats: u32) {
+        let key = DataKeyExt::Ticket(flight_id.clone(), passenger.clone());
+        let Some(mut ticket) = Self::fetch::<Ticket>(env, &key) else {
+            return;
+        };
+        ticket.seats = ticket.seats.saturating_sub(seats);
+        if ticket.seats == 0 {
+            env.storage().persistent().remove(&key);
+            return;
+        }
+        ticket.boarded = ticket.boarded.min(ticket.seats);
+        Self::persist(env, &key, &ticket);
+    }
+
+    /// Each infant needs an adult of their own on the booking: fails unless
+    /// `holder` has more adult records than infant ones in `pass_list`
+    fn require_infant_room(env: &Env, pass_list: &Vec<PassengerRecord>, holder: &Holder) {
+        let mut adults = 0;
+        let mut infants = 0;
+        for rec in pass_list.iter() {
+            if !holder.is(&rec.passenger) {
+                continue;
+            }
+            match rec.passenger_type {
+                PassengerType::Adult => adults += 1,
+                PassengerType::Infant => infants += 1,
+                PassengerType::Child => {}
+            }
+        }
+        if infants >= adults {
+            panic_with_error!(env, FlyStellarError::InvalidInput);
+        }
+    }
+
+    /// Seed bookings migrated from an off-chain system. No funds move, but the
+    /// imported fares are counted as collected. An infant must come after the
+    /// adult they travel with, who may already be booked.
+    pub fn import_passengers(
+        env: Env,
+        admin: Address,
+        flight_id: BytesN<32>,
+        records: Vec<PassengerRecord>,
+    ) {
+        Self::require_admin(&env, &admin);
+        Self::audit(
+            &env,
+            AuditAction::PassengersEdited,
+            &admin,
+            Some(flight_id.clone()),
+            records.len() as i128,
+        );
+
+        let mut flight: FlightDetails = Self::load_flight(&env, &flight_id)
+            .unwrap_or_else(|| panic_with_error!(&env, FlyStellarError::FlightNotFound));
+
+        if flight.status != Symbol::new(&env, "booking") {
+            panic_with_error!(&env, FlyStellarError::InvalidStatus);
+        }
+        let seated = records
+            .iter()
+            .filter(|rec| rec.passenger_type != PassengerType::Infant)
+            .count() as u32;
+        if flight.passenger_count.saturating_add(seated) > flight.max_passengers {
+            panic_with_error!(&env, FlyStellarError::FlightFull);
+        }
+
+        let pass_list_key = DataKey::PassengerList(flight_id.clone());
+        let mut pass_list: Vec<PassengerRecord> =
+            Self::fetch(&env, &pass_list_key).unwrap_or(Vec::new(&env));
+        Self::require_list_room(&env, &pass_list, records.len());
+
+        let seat_map_key = DataKey::SeatMap(flight_id.clone());
+        let mut seat_map: Map<u32, PassengerId> =
+            Self::fetch(&env, &seat_map_key).unwrap_or(Map::new(&env));
+
+        // Imported bookings are numbered in the order given, after any existing
+        let mut index = Self::booking_index(&env, &flight_id);
+        let mut collected: i128 = 0;
+        for mut rec in records.iter() {
+            // Plain ids are stored the way a booking made now would be
+            let holder = Self::holder_of(&env, &flight_id, &rec.passenger);
+            rec.passenger = Self::stored_id(&env, &pass_list, &holder);
+            let payer = Self::holder_of(&env, &flight_id, &rec.payer);
+            rec.payer = Self::stored_id(&env, &pass_list, &payer);
+            let hashed = rec.passenger.address().is_none() || rec.payer.address().is_none();
+            if rec.pay_token.is_some() && hashed {
+                panic_with_error!(&env, FlyStellarError::InvalidToken);
+            }
+            rec.booking_seq = index.assign(&rec.passenger);
+            if rec.paid < 0 {
+                panic_with_error!(&env, FlyStellarError::InvalidInput);
+            }
+            Self::require_valid_details(&env, &rec.details);
+            collected += rec.paid;
+            if rec.passenger_type == PassengerType::Infant {
+                // Lap infants ride on an adult's booking without a seat
+                if rec.seat != 0 {
+                    panic_with_error!(&env, FlyStellarError::InvalidInput);
+                }
+                Self::require_infant_room(&env, &pass_list, &holder);
+                pass_list.push_back(rec);
+                continue;
+            }
+            if rec.seat == 0 || rec.seat > flight.max_passengers {
+                panic_with_error!(&env, FlyStellarError::InvalidInput);
+            }
+            if seat_map.contains_key(rec.seat) {
+                panic_with_error!(&env, FlyStellarError::SeatTaken);
+            }
+            seat_map.set(rec.seat, rec.passenger.clone());
+            if let Some(address) = &holder.address {
+                Self::add_to_registry(&env, address, &flight_id);
+            }
+            Self::add_tickets(&env, &flight_id, &rec.passenger, 1);
+            pass_list.push_back(rec);
+        }
+
+        Self::persist(&env, &pass_list_key, &pass_list);
+        Self::persist(&env, &seat_map_key, &seat_map);
+        Self::persist(&env, &DataKeyExt::BookingIndex(flight_id.clone()), &index);
+        Self::record_flows(&env, &flight_id, collected, 0, 0);
+
+        flight.passenger_count += seated;
+        Self::save_flight_state(&env, &flight);
+        Self::announce_occupancy(&env, &flight);
+    }
+
+    /// Cancel a ticket and get refund, paid to `refund_to` when given. The seat
+    /// goes back on sale; there is no waitlist to promote anyone from.
+    pub fn cancel_ticket(
+        env: Env,
+        flight_id: BytesN<32>,
+        passenger: Address,
+        refund_to: Option<Address>,
+    ) {
+        Self::require_initialized(&env);
+        // Passenger must authorize cancellation
+        passenger.require_auth();
+
+        Self::cancel_bookings(env, flight_id, passenger, refund_to, None);
+    }
+
+    /// Give back `count` of the seats a passenger holds on a flight, most recent
+    /// bookings first, refunded like `cancel_ticket`. Fails when they hold fewer.
+    pub fn cancel_seats(env: Env, flight_id: BytesN<32>, passenger: Address, count: u32) {
+        Self::require_initialized(&env);
+        passenger.require_auth();
+
+        Self::cancel_bookings(env, flight_id, passenger, None, Some(count));
+    }
+
+    /// Book a ticket for someone else. Only the payer authorizes and pays; the
+    /// ticket, registry entry and check-in rights are the passenger's, and the
+    /// first free seat is assigned. Either of them may cancel it, and the
+    /// refund always returns to the payer. Returns the booking's receipt.
+    pub fn gift_ticket(
+        env: Env,
+        flight_id: BytesN<32>,
+        payer: Address,
+        passenger: Address,
+        details: Symbol,
+    ) -> Receipt {
+        Self::require_initialized(&env);
+        payer.require_auth();
+
+        let flight: FlightDetails = Self::load_flight(&env, &flight_id)
+            .unwrap_or_else(|| panic_with_error!(&env, FlyStellarError::FlightNotFound));
+        let seat_map: Map<u32, PassengerId> =
+            Self::fetch(&env, &DataKey::SeatMap(flight_id.clone())).unwrap_or(Map::new(&env));
+        let seat = Self::first_free_seat(&seat_map, flight.max_passengers);
+        Self::book(
+            &env,
+            flight_id,
+            &payer,
+            &passenger,
+            details,
+            seat,
+            None,
+            PassengerType::Adult,
+            None,
+        )
+    }
+
+    /// Payer-side cancellation of tickets bought through `gift_ticket`. Every
+    /// booking the passenger holds on the flight must have been paid by `payer`.
+    pub fn cancel_gift(env: Env, flight_id: BytesN<32>, payer: Address, passenger: Address) {
+        Self::require_initialized(&env);
+        payer.require_auth();
+
+        let pass_list: Vec<PassengerRecord> =
+            Self::fetch(&env, &DataKey::PassengerList(flight_id.clone())).unwrap_or(Vec::new(&env));
+        let holder = Self::holder(&env, &flight_id, &passenger);
+        let payer = Self::holder(&env, &flight_id, &payer);
+        if pass_list
+            .iter()
+            .any(|rec| holder.is(&rec.passenger) && !payer.is(&rec.payer))
+        {
+            panic_with_error!(&env, FlyStellarError::Unauthorized);
+        }
+
+        Self::cancel_bookings(env, flight_id, passenger, None, None);
+    }
+
+    fn cancel_bookings(
+        env: Env,
+        flight_id: BytesN<32>,
+        passenger: Address,
+        refund_to: Option<Address>,
+        seats: Option<u32>,
+    ) {
+        // Get flight details
+        let mut flight: FlightDetails =
+            Self::load_flight(&env, &flight_id).expect("Flight not found");
+
+        Self::require_no_pending_refunds(&env, &flight_id);
+        Self::require_not_frozen(&env, &flight_id);
+        let holder = Self::holder(&env, &flight_id, &passenger);
+        Self::require_not_disputed(&env, &flight_id, &holder);
+        // Seats that have boarded can't be given back
+        if let Some(ticket) =
+            Self::fetch::<Ticket>(&env, &Self::ticket_key(&env, &flight_id, &holder))
+        {
+            let cancelling = seats.unwrap_or(ticket.seats).min(ticket.seats);
+            if ticket.boarded > ticket.seats - cancelling {
+                panic_with_error!(&env, FlyStellarError::AlreadyBoarded);
+            }
+        }
+        // Bookings made before a reschedule are refunded in full while its
+        // window is open; anything else follows the normal policy
+        let rescheduled_at = Self::open_reschedule_window(&env, &flight);
+        let mut normal_bps = rescheduled_at
+            .is_none()
+            .then(|| Self::cancel_refund_bps(&env, &flight));
+        let removed = Self::take_bookings(&env, &flight_id, &mut flight, &holder, seats);
+
+        // Process refund (90% of what was actually paid to passenger, 10% admin fee).
+        // Records already refunded by `refund_batch` owe nothing more.
+        let mut refunds: Vec<i128> = Vec::new(&env);
+        let mut refund_total: i128 = 0;
+        let mut escrow_refund: i128 = 0;
+        let mut fee_total: i128 = 0;
+        for rec in removed.iter() {
+            let refund_bps = match rescheduled_at {
+                Some(at) if rec.booked_at <= at => BPS_DENOMINATOR,
+                _ => *normal_bps.get_or_insert_with(|| Self::cancel_refund_bps(&env, &flight)),
+            };
+            let owed = if rec.settled { 0 } else { rec.paid };
+            let refund = Self::refund_share(&env, owed, refund_bps);
+            refunds.push_back(refund);
+            refund_total += refund;
+            if !Self::record_paid_in_token(&env, &flight_id, &rec, refund, owed - refund) {
+                escrow_refund += refund;
+                fee_total += owed - refund;
+            }
+        }
+
+        // Booked first so a drawn-down escrow fails cleanly before any transfer
+        Self::record_flows(&env, &flight_id, 0, escrow_refund, fee_total);
+
+        // Refund whoever paid for each booking; `refund_to` only redirects the
+        // passenger's own money. The admin fee stays in escrow in the fee pot.
+        let recipient = PassengerId::Plain(refund_to.unwrap_or(passenger.clone()));
+        for (rec, refund) in removed.iter().zip(refunds.iter()) {
+            let to = if holder.is(&rec.payer) {
+                &recipient
+            } else {
+                &rec.payer
+            };
+            Self::refund_record(&env, &rec, refund, to);
+        }
+        Self::add_to_fee_pot(&env, fee_total);
+        Self::add_flight_fees(&env, &flight_id, fee_total);
+        if Self::get_cancel_cooldown(env.clone()) > 0 {
+            Self::persist(
+                &env,
+                &DataKey::CancelCooldown(passenger.clone()),
+                &env.ledger().timestamp(),
+            );
+        }
+        if !Self::get_privacy_mode(env.clone()) {
+            TicketCancelled {
+                passenger: passenger.clone(),
+                flight_id: flight_id.clone(),
+                refund: refund_total,
+            }
+            .publish(&env);
+        }
+        Self::notify_hook(
+            &env,
+            "on_cancellation",
+            &flight_id,
+            &passenger,
+            refund_total,
+        );
+    }
+
+    /// Share of the fare a voluntary cancellation gives back right now: 90% normally,
+    /// all of it after an airline cancellation or a qualifying delay, and nothing
+    /// inside the cutoff under the forfeit policy
+    fn cancel_refund_bps(env: &Env, flight: &FlightDetails) -> u32 {
+        // Departed flights are settled through `sweep_no_shows` instead
+        if flight.status == Symbol::new(env, "takeoff") {
+            panic_with_error!(env, FlyStellarError::InvalidStatus);
+        }
+        if flight.status == Symbol::new(env, "cancelled") {
+            return BPS_DENOMINATOR;
+        }
+        if env.ledger().timestamp() >= Self::cancel_deadline(env, flight) {
+            if !Self::get_cancel_policy(env.clone()).forfeit {
+                panic_with_error!(env, FlyStellarError::CancellationClosed);
+            }
+            return 0;
+        }
+        if Self::delay_tier(env, flight.delay_secs).is_some() {
+            return BPS_DENOMINATOR;
+        }
+        9_000
+    }
+
+    /// Add cancellation fees kept on a flight to its running total
+    fn add_flight_fees(env: &Env, flight_id: &BytesN<32>, fee: i128) {
+        if fee == 0 {
+            return;
+        }
+        let key = DataKeyExt::FlightFees(flight_id.clone());
+        let total: i128 = Self::fetch(env, &key).unwrap_or(0);
+        Self::persist(env, &key, &(total + fee));
+    }
+
+    /// Cancellation fees kept on a flight so far. Unlike `FlightBalance::fees`
+    /// this leaves out no-show settlements.
+    pub fn get_flight_fees(env: Env, admin: Address, flight_id: BytesN<32>) -> i128 {
+        Self::require_admin(&env, &admin);
+        Self::fetch(&env, &DataKeyExt::FlightFees(flight_id)).unwrap_or(0)
+    }
+
+    /// The passenger's `refund_bps` share of `amount`, rounded per `get_refund_rounding`
+    fn refund_share(env: &Env, amount: i128, refund_bps: u32) -> i128 {
+        let scaled = amount * refund_bps as i128;
+        let denominator = BPS_DENOMINATOR as i128;
+        match Self::get_refund_rounding(env.clone()) {
+            Rounding::Floor => scaled / denominator,
+            Rounding::Nearest => (scaled + denominator / 2) / denominator,
+            Rounding::Ceil => (scaled + denominator - 1) / denominator,
+        }
+    }
+
+    /// Round partial refunds more in the passenger's favour; rounding them
+    /// down again goes through `queue_config_change`
+    pub fn set_refund_rounding(env: Env, admin: Address, mode: Rounding) {
+        Self::require_admin(&env, &admin);
+        Self::audit(&env, AuditAction::FeeChanged, &admin, None, mode as i128);
+
+        Self::apply_now(&env, ConfigChange::RefundRounding(mode));
+    }
+
+    pub fn get_refund_rounding(env: Env) -> Rounding {
+        env.storage()
+            .instance()
+            .get(&DataKeyExt::RefundRounding)
+            .unwrap_or_default()
+    }
+
+    /// Relax the default cancellation cutoff or stop forfeiting fares inside it;
+    /// anything stricter goes through `queue_config_change`
+    pub fn set_cancel_policy(env: Env, admin: Address, cutoff_secs: u64, forfeit: bool) {
+        Self::require_admin(&env, &admin);
+        Self::audit(
+            &env,
+            AuditAction::FeeChanged,
+            &admin,
+            None,
+            cutoff_secs as i128,
+        );
+
+        Self::apply_now(
+            &env,
+            ConfigChange::CancelPolicy(CancelPolicy {
+                cutoff_secs,
+                forfeit,
+            }),
+        );
+    }
+
+    pub fn get_cancel_policy(env: Env) -> CancelPolicy {
+        env.storage()
+            .instance()
+            .get(&DataKey::CancelPolicy)
+            .unwrap_or_default()
+    }
+
+    /// Override the default cutoff for one flight; `None` falls back to the default
+    pub fn set_flight_cancel_cutoff(
+        env: Env,
+        admin: Address,
+        flight_id: BytesN<32>,
+        cutoff_secs: Option<u64>,
+    ) {
+        Self::require_admin(&env, &admin);
+        Self::audit(
+            &env,
+            AuditAction::FeeChanged,
+            &admin,
+            Some(flight_id.clone()),
+            cutoff_secs.unwrap_or(0) as i128,
+        );
+
+        if !env
+            .storage()
+            .persistent()
+            .has(&DataKey::Flight(flight_id.clone()))
+        {
+            panic_with_error!(&env, FlyStellarError::FlightNotFound);
+        }
+        let key = DataKey::CancelCutoff(flight_id);
+        match cutoff_secs {
+            Some(secs) => Self::persist(&env, &key, &secs),
+            None => env.storage().persistent().remove(&key),
+        }
+    }
+
+    /// First timestamp at which `cancel_ticket` stops giving the normal refund
+    pub fn get_cancel_deadline(env: Env, flight_id: BytesN<32>) -> u64 {
+        let flight: FlightDetails = Self::load_flight(&env, &flight_id)
+            .unwrap_or_else(|| panic_with_error!(&env, FlyStellarError::FlightNotFound));
+        Self::cancel_deadline(&env, &flight)
+    }
+
+    fn cancel_deadline(env: &Env, flight: &FlightDetails) -> u64 {
+        let cutoff = Self::fetch(env, &DataKey::CancelCutoff(flight.id.clone()))
+            .unwrap_or_else(|| Self::get_cancel_policy(env.clone()).cutoff_secs);
+        flight.depart_at.saturating_sub(cutoff)
+    }
+
+    /// Involuntarily remove a passenger from an overbooked flight, refunding their
+    /// fare in full plus `compensation` paid out of the fee pot
+    pub fn bump_passenger(
+        env: Env,
+        admin: Address,
+        flight_id: BytesN<32>,
+        passenger: Address,
+        compensation: i128,
+    ) {
+        Self::require_admin(&env, &admin);
+        Self::audit(
+            &env,
+            AuditAction::PassengersEdited,
+            &admin,
+            Some(flight_id.clone()),
+            compensation,
+        );
+
+        let mut flight: FlightDetails = Self::load_flight(&env, &flight_id)
+            .unwrap_or_else(|| panic_with_error!(&env, FlyStellarError::FlightNotFound));
+        if flight.status != Symbol::new(&env, "booking")
+            || flight.passenger_count <= flight.max_passengers
+        {
+            panic_with_error!(&env, FlyStellarError::InvalidStatus);
+        }
+        if compensation < 0 || compensation > Self::get_fee_pot(env.clone()) {
+            panic_with_error!(&env, FlyStellarError::InvalidInput);
+        }
+
+        let holder = Self::holder(&env, &flight_id, &passenger);
+        let removed = Self::take_bookings(&env, &flight_id, &mut flight, &holder, None);
+        let mut refund_total: i128 = 0;
+        for rec in removed.iter() {
+            if !Self::record_paid_in_token(&env, &flight_id, &rec, rec.paid, 0) {
+                refund_total += rec.paid;
+            }
+            Self::refund_record(&env, &rec, rec.paid, &rec.payer);
+        }
+
+        // Compensation is the passenger's regardless of who paid the fare
+        if compensation > 0 {
+            Self::token_client(&env).transfer(
+                &env.current_contract_address(),
+                &passenger,
+                &compensation,
+            );
+        }
+        Self::add_to_fee_pot(&env, -compensation);
+        Self::record_flows(&env, &flight_id, 0, refund_total, 0);
+        Self::record_compensation(&env, &flight_id, compensation);
+    }
+
+    /// Flag every booking `passenger` holds on a flight as disputed, freezing
+    /// them until `resolve_dispute`. Other passengers are unaffected.
+    pub fn flag_ticket(
+        env: Env,
+        caller: Address,
+        flight_id: BytesN<32>,
+        passenger: Address,
+        reason: Symbol,
+    ) {
+        Self::require_admin_or_operator(&env, &caller);
+        Self::audit(
+            &env,
+            AuditAction::PassengersEdited,
+            &caller,
+            Some(flight_id.clone()),
+            0,
+        );
+
+        let pass_list_key = DataKey::PassengerList(flight_id.clone());
+        let mut pass_list: Vec<PassengerRecord> =
+            Self::fetch(&env, &pass_list_key).unwrap_or(Vec::new(&env));
+        let holder = Self::holder(&env, &flight_id, &passenger);
+        let mut found = None;
+        for i in 0..pass_list.len() {
+            let mut rec = pass_list.get_unchecked(i);
+            if holder.is(&rec.passenger) {
+                found = Some(rec.passenger.clone());
+                rec.dispute = Some(reason.clone());
+                pass_list.set(i, rec);
+            }
+        }
+        let Some(id) = found else {
+            panic_with_error!(&env, FlyStellarError::PassengerNotFound);
+        };
+        Self::persist(&env, &pass_list_key, &pass_list);
+        Self::set_ticket_disputed(&env, &flight_id, &id, true);
+
+        if !Self::get_privacy_mode(env.clone()) {
+            TicketFlagged {
+                flight_id,
+                passenger,
+                reason,
+            }
+            .publish(&env);
+        }
+    }
+
+    /// Settle a booking flagged by `flag_ticket`. Cancelling outcomes need the
+    /// flight to still be open for booking.
+    pub fn resolve_dispute(
+        env: Env,
+        admin: Address,
+        flight_id: BytesN<32>,
+        passenger: Address,
+        outcome: DisputeOutcome,
+    ) {
+        Self::require_admin(&env, &admin);
+
+        let pass_list_key = DataKey::PassengerList(flight_id.clone());
+        let mut pass_list: Vec<PassengerRecord> =
+            Self::fetch(&env, &pass_list_key).unwrap_or(Vec::new(&env));
+        let holder = Self::holder(&env, &flight_id, &passenger);
+        let mut disputed = false;
+        let mut found = None;
+        for rec in pass_list.iter() {
+            if holder.is(&rec.passenger) {
+                found = Some(rec.passenger.clone());
+                disputed |= rec.dispute.is_some();
+            }
+        }
+        let Some(id) = found else {
+            panic_with_error!(&env, FlyStellarError::PassengerNotFound);
+        };
+        if !disputed {
+            panic_with_error!(&env, FlyStellarError::InvalidStatus);
+        }
+
+        let mut amount: i128 = 0;
+        if outcome == DisputeOutcome::Clear {
+            for i in 0..pass_list.len() {
+                let mut rec = pass_list.get_unchecked(i);
+                if holder.is(&rec.passenger) {
+                    rec.dispute = None;
+                    pass_list.set(i, rec);
+                }
+            }
+            Self::persist(&env, &pass_list_key, &pass_list);
+            Self::set_ticket_disputed(&env, &flight_id, &id, false);
+        } else {
+            let mut flight: FlightDetails = Self::load_flight(&env, &flight_id)
+                .unwrap_or_else(|| panic_with_error!(&env, FlyStellarError::FlightNotFound));
+            if flight.status != Symbol::new(&env, "booking") {
+                panic_with_error!(&env, FlyStellarError::InvalidStatus);
+            }
+            Self::require_no_pending_refunds(&env, &flight_id);
+
+            let removed = Self::take_bookings(&env, &flight_id, &mut flight, &holder, None);
+            let refunding = outcome == DisputeOutcome::Refund;
+            let mut held: i128 = 0;
+            for rec in removed.iter() {
+                let (refund, fee) = if refunding {
+                    (rec.paid, 0)
+                } else {
+                    (0, rec.paid)
+                };
+                if !Self::record_paid_in_token(&env, &flight_id, &rec, refund, fee) {
+                    held += rec.paid;
+                }
+                if refunding {
+                    Self::refund_record(&env, &rec, rec.paid, &rec.payer);
+                }
+                amount += rec.paid;
+            }
+            if refunding {
+                Self::record_flows(&env, &flight_id, 0, held, 0);
+            } else {
+                Self::add_to_fee_pot(&env, held);
+                Self::record_flows(&env, &flight_id, 0, 0, held);
+            }
+        }
+
+        Self::audit(
+            &env,
+            AuditAction::PassengersEdited,
+            &admin,
+            Some(flight_id.clone()),
+            amount,
+        );
+        if !Self::get_privacy_mode(env.clone()) {
+            DisputeResolved {
+                flight_id,
+                passenger,
+                outcome,
+                amount,
+            }
+            .publish(&env);
+        }
+    }
+
+    fn require_not_disputed(env: &Env, flight_id: &BytesN<32>, holder: &Holder) {
+        let pass_list: Vec<PassengerRecord> =
+            Self::fetch(env, &DataKey::PassengerList(flight_id.clone())).unwrap_or(Vec::new(env));
+        if pass_list
+            .iter()
+            .any(|rec| holder.is(&rec.passenger) && rec.dispute.is_some())
+        {
+            panic_with_error!(env, FlyStellarError::TicketDisputed);
+        }
+    }
+
+    /// Move a passenger's ticket to another flight in one step. The fare difference
+    /// is charged to the passenger, who then owns the refund rights, or refunded to
+    /// whoever paid the original ticket. The first free seat on the new flight is
+    /// assigned.
+    pub fn rebook(env: Env, from_flight: BytesN<32>, to_flight: BytesN<32>, passenger: Address) {
+        Self::require_initialized(&env);
+        passenger.require_auth();
+
+        if from_flight == to_flight {
+            panic_with_error!(&env, FlyStellarError::InvalidInput);
+        }
+        Self::require_not_frozen(&env, &from_flight);
+        Self::require_not_frozen(&env, &to_flight);
+        let from_holder = Self::holder(&env, &from_flight, &passenger);
+        Self::require_not_disputed(&env, &from_flight, &from_holder);
+        let mut from: FlightDetails = Self::load_flight(&env, &from_flight)
+            .unwrap_or_else(|| panic_with_error!(&env, FlyStellarError::FlightNotFound));
+        let mut to: FlightDetails = Self::load_flight(&env, &to_flight)
+            .unwrap_or_else(|| panic_with_error!(&env, FlyStellarError::FlightNotFound));
+
+        // Leaving the old flight follows the same rules as cancelling it
+        if from.status == Symbol::new(&env, "takeoff") {
+            panic_with_error!(&env, FlyStellarError::InvalidStatus);
+        }
+        if from.status != Symbol::new(&env, "cancelled")
+            && env.ledger().timestamp() >= Self::cancel_deadline(&env, &from)
+        {
+            panic_with_error!(&env, FlyStellarError::CancellationClosed);
+        }
+        Self::require_no_pending_refunds(&env, &from_flight);
+
+        // Only a single seated booking moves; infants travel with their adult
+        let removed = Self::take_bookings(&env, &from_flight, &mut from, &from_holder, None);
+        if removed.len() != 1 || removed.get_unchecked(0).passenger_type == PassengerType::Infant {
+            panic_with_error!(&env, FlyStellarError::InvalidInput);
+        }
+        let old = removed.get_unchecked(0);
+        // Fare differences are settled in the base token only
+        if old.pay_token.is_some() {
+            panic_with_error!(&env, FlyStellarError::InvalidInput);
+        }
+        let fare = Self::quote(&env, &to, Some(&passenger), &None, old.passenger_type);
+        // A ticket already refunded by `refund_batch` carries no credit over
+        let credit = if old.settled { 0 } else { old.paid };
+
+        // The escrow already held moves across; only the difference is
+        // collected or refunded
+        Self::record_transfer(&env, &from_flight, &to_flight, credit.min(fare));
+        if fare > credit {
+            Self::token_client(&env).transfer(
+                &passenger,
+                env.current_contract_address(),
+                &(fare - credit),
+            );
+            Self::record_flows(&env, &to_flight, fare - credit, 0, 0);
+        } else if fare < credit {
+            Self::record_flows(&env, &from_flight, 0, credit - fare, 0);
+            Self::pay_id(&env, &old.payer, credit - fare);
+        }
+
+        let pass_list_key = DataKey::PassengerList(to_flight.clone());
+        let mut pass_list: Vec<PassengerRecord> =
+            Self::fetch(&env, &pass_list_key).unwrap_or(Vec::new(&env));
+        Self::require_list_room(&env, &pass_list, 1);
+        let passenger_id = Self::stored_id(
+            &env,
+            &pass_list,
+            &Self::holder(&env, &to_flight, &passenger),
+        );
+        let payer = if fare > credit || from_holder.is(&old.payer) {
+            passenger_id.clone()
+        } else {
+            old.payer
+        };
+
+        let seat_map_key = DataKey::SeatMap(to_flight.clone());
+        let mut seat_map: Map<u32, PassengerId> =
+            Self::fetch(&env, &seat_map_key).unwrap_or(Map::new(&env));
+        let seat = Self::first_free_seat(&seat_map, to.max_passengers);
+        if seat != 0 {
+            seat_map.set(seat, passenger_id.clone());
+            Self::persist(&env, &seat_map_key, &seat_map);
+        }
+
+        let mut index = Self::booking_index(&env, &to_flight);
+        let booking_seq = index.assign(&passenger_id);
+        Self::persist(&env, &DataKeyExt::BookingIndex(to_flight.clone()), &index);
+
+        pass_list.push_back(PassengerRecord {
+            passenger: passenger_id.clone(),
+            payer,
+            paid: fare,
+            details: old.details,
+            seat,
+            voucher: None,
+            checked_in: false,
+            settled: false,
+            compensation: 0,
+            booked_at: env.ledger().timestamp(),
+            passenger_type: old.passenger_type,
+            pay_token: None,
+            token_paid: fare,
+            fee_paid: 0,
+            booking_seq,
+            dispute: None,
+            promo: false,
+        });
+        Self::persist(&env, &pass_list_key, &pass_list);
+
+        Self::add_to_registry(&env, &passenger, &to_flight);
+        if old.passenger_type != PassengerType::Infant {
+            Self::add_tickets(&env, &to_flight, &passenger_id, 1);
+        }
+        Self::clear_hold(&env, &to_flight, &passenger);
+        to.passenger_count += 1;
+        Self::save_flight_state(&env, &to);
+        Self::announce_occupancy(&env, &to);
+    }
+
+    /// Schedule another departure of `source_id` under the same flight number,
+    /// copying its route, capacity, fare settings and cancellation cutoff. The
+    /// copy starts empty and open for booking.
+    pub fn duplicate_flight(
+        env: Env,
+        admin: Address,
+        source_id: BytesN<32>,
+        new_id: BytesN<32>,
+        new_depart_at: u64,
+        new_arrive_at: u64,
+    ) {
+        Self::require_admin(&env, &admin);
+
+        let source = Self::load_flight(&env, &source_id)
+            .unwrap_or_else(|| panic_with_error!(&env, FlyStellarError::FlightNotFound));
+        if new_arrive_at <= new_depart_at {
+            panic_with_error!(&env, FlyStellarError::InvalidInput);
+        }
+
+        Self::register_flight(
+            env.clone(),
+            admin,
+            new_id.clone(),
+            source.flight_number.clone(),
+            source.max_passengers,
+            source.distance,
+            source.src.clone(),
+            source.dest.clone(),
+            new_depart_at,
+        );
+        let mut copy = Self::load_flight(&env, &new_id).unwrap();
+        copy.fare = source.fare;
+        copy.escrow_amount = source.escrow_amount;
+        copy.overbook_bps = source.overbook_bps;
+        copy.promotional = source.promotional;
+        copy.charter_price = source.charter_price;
+        copy.arrive_at = Some(new_arrive_at);
+        Self::save_flight(&env, &copy);
+
+        let cutoff: Option<u64> = Self::fetch(&env, &DataKey::CancelCutoff(source_id));
+        if let Some(secs) = cutoff {
+            Self::persist(&env, &DataKey::CancelCutoff(new_id), &secs);
+        }
+    }
+
+    /// Move `move_passengers` from an oversold flight onto a new departure. The new
+    /// flight copies the source's route, capacity and fare settings; moved
+    /// bookings keep their records, seats and escrow.
+    pub fn split_flight(
+        env: Env,
+        admin: Address,
+        source_id: BytesN<32>,
+        new_id: BytesN<32>,
+        flight_number: Symbol,
+        depart_at: u64,
+        move_passengers: Vec<Address>,
+    ) {
+        Self::require_admin(&env, &admin);
+        Self::audit(
+            &env,
+            AuditAction::PassengersEdited,
+            &admin,
+            Some(source_id.clone()),
+            move_passengers.len() as i128,
+        );
+
+        let mut source = Self::load_flight(&env, &source_id)
+            .unwrap_or_else(|| panic_with_error!(&env, FlyStellarError::FlightNotFound));
+        if source.status != Symbol::new(&env, "booking") {
+            panic_with_error!(&env, FlyStellarError::InvalidStatus);
+        }
+        Self::require_no_pending_refunds(&env, &source_id);
+
+        Self::register_flight(
+            env.clone(),
+            admin,
+            new_id.clone(),
+            flight_number,
+            source.max_passengers,
+            source.distance,
+            source.src.clone(),
+            source.dest.clone(),
+            depart_at,
+        );
+        let mut split = Self::load_flight(&env, &new_id).unwrap();
+        split.fare = source.fare;
+        split.escrow_amount = source.escrow_amount;
+        split.overbook_bps = source.overbook_bps;
+        split.promotional = source.promotional;
+
+        let pass_list_key = DataKey::PassengerList(new_id.clone());
+        let mut pass_list: Vec<PassengerRecord> = Vec::new(&env);
+        let mut seat_map: Map<u32, PassengerId> = Map::new(&env);
+        let mut escrow: i128 = 0;
+        let mut index = Self::booking_index(&env, &new_id);
+        for passenger in move_passengers.iter() {
+            let holder = Self::holder(&env, &source_id, &passenger);
+            // Fails on anyone not booked on the source flight
+            let moved = Self::take_bookings(&env, &source_id, &mut source, &holder, None);
+            // Hashed ids stay hashed, now against the new flight
+            let id = match moved.get_unchecked(0).passenger {
+                PassengerId::Plain(_) => PassengerId::Plain(passenger.clone()),
+                PassengerId::Hashed(_) => PassengerId::Hashed(Self::passenger_hash(
+                    env.clone(),
+                    new_id.clone(),
+                    passenger.clone(),
+                )),
+            };
+            for mut rec in moved.iter() {
+                if holder.is(&rec.payer) {
+                    rec.payer = id.clone();
+                }
+                rec.passenger = id.clone();
+                rec.booking_seq = index.assign(&id);
+                if rec.passenger_type != PassengerType::Infant {
+                    split.passenger_count += 1;
+                    Self::add_tickets(&env, &new_id, &id, 1);
+                }
+                // The gate reads the dispute off the ticket, not the record
+                if rec.dispute.is_some() {
+                    Self::set_ticket_disputed(&env, &new_id, &id, true);
+                }
+                if rec.seat != 0 {
+                    seat_map.set(rec.seat, id.clone());
+                }
+                if !rec.settled {
+                    match &rec.pay_token {
+                        None => escrow += rec.paid,
+                        Some(token) => {
+                            Self::record_token_flows(&env, &source_id, token, -rec.token_paid, 0);
+                            Self::record_token_flows(&env, &new_id, token, rec.token_paid, 0);
+                        }
+                    }
+                }
+                pass_list.push_back(rec);
+            }
+            Self::add_to_registry(&env, &passenger, &new_id);
+        }
+        Self::persist(&env, &pass_list_key, &pass_list);
+        Self::persist(&env, &DataKey::SeatMap(new_id.clone()), &seat_map);
+        Self::persist(&env, &DataKeyExt::BookingIndex(new_id.clone()), &index);
+        Self::record_transfer(&env, &source_id, &new_id, escrow);
+        Self::save_flight(&env, &split);
+        Self::announce_occupancy(&env, &split);
+    }
+
+    /// Remove every booking `passenger` holds on a flight, freeing their seats,
+    /// promo quota, passenger count and registry entry. With `seats`, only that many of their
+    /// latest seated bookings go, and the registry entry stays while any remain.
+    /// Returns the removed records.
+    fn take_bookings(
+        env: &Env,
+        flight_id: &BytesN<32>,
+        flight: &mut FlightDetails,
+        holder: &Holder,
+        seats: Option<u32>,
+    ) -> Vec<PassengerRecord> {
+        // Get passenger list
+        let pass_list_key = DataKey::PassengerList(flight_id.clone());
+        let pass_list: Vec<PassengerRecord> =
+            Self::fetch(env, &pass_list_key).unwrap_or(Vec::new(env));
+        if pass_list.is_empty() {
+            panic_with_error!(env, FlyStellarError::NoPassengers);
+        }
+
+        let seat_map_key = DataKey::SeatMap(flight_id.clone());
+        let mut seat_map: Map<u32, PassengerId> =
+            Self::fetch(env, &seat_map_key).unwrap_or(Map::new(env));
+
+        // Seated bookings of the passenger to keep before removing the rest;
+        // giving up every seat takes the lap infants along with them
+        let mut keep = match seats {
+            Some(count) => {
+                let held = pass_list
+                    .iter()
+                    .filter(|rec| {
+                        holder.is(&rec.passenger) && rec.passenger_type != PassengerType::Infant
+                    })
+                    .count() as u32;
+                if held == 0 {
+                    panic_with_error!(env, FlyStellarError::PassengerNotFound);
+                }
+                if count == 0 || count > held {
+                    panic_with_error!(env, FlyStellarError::InvalidInput);
+                }
+                held - count
+            }
+            None => 0,
+        };
+        let seats = seats.filter(|_| keep > 0);
+
+        let mut new_list: Vec<PassengerRecord> = Vec::new(env);
+        let mut removed: Vec<PassengerRecord> = Vec::new(env);
+        let mut seated: u32 = 0;
+        let mut kept_any = false;
+        for rec in pass_list.iter() {
+            let mine = holder.is(&rec.passenger);
+            let take = mine
+                && match seats {
+                    None => true,
+                    Some(_) if rec.passenger_type == PassengerType::Infant => false,
+                    Some(_) if keep > 0 => {
+                        keep -= 1;
+                        false
+                    }
+                    Some(_) => true,
+                };
+            if take {
+                seat_map.remove(rec.seat);
+                if rec.passenger_type != PassengerType::Infant {
+                    seated += 1;
+                    Self::remove_tickets(env, flight_id, &rec.passenger, 1);
+                }
+                removed.push_back(rec);
+            } else {
+                kept_any |= mine;
+                new_list.push_back(rec);
+            }
+        }
+
+        if removed.is_empty() {
+            panic_with_error!(env, FlyStellarError::PassengerNotFound);
+        }
+        let promo_seats = removed.iter().filter(|rec| rec.promo).count() as i32;
+        if promo_seats > 0 {
+            Self::adjust_promo_quota(env, flight_id, promo_seats);
+        }
+
+        Self::persist(env, &pass_list_key, &new_list);
+        Self::persist(env, &seat_map_key, &seat_map);
+        let mut index = Self::booking_index(env, flight_id);
+        for rec in removed.iter() {
+            index.holders.remove(rec.booking_seq);
+        }
+        Self::persist(env, &DataKeyExt::BookingIndex(flight_id.clone()), &index);
+
+        flight.passenger_count = flight.passenger_count.saturating_sub(seated);
+        Self::save_flight_state(env, flight);
+        Self::announce_occupancy(env, flight);
+
+        let Some(passenger) = &holder.address else {
+            return removed;
+        };
+        let pass_reg_key = DataKey::PassengerRegistry(passenger.clone());
+        if !kept_any && env.storage().persistent().has(&pass_reg_key) {
+            let reg: Vec<BytesN<32>> = Self::fetch(env, &pass_reg_key).unwrap();
+            let mut new_reg: Vec<BytesN<32>> = Vec::new(env);
+            for id in reg.iter() {
+                if id != *flight_id {
+                    new_reg.push_back(id);
+                }
+            }
+            Self::persist(env, &pass_reg_key, &new_reg);
+        }
+
+        removed
+    }
+
+    /// Configure delay compensation tiers, ordered by strictly increasing `min_delay_secs`
+    pub fn set_delay_tiers(env: Env, admin: Address, tiers: Vec<DelayTier>) {
+        Self::require_admin(&env, &admin);
+        Self::audit(
+            &env,
+            AuditAction::FeeChanged,
+            &admin,
+            None,
+            tiers.len() as i128,
+        );
+
+        let mut last: Option<u64> = None;
+        for tier in tiers.iter() {
+            if tier.flat < 0 || tier.bps > BPS_DENOMINATOR {
+                panic_with_error!(&env, FlyStellarError::InvalidInput);
+            }
+            if let Some(prev) = last {
+                if tier.min_delay_secs <= prev {
+                    panic_with_error!(&env, FlyStellarError::InvalidInput);
+                }
+            }
+            last = Some(tier.min_delay_secs);
+        }
+        env.storage().instance().set(&DataKey::DelayTiers, &tiers);
+    }
+
+    /// Highest compensation tier reached by a delay, if any
+    fn delay_tier(env: &Env, delay_secs: u64) -> Option<DelayTier> {
+        let tiers: Vec<DelayTier> = env
+            .storage()
+            .instance()
+            .get(&DataKey::DelayTiers)
+            .unwrap_or(Vec::new(env));
+        let mut reached = None;
+        for tier in tiers.iter() {
+            if delay_secs >= tier.min_delay_secs {
+                reached = Some(tier);
+            }
+        }
+        reached
+    }
+
+    /// Seats the passenger list accounts for: every booking except infants,
+    /// plus any chartered seats
+    fn counted_seats(env: &Env, flight_id: &BytesN<32>) -> u32 {
+        let pass_list: Vec<PassengerRecord> =
+            Self::fetch(env, &DataKey::PassengerList(flight_id.clone())).unwrap_or(Vec::new(env));
+        let mut count = pass_list
+            .iter()
+            .filter(|rec| rec.passenger_type != PassengerType::Infant)
+            .count() as u32;
+        if let Some(charter) = Self::get_charter(env.clone(), flight_id.clone()) {
+            count += charter.seats;
+        }
+        count
+    }
+
+    /// Whether the flight's `passenger_count` matches its passenger list
+    pub fn verify_count(env: Env, admin: Address, flight_id: BytesN<32>) -> bool {
+        Self::require_admin(&env, &admin);
+
+        let flight = Self::get_flight(env.clone(), flight_id.clone());
+        flight.passenger_count == Self::counted_seats(&env, &flight_id)
+    }
+
+    /// Reset `passenger_count` from the passenger list; returns the new count
+    pub fn fix_count(env: Env, admin: Address, flight_id: BytesN<32>) -> u32 {
+        Self::require_admin(&env, &admin);
+        Self::audit(
+            &env,
+            AuditAction::CapacityChanged,
+            &admin,
+            Some(flight_id.clone()),
+            0,
+        );
+
+        let mut flight = Self::get_flight(env.clone(), flight_id.clone());
+        let count = Self::counted_seats(&env, &flight_id);
+        if flight.passenger_count != count {
+            debug_log!(
+                &env,
+                "🔧 Fixing passenger count {} -> {}",
+                flight.passenger_count,
+                count
+            );
+            flight.passenger_count = count;
+            Self::save_flight_state(&env, &flight);
+        }
+        count
+    }
+
+    /// Shrink a flight to `new_max` seats after an aircraft swap. Bookings past
+    /// the new size are removed latest first, at most `max_items` passengers per
+    /// call; each passenger gets a full refund plus `compensation` from the fee
+    /// pot, and loses all of their bookings on the flight together. Once
+    /// everyone fits, seats above `new_max` move to free lower seats. Returns
+    /// how many bookings are still over; call again until it returns 0.
+    pub fn reduce_capacity(
+        env: Env,
+        caller: Address,
+        flight_id: BytesN<32>,
+        new_max: u32,
+        compensation: i128,
+        max_items: u32,
+    ) -> u32 {
+        Self::require_admin_or_operator(&env, &caller);
+        Self::audit(
+            &env,
+            AuditAction::CapacityChanged,
+            &caller,
+            Some(flight_id.clone()),
+            new_max as i128,
+        );
+
+        let mut flight: FlightDetails = Self::load_flight(&env, &flight_id)
+            .unwrap_or_else(|| panic_with_error!(&env, FlyStellarError::FlightNotFound));
+        if flight.status != Symbol::new(&env, "booking") || flight.charter_holder.is_some() {
+            panic_with_error!(&env, FlyStellarError::InvalidStatus);
+        }
+        if new_max == 0 || new_max > flight.max_passengers || compensation < 0 {
+            panic_with_error!(&env, FlyStellarError::InvalidInput);
+        }
+        if new_max != flight.max_passengers {
+            flight.max_passengers = new_max;
+            flight.escrow_amount = new_max as i128 * flight.fare;
+            Self::save_flight(&env, &flight);
+            Self::announce_occupancy(&env, &flight);
+        }
+
+        let mut bumped: u32 = 0;
+        while flight.passenger_count > new_max && bumped < max_items {
+            let pass_list: Vec<PassengerRecord> =
+                Self::fetch(&env, &DataKey::PassengerList(flight_id.clone()))
+                    .unwrap_or(Vec::new(&env));
+            // Last in, first out
+            let passenger = match pass_list
+                .iter()
+                .filter(|rec| rec.passenger_type != PassengerType::Infant)
+                .max_by_key(|rec| rec.booking_seq)
+            {
+                Some(rec) => rec.passenger,
+                None => panic_with_error!(&env, FlyStellarError::NoPassengers),
+            };
+
+            let holder = Self::holder_of(&env, &flight_id, &passenger);
+            let removed = Self::take_bookings(&env, &flight_id, &mut flight, &holder, None);
+            let mut refund: i128 = 0;
+            let mut held: i128 = 0;
+            for rec in removed.iter() {
+                if !rec.settled {
+                    if !Self::record_paid_in_token(&env, &flight_id, &rec, rec.paid, 0) {
+                        held += rec.paid;
+                    }
+                    Self::refund_record(&env, &rec, rec.paid, &rec.payer);
+                    refund += rec.paid;
+                }
+            }
+            if compensation > 0 {
+                if compensation > Self::get_fee_pot(env.clone()) {
+                    panic_with_error!(&env, FlyStellarError::InvalidInput);
+                }
+                Self::add_to_fee_pot(&env, -compensation);
+                Self::pay_id(&env, &passenger, compensation);
+                Self::record_compensation(&env, &flight_id, compensation);
+            }
+            Self::record_flows(&env, &flight_id, 0, held, 0);
+            if let Some(passenger) = Self::public_address(&env, &passenger) {
+                PassengerBumped {
+                    flight_id: flight_id.clone(),
+                    passenger,
+                    refund,
+                    compensation,
+                }
+                .publish(&env);
+            }
+            bumped += 1;
+        }
+
+        let over = flight.passenger_count.saturating_sub(new_max);
+        if over == 0 {
+            Self::reseat_within(&env, &flight_id, new_max);
+        }
+        over
+    }
+
+    /// Move every booking seated above `max_seat` to the lowest free seat,
+    /// or to no seat when the cabin is full
+    fn reseat_within(env: &Env, flight_id: &BytesN<32>, max_seat: u32) {
+        let pass_list_key = DataKey::PassengerList(flight_id.clone());
+        let mut pass_list: Vec<PassengerRecord> =
+            Self::fetch(env, &pass_list_key).unwrap_or(Vec::new(env));
+        let seat_map_key = DataKey::SeatMap(flight_id.clone());
+        let mut seat_map: Map<u32, PassengerId> =
+            Self::fetch(env, &seat_map_key).unwrap_or(Map::new(env));
+
+        let mut moved = false;
+        for i in 0..pass_list.len() {
+            let mut rec = pass_list.get_unchecked(i);
+            if rec.seat <= max_seat {
+                continue;
+            }
+            seat_map.remove(rec.seat);
+            rec.seat = Self::first_free_seat(&seat_map, max_seat);
+            if rec.seat != 0 {
+                seat_map.set(rec.seat, rec.passenger.clone());
+            }
+            pass_list.set(i, rec);
+            moved = true;
+        }
+        if moved {
+            Self::persist(env, &pass_list_key, &pass_list);
+            Self::persist(env, &seat_map_key, &seat_map);
+        }
+    }
+
+    /// Move a flight to new departure and arrival times. Passengers already
+    /// booked may then cancel for a full refund until the grace window closes.
+    /// The new time is the new schedule: any delay is cleared and the flight
+    /// number moves to the new departure day, which must be free.
+    pub fn reschedule_flight(
+        env: Env,
+        caller: Address,
+        flight_id: BytesN<32>,
+        new_departure: u64,
+        new_arrival: u64,
+    ) {
+        Self::require_admin_or_operator(&env, &caller);
+        Self::audit(
+            &env,
+            AuditAction::FlightEdited,
+            &caller,
+            Some(flight_id.clone()),
+            new_departure as i128,
+        );
+
+        let mut flight: FlightDetails = Self::load_flight(&env, &flight_id)
+            .unwrap_or_else(|| panic_with_error!(&env, FlyStellarError::FlightNotFound));
+        if flight.status != Symbol::new(&env, "booking") {
+            panic_with_error!(&env, FlyStellarError::InvalidStatus);
+        }
+        let now = env.ledger().timestamp();
+        if flight.depart_at <= now {
+            panic_with_error!(&env, FlyStellarError::InvalidStatus);
+        }
+        if new_departure <= now || new_arrival <= new_departure {
+            panic_with_error!(&env, FlyStellarError::InvalidInput);
+        }
+
+        let number_key = DataKey::FlightNumber(flight.flight_number.clone());
+        let mut days: Map<u64, BytesN<32>> =
+            Self::fetch(&env, &number_key).unwrap_or(Map::new(&env));
+        let old_day = Self::number_day(&flight);
+        let new_day = new_departure / SECS_PER_DAY;
+        if new_day != old_day {
+            if days.get(new_day).is_some() {
+                panic_with_error!(&env, FlyStellarError::FlightAlreadyExists);
+            }
+            if days.get(old_day) == Some(flight_id.clone()) {
+                days.remove(old_day);
+            }
+            days.set(new_day, flight_id.clone());
+            Self::persist(&env, &number_key, &days);
+        }
+
+        let event = FlightRescheduled {
+            flight_id: flight_id.clone(),
+            old_departure: flight.depart_at,
+            new_departure,
+            old_arrival: flight.arrive_at,
+            new_arrival,
+        };
+        flight.depart_at = new_departure;
+        flight.delay_secs = 0;
+        flight.arrive_at = Some(new_arrival);
+        Self::save_flight(&env, &flight);
+
+        let until = now.saturating_add(Self::get_reschedule_grace(env.clone()));
+        Self::persist(&env, &DataKey::RescheduleWindow(flight_id), &(now, until));
+        event.publish(&env);
+    }
+
+    /// How long after a reschedule its passengers may cancel for free
+    pub fn set_reschedule_grace(env: Env, admin: Address, secs: u64) {
+        Self::require_admin(&env, &admin);
+        Self::audit(&env, AuditAction::ConfigChanged, &admin, None, secs as i128);
+
+        env.storage()
+            .instance()
+            .set(&DataKey::RescheduleGraceSecs, &secs);
+    }
+
+    pub fn get_reschedule_grace(env: Env) -> u64 {
+        env.storage()
+            .instance()
+            .get(&DataKey::RescheduleGraceSecs)
+            .unwrap_or(DEFAULT_RESCHEDULE_GRACE_SECS)
+    }
+
+    /// When the flight was last rescheduled, if its free-cancellation window
+    /// is still open and it has not departed
+    fn open_reschedule_window(env: &Env, flight: &FlightDetails) -> Option<u64> {
+        let (at, until): (u64, u64) =
+            Self::fetch(env, &DataKey::RescheduleWindow(flight.id.clone()))?;
+        if env.ledger().timestamp() >= until || flight.status == Symbol::new(env, "takeoff") {
+            return None;
+        }
+        Some(at)
+    }
+
+    /// Push a flight's departure back to `new_departure`. Once the accumulated delay
+    /// reaches a compensation tier, every booked passenger is credited up to that
+    /// tier's amount (never stacking across successive delays), claimable through
+    /// `claim_refund` without cancelling. Compensation is paid out of the fee pot.
+    pub fn mark_delayed(env: Env, admin: Address, flight_id: BytesN<32>, new_departure: u64) {
+        Self::require_admin(&env, &admin);
+        Self::audit(
+            &env,
+            AuditAction::FlightStatusChanged,
+            &admin,
+            Some(flight_id.clone()),
+            new_departure as i128,
+        );
+
+        let mut flight: FlightDetails = Self::load_flight(&env, &flight_id)
+            .unwrap_or_else(|| panic_with_error!(&env, FlyStellarError::FlightNotFound));
+        if flight.status != Symbol::new(&env, "booking") {
+            panic_with_error!(&env, FlyStellarError::InvalidStatus);
+        }
+        if new_departure <= flight.depart_at {
+            panic_with_error!(&env, FlyStellarError::InvalidInput);
+        }
+
+        flight.delay_secs += new_departure - flight.depart_at;
+        flight.depart_at = new_departure;
+        Self::save_flight(&env, &flight);
+
+        let tier = match Self::delay_tier(&env, flight.delay_secs) {
+            Some(tier) => tier,
+            None => return,
+        };
+
+        let pass_list_key = DataKey::PassengerList(flight_id.clone());
+        let mut pass_list: Vec<PassengerRecord> =
+            Self::fetch(&env, &pass_list_key).unwrap_or(Vec::new(&env));
+
+        let mut credited: i128 = 0;
+        for i in 0..pass_list.len() {
+            let mut rec = pass_list.get_unchecked(i);
+            let owed = tier.flat + rec.paid * tier.bps as i128 / BPS_DENOMINATOR as i128;
+            if owed <= rec.compensation {
+                continue;
+            }
+            let top_up = owed - rec.compensation;
+            Self::credit_id(&env, &rec.passenger, top_up);
+            credited += top_up;
+            rec.compensation = owed;
+            pass_list.set(i, rec);
+        }
+
+        if credited > Self::get_fee_pot(env.clone()) {
+            panic_with_error!(&env, FlyStellarError::InvalidInput);
+        }
+        Self::add_to_fee_pot(&env, -credited);
+        Self::persist(&env, &pass_list_key, &pass_list);
+        Self::record_compensation(&env, &flight_id, credited);
+    }
+
+    /// Top up the fee pot that funds passenger compensation
+    pub fn fund_fee_pot(env: Env, from: Address, amount: i128) {
+        Self::require_initialized(&env);
+        from.require_auth();
+
+        if amount <= 0 {
+            panic_with_error!(&env, FlyStellarError::InvalidInput);
+        }
+        Self::token_client(&env).transfer(&from, env.current_contract_address(), &amount);
+        Self::add_to_fee_pot(&env, amount);
+    }
+
+    fn credit_refund(env: &Env, passenger: &Address, amount: i128) {
+        let key = DataKey::PendingRefund(passenger.clone());
+        let pending: i128 = Self::fetch(env, &key).unwrap_or(0);
+        Self::persist(env, &key, &(pending + amount));
+        Self::add_liabilities(env, amount);
+
+        if pending == 0 {
+            let mut list = Self::pending_refund_list(env);
+            list.push_back(passenger.clone());
+            Self::persist(env, &DataKeyExt::PendingRefundList, &list);
+        }
+    }
+
+    /// `credit_refund` for a stored id
+    fn credit_id(env: &Env, to: &PassengerId, amount: i128) {
+        match to {
+            PassengerId::Plain(address) => Self::credit_refund(env, address, amount),
+            PassengerId::Hashed(hash) => Self::credit_private(env, hash, amount),
+        }
+    }
+
+    fn credit_private(env: &Env, hash: &BytesN<32>, amount: i128) {
+        let key = DataKeyExt::PrivateRefund(hash.clone());
+        let pending: i128 = Self::fetch(env, &key).unwrap_or(0);
+        Self::persist(env, &key, &(pending + amount));
+        Self::add_liabilities(env, amount);
+    }
+
+    fn pending_refund_list(env: &Env) -> Vec<Address> {
+        Self::fetch(env, &DataKeyExt::PendingRefundList).unwrap_or(Vec::new(env))
+    }
+
+    /// Refund credit `passenger` can withdraw with `claim_refund`
+    pub fn get_pending_refund(env: Env, passenger: Address) -> i128 {
+        Self::fetch(&env, &DataKey::PendingRefund(passenger)).unwrap_or(0)
+    }
+
+    /// Unclaimed refund credits, oldest first, one page of at most
+    /// `MAX_PAGE_LIMIT` passengers at a time alongside the total number waiting
+    pub fn list_pending_refunds(
+        env: Env,
+        admin: Address,
+        start: u32,
+        limit: u32,
+    ) -> (Vec<(Address, i128)>, u32) {
+        Self::require_admin(&env, &admin);
+
+        let list = Self::pending_refund_list(&env);
+        let total = list.len();
+        let end = start.saturating_add(limit.min(MAX_PAGE_LIMIT)).min(total);
+        let mut out = Vec::new(&env);
+        for i in start.min(end)..end {
+            let passenger = list.get_unchecked(i);
+            let amount = Self::get_pending_refund(env.clone(), passenger.clone());
+            out.push_back((passenger, amount));
+        }
+        (out, total)
+    }
+
+    /// Withdraw everything credited to `passenger` (e.g. delay compensation)
+    pub fn claim_refund(env: Env, passenger: Address) -> i128 {
+        Self::require_initialized(&env);
+        passenger.require_auth();
+
+        let key = DataKey::PendingRefund(passenger.clone());
+        let pending: i128 = Self::fetch(&env, &key).unwrap_or(0);
+        if pending == 0 {
+            panic_with_error!(&env, FlyStellarError::NoPendingRefund);
+        }
+        env.storage().persistent().remove(&key);
+        Self::add_liabilities(&env, -pending);
+        let mut list = Self::pending_refund_list(&env);
+        if let Some(i) = list.first_index_of(&passenger) {
+            list.remove(i);
+            Self::persist(&env, &DataKeyExt::PendingRefundList, &list);
+        }
+        Self::token_client(&env).transfer(&env.current_contract_address(), &passenger, &pending);
+        pending
+    }
+
+    /// Refund credit owed to `passenger` on a booking made in privacy mode.
+    /// `flight_id` is the flight the booking was paid on.
+    pub fn get_private_refund(env: Env, flight_id: BytesN<32>, passenger: Address) -> i128 {
+        let hash = Self::passenger_hash(env.clone(), flight_id, passenger);
+        Self::fetch(&env, &DataKeyExt::PrivateRefund(hash)).unwrap_or(0)
+    }
+
+    /// `claim_refund` for credit owed under a privacy-mode hash, see
+    /// `get_private_refund`
+    pub fn claim_private_refund(env: Env, flight_id: BytesN<32>, passenger: Address) -> i128 {
+        Self::require_initialized(&env);
+        passenger.require_auth();
+
+        let hash = Self::passenger_hash(env.clone(), flight_id, passenger.clone());
+        let key = DataKeyExt::PrivateRefund(hash);
+        let pending: i128 = Self::fetch(&env, &key).unwrap_or(0);
+        if pending == 0 {
+            panic_with_error!(&env, FlyStellarError::NoPendingRefund);
+        }
+        env.storage().persistent().remove(&key);
+        Self::add_liabilities(&env, -pending);
+        Self::token_client(&env).transfer(&env.current_contract_address(), &passenger, &pending);
+        pending
+    }
+
+    /// Check in for a booked flight before departure
+    pub fn check_in(env: Env, flight_id: BytesN<32>, passenger: Address) {
+        Self::require_initialized(&env);
+        passenger.require_auth();
+
+        let flight: FlightDetails = Self::load_flight(&env, &flight_id).expect("Flight not found");
+        if flight.status != Symbol::new(&env, "booking") {
+            panic_with_error!(&env, FlyStellarError::InvalidStatus);
+        }
+
+        let holder = Self::holder(&env, &flight_id, &passenger);
+        let pass_list_key = DataKey::PassengerList(flight_id);
+        let pass_list: Vec<PassengerRecord> =
+            Self::fetch(&env, &pass_list_key).unwrap_or(Vec::new(&env));
+
+        let mut found = false;
+        let mut new_list: Vec<PassengerRecord> = Vec::new(&env);
+        for mut rec in pass_list.iter() {
+            if holder.is(&rec.passenger) {
+                if rec.dispute.is_some() {
+                    panic_with_error!(&env, FlyStellarError::TicketDisputed);
+                }
+                found = true;
+                rec.checked_in = true;
+            }
+            new_list.push_back(rec);
+        }
+
+        if !found {
+            panic_with_error!(&env, FlyStellarError::PassengerNotFound);
+        }
+        Self::persist(&env, &pass_list_key, &new_list);
+    }
+
+    /// Gate check: true while the flight is boarding-eligible and the passenger
+    /// holds an undisputed seat that has not boarded yet. Reads two entries,
+    /// never the passenger list.
+    pub fn verify_ticket(env: Env, flight_id: BytesN<32>, passenger: Address) -> bool {
+        let Some(state) =
+            Self::fetch::<FlightState>(&env, &DataKey::FlightState(flight_id.clone()))
+        else {
+            return false;
+        };
+        if state.status != Symbol::new(&env, "booking") {
+            return false;
+        }
+        let holder = Self::holder(&env, &flight_id, &passenger);
+        Self::fetch::<Ticket>(&env, &Self::ticket_key(&env, &flight_id, &holder))
+            .map(|ticket| !ticket.disputed && ticket.boarded < ticket.seats)
+            .unwrap_or(false)
+    }
+
+    /// Record one of the passenger's seats as boarded, checking in the booking
+    /// it belongs to. Once the last seat boards, lap infants are checked in too.
+    /// Admins or operators only.
+    pub fn mark_boarded(env: Env, caller: Address, flight_id: BytesN<32>, passenger: Address) {
+        Self::require_admin_or_operator(&env, &caller);
+        Self::audit(
+            &env,
+            AuditAction::PassengersEdited,
+            &caller,
+            Some(flight_id.clone()),
+            0,
+        );
+
+        let state: FlightState = Self::fetch(&env, &DataKey::FlightState(flight_id.clone()))
+            .unwrap_or_else(|| panic_with_error!(&env, FlyStellarError::FlightNotFound));
+        if state.status != Symbol::new(&env, "booking") {
+            panic_with_error!(&env, FlyStellarError::InvalidStatus);
+        }
+
+        let holder = Self::holder(&env, &flight_id, &passenger);
+        let key = Self::ticket_key(&env, &flight_id, &holder);
+        let mut ticket: Ticket = Self::fetch(&env, &key)
+            .unwrap_or_else(|| panic_with_error!(&env, FlyStellarError::PassengerNotFound));
+        if ticket.disputed {
+            panic_with_error!(&env, FlyStellarError::TicketDisputed);
+        }
+        if ticket.boarded >= ticket.seats {
+            panic_with_error!(&env, FlyStellarError::AlreadyBoarded);
+        }
+        ticket.boarded += 1;
+        Self::persist(&env, &key, &ticket);
+
+        let all_aboard = ticket.boarded == ticket.seats;
+        let pass_list_key = DataKey::PassengerList(flight_id);
+        let mut pass_list: Vec<PassengerRecord> =
+            Self::fetch(&env, &pass_list_key).unwrap_or(Vec::new(&env));
+        let mut seat_marked = false;
+        for i in 0..pass_list.len() {
+            let mut rec = pass_list.get_unchecked(i);
+            if !holder.is(&rec.passenger) || rec.checked_in {
+                continue;
+            }
+            let infant = rec.passenger_type == PassengerType::Infant;
+            if (infant && all_aboard) || (!infant && !seat_marked) {
+                seat_marked |= !infant;
+                rec.checked_in = true;
+                pass_list.set(i, rec);
+            }
+        }
+        Self::persist(&env, &pass_list_key, &pass_list);
+    }
+
+    /// Customer-service correction of what a booking cost. The difference is
+    /// refunded to, or charged from, whoever paid for the booking; charging
+    /// needs the payer's authorization. Applies to the passenger's first booking
+    /// on the flight that has not been refunded yet.
+    pub fn adjust_paid(
+        env: Env,
+        admin: Address,
+        flight_id: BytesN<32>,
+        passenger: Address,
+        new_paid: i128,
+    ) {
+        Self::require_admin(&env, &admin);
+        Self::audit(
+            &env,
+            AuditAction::PassengersEdited,
+            &admin,
+            Some(flight_id.clone()),
+            new_paid,
+        );
+
+        if new_paid < 0 {
+            panic_with_error!(&env, FlyStellarError::InvalidInput);
+        }
+        let flight = Self::load_flight(&env, &flight_id)
+            .unwrap_or_else(|| panic_with_error!(&env, FlyStellarError::FlightNotFound));
+        if flight.status != Symbol::new(&env, "booking") {
+            panic_with_error!(&env, FlyStellarError::InvalidStatus);
+        }
+        let pass_list_key = DataKey::PassengerList(flight_id.clone());
+        let mut pass_list: Vec<PassengerRecord> =
+            Self::fetch(&env, &pass_list_key).unwrap_or(Vec::new(&env));
+        let holder = Self::holder(&env, &flight_id, &passenger);
+        let idx = pass_list
+            .iter()
+            .position(|rec| holder.is(&rec.passenger) && !rec.settled)
+            .unwrap_or_else(|| panic_with_error!(&env, FlyStellarError::PassengerNotFound))
+            as u32;
+
+        let mut rec = pass_list.get_unchecked(idx);
+        // Adjustments move base tokens only
+        if rec.pay_token.is_some() {
+            panic_with_error!(&env, FlyStellarError::InvalidInput);
+        }
+        // A charge is collected, a give-back is a refund like any other
+        let delta = new_paid - rec.paid;
+        let token = Self::token_client(&env);
+        if delta > 0 {
+            // A hashed payer can only be charged when they are the passenger
+            let payer = match rec.payer.address() {
+                Some(payer) => payer,
+                None if holder.is(&rec.payer) => passenger,
+                None => panic_with_error!(&env, FlyStellarError::Unauthorized),
+            };
+            payer.require_auth();
+            token.transfer(&payer, env.current_contract_address(), &delta);
+            Self::record_flows(&env, &flight_id, delta, 0, 0);
+        } else if delta < 0 {
+            Self::record_flows(&env, &flight_id, 0, -delta, 0);
+            Self::pay_id(&env, &rec.payer, -delta);
+        }
+        rec.paid = new_paid;
+        rec.token_paid = new_paid;
+        pass_list.set(idx, rec);
+        Self::persist(&env, &pass_list_key, &pass_list);
+    }
+
+    /// Correct the details on a booking before check-in
+    pub fn update_ticket_details(
+        env: Env,
+        flight_id: BytesN<32>,
+        passenger: Address,
+        new_details: Symbol,
+    ) {
+        Self::require_initialized(&env);
+        passenger.require_auth();
+        Self::require_valid_details(&env, &new_details);
+
+        let flight: FlightDetails = Self::load_flight(&env, &flight_id)
+            .unwrap_or_else(|| panic_with_error!(&env, FlyStellarError::FlightNotFound));
+        if flight.status != Symbol::new(&env, "booking") {
+            panic_with_error!(&env, FlyStellarError::InvalidStatus);
+        }
+
+        let pass_list_key = DataKey::PassengerList(flight_id.clone());
+        let mut pass_list: Vec<PassengerRecord> =
+            Self::fetch(&env, &pass_list_key).unwrap_or(Vec::new(&env));
+
+        let holder = Self::holder(&env, &flight_id, &passenger);
+        let mut found = false;
+        for i in 0..pass_list.len() {
+            let mut rec = pass_list.get_unchecked(i);
+            if !holder.is(&rec.passenger) {
+                continue;
+            }
+            if rec.checked_in {
+                panic_with_error!(&env, FlyStellarError::InvalidStatus);
+            }
+            found = true;
+            rec.details = new_details.clone();
+            pass_list.set(i, rec);
+        }
+
+        if !found {
+            panic_with_error!(&env, FlyStellarError::PassengerNotFound);
+        }
+        Self::persist(&env, &pass_list_key, &pass_list);
+
+        if !Self::get_privacy_mode(env.clone()) {
+            TicketDetailsUpdated {
+                flight_id,
+                passenger,
+                details: new_details,
+            }
+            .publish(&env);
+        }
+    }
+
+    /// Settle up to `max_items` passenger records of a departed flight, splitting the
+    /// fares of passengers who never checked in between the airline payout and the
+    /// admin fee pot. Returns the number of records still left to process.
+    pub fn sweep_no_shows(env: Env, admin: Address, flight_id: BytesN<32>, max_items: u32) -> u32 {
+        Self::require_admin(&env, &admin);
+        Self::audit(
+            &env,
+            AuditAction::FundsMoved,
+            &admin,
+            Some(flight_id.clone()),
+            max_items as i128,
+        );
+
+        if max_items == 0 {
+            panic_with_error!(&env, FlyStellarError::InvalidInput);
+        }
+
+        let flight: FlightDetails = Self::load_flight(&env, &flight_id)
+            .unwrap_or_else(|| panic_with_error!(&env, FlyStellarError::FlightNotFound));
+        if flight.status != Symbol::new(&env, "takeoff") {
+            panic_with_error!(&env, FlyStellarError::InvalidStatus);
+        }
+
+        let airline_bps: u32 = env
+            .storage()
+            .instance()
+            .get(&DataKey::NoShowShareBps)
+            .unwrap_or(DEFAULT_NO_SHOW_AIRLINE_BPS);
+
+        let pass_list_key = DataKey::PassengerList(flight_id.clone());
+        let mut pass_list: Vec<PassengerRecord> =
+            Self::fetch(&env, &pass_list_key).unwrap_or(Vec::new(&env));
+
+        let cursor_key = DataKey::SweepCursor(flight_id.clone());
+        let cursor: u32 = Self::fetch(&env, &cursor_key).unwrap_or(0);
+        let end = cursor.saturating_add(max_items).min(pass_list.len());
+
+        let mut airline_total: i128 = 0;
+        let mut fee_total: i128 = 0;
+        let mut escrow_fees: i128 = 0;
+        for i in cursor..end {
+            let mut rec = pass_list.get_unchecked(i);
+            // Settled trips move out of the passenger's active registry
+            Self::archive_trip(&env, &rec.passenger, &flight_id);
+            if rec.checked_in || rec.settled {
+                continue;
+            }
+            let airline_share = rec.paid * airline_bps as i128 / BPS_DENOMINATOR as i128;
+            airline_total += airline_share;
+            fee_total += rec.paid - airline_share;
+            // The airline's share stays in escrow until `settle_flight`
+            if !Self::record_paid_in_token(&env, &flight_id, &rec, 0, rec.paid - airline_share) {
+                escrow_fees += rec.paid - airline_share;
+            }
+            rec.settled = true;
+            pass_list.set(i, rec);
+        }
+
+        Self::persist(&env, &pass_list_key, &pass_list);
+        Self::persist(&env, &cursor_key, &end);
+
+        if airline_total > 0 {
+            let payout_key = DataKey::AirlinePayout(flight_id.clone());
+            let payout: i128 = Self::fetch(&env, &payout_key).unwrap_or(0);
+            Self::persist(&env, &payout_key, &(payout + airline_total));
+        }
+        if escrow_fees > 0 {
+            Self::add_to_fee_pot(&env, escrow_fees);
+            Self::record_flows(&env, &flight_id, 0, 0, escrow_fees);
+        }
+        if airline_total > 0 || fee_total > 0 {
+            let key = DataKeyExt::ReportSupplements(flight_id.clone());
+            let mut supplements: Vec<NoShowSettlement> =
+                Self::fetch(&env, &key).unwrap_or(Vec::new(&env));
+            supplements.push_back(NoShowSettlement {
+                airline: airline_total,
+                fees: fee_total,
+                settled_at: env.ledger().timestamp(),
+            });
+            Self::persist(&env, &key, &supplements);
+        }
+
+        pass_list.len() - end
+    }
+
+    /// Compare what a flight's books say it took in for its bookings,
+    /// `collected - refunded - fees - transferred` (released escrow included), with the `paid`
+    /// of its base-token bookings not yet refunded or swept, the charter and the
+    /// airline's share of swept no-shows. Publishes `EscrowDrift` when they
+    /// differ and returns `(held, computed)`. Once `settle_flight` has run its
+    /// commission counts as a fee, so only unsettled flights should match.
+    pub fn reconcile_flight(env: Env, admin: Address, flight_id: BytesN<32>) -> (i128, i128) {
+        Self::require_admin(&env, &admin);
+        Self::audit(
+            &env,
+            AuditAction::FundsMoved,
+            &admin,
+            Some(flight_id.clone()),
+            0,
+        );
+
+        let balance = Self::get_flight_balance(env.clone(), flight_id.clone());
+        let held = balance.collected - balance.refunded - balance.fees - balance.transferred;
+        let pass_list: Vec<PassengerRecord> =
+            Self::fetch(&env, &DataKey::PassengerList(flight_id.clone())).unwrap_or(Vec::new(&env));
+        let mut computed = Self::get_airline_payout(env.clone(), flight_id.clone());
+        for rec in pass_list.iter() {
+            if !rec.settled && rec.pay_token.is_none() {
+                computed += rec.paid;
+            }
+        }
+        if let Some(charter) = Self::get_charter(env.clone(), flight_id.clone()) {
+            computed += charter.paid;
+        }
+
+        if computed != held {
+            EscrowDrift {
+                flight_id,
+                held,
+                computed,
+            }
+            .publish(&env);
+        }
+        (held, computed)
+    }
+
+    /// Refund up to `max_items` bookings of a cancelled flight in full to whoever
+    /// paid for them, resuming where the previous batch stopped. Anyone may drive
+    /// this; it returns how many records are still left to process.
+    pub fn refund_batch(env: Env, flight_id: BytesN<32>, max_items: u32) -> u32 {
+        Self::require_initialized(&env);
+
+        if max_items == 0 {
+            panic_with_error!(&env, FlyStellarError::InvalidInput);
+        }
+        let flight: FlightDetails = Self::load_flight(&env, &flight_id)
+            .unwrap_or_else(|| panic_with_error!(&env, FlyStellarError::FlightNotFound));
+        if flight.status != Symbol::new(&env, "cancelled") {
+            panic_with_error!(&env, FlyStellarError::InvalidStatus);
+        }
+
+        let pass_list_key = DataKey::PassengerList(flight_id.clone());
+        let mut pass_list: Vec<PassengerRecord> =
+            Self::fetch(&env, &pass_list_key).unwrap_or(Vec::new(&env));
+
+        let cursor_key = DataKey::RefundCursor(flight_id.clone());
+        let cursor: u32 = Self::fetch(&env, &cursor_key).unwrap_or(0);
+        let end = cursor.saturating_add(max_items).min(pass_list.len());
+
+        // Book the whole batch against escrow first so a drawn-down flight
+        // fails with `InsufficientEscrow` before any money moves
+        let mut refund_total: i128 = 0;
+        for i in cursor..end {
+            let rec = pass_list.get_unchecked(i);
+            if !rec.settled && !Self::record_paid_in_token(&env, &flight_id, &rec, rec.paid, 0) {
+                refund_total += rec.paid;
+            }
+        }
+        Self::record_flows(&env, &flight_id, 0, refund_total, 0);
+
+        // `settled` marks a record as paid out, so no batch can refund it twice
+        for i in cursor..end {
+            let mut rec = pass_list.get_unchecked(i);
+            if rec.settled {
+                continue;
+            }
+            Self::refund_record(&env, &rec, rec.paid, &rec.payer);
+            let passenger = Self::public_address(&env, &rec.passenger);
+            let payer = Self::public_address(&env, &rec.payer);
+            if let (Some(passenger), Some(payer)) = (passenger, payer) {
+                BookingRefunded {
+                    flight_id: flight_id.clone(),
+                    passenger,
+                    payer,
+                    amount: rec.paid,
+                }
+                .publish(&env);
+            }
+            rec.settled = true;
+            pass_list.set(i, rec);
+        }
+
+        Self::persist(&env, &pass_list_key, &pass_list);
+        Self::persist(&env, &cursor_key, &end);
+
+        pass_list.len() - end
+    }
+
+    /// Block anything that would reorder or settle a flight's bookings while a
+    /// `refund_batch` run over it is only partly done
+    fn require_no_pending_refunds(env: &Env, flight_id: &BytesN<32>) {
+        if let Err(err) = Self::check_no_pending_refunds(env, flight_id) {
+            panic_with_error!(env, err);
+        }
+    }
+
+    fn check_no_pending_refunds(env: &Env, flight_id: &BytesN<32>) -> Result<(), FlyStellarError> {
+        let cursor: Option<u32> = Self::fetch(env, &DataKey::RefundCursor(flight_id.clone()));
+        if let Some(cursor) = cursor {
+            let pass_list: Vec<PassengerRecord> =
+                Self::fetch(env, &DataKey::PassengerList(flight_id.clone()))
+                    .unwrap_or(Vec::new(env));
+            if cursor < pass_list.len() {
+                return Err(FlyStellarError::RefundsPending);
+            }
+        }
+        Ok(())
+    }
+
+    /// Escrow accounting for a flight: what was collected, refunded, taken as fees,
+    /// and what the contract still holds for it
+    pub fn get_flight_balance(env: Env, flight_id: BytesN<32>) -> FlightBalance {
+        if !env
+            .storage()
+            .persistent()
+            .has(&DataKey::Flight(flight_id.clone()))
+        {
+            panic_with_error!(&env, FlyStellarError::FlightNotFound);
+        }
+        Self::fetch(&env, &DataKey::Balance(flight_id)).unwrap_or_default()
+    }
+
+    /// Number of live bookings made in `[start, end]` and the revenue they brought in
+    pub fn sales_in_window(env: Env, flight_id: BytesN<32>, start: u64, end: u64) -> (u32, i128) {
+        if start > end {
+            panic_with_error!(&env, FlyStellarError::InvalidInput);
+        }
+        if !env
+            .storage()
+            .persistent()
+            .has(&DataKey::Flight(flight_id.clone()))
+        {
+            panic_with_error!(&env, FlyStellarError::FlightNotFound);
+        }
+
+        let pass_list: Vec<PassengerRecord> =
+            Self::fetch(&env, &DataKey::PassengerList(flight_id)).unwrap_or(Vec::new(&env));
+
+        let mut count: u32 = 0;
+        let mut revenue: i128 = 0;
+        for rec in pass_list.iter() {
+            if rec.booked_at >= start && rec.booked_at <= end {
+                count += 1;
+                revenue += rec.paid;
+            }
+        }
+        (count, revenue)
+    }
+
+    /// Move a departed flight from the passenger's active registry to their
+    /// history. Registries written before entries were deduplicated may list it
+    /// more than once; every copy goes, and the history gets it once. A hashed
+    /// record names no registry; `archive_registry` tidies those up.
+    fn archive_trip(env: &Env, passenger: &PassengerId, flight_id: &BytesN<32>) {
+        let Some(passenger) = passenger.address() else {
+            return;
+        };
+        let pass_reg_key = DataKey::PassengerRegistry(passenger.clone());
+        let mut reg: Vec<BytesN<32>> = Self::fetch(env, &pass_reg_key).unwrap_or(Vec::new(env));
+        if !reg.contains(flight_id) {
+            return;
+        }
+        while let Some(i) = reg.first_index_of(flight_id) {
+            reg.remove(i);
+        }
+        Self::persist(env, &pass_reg_key, &reg);
+        Self::push_history(env, &passenger, flight_id);
+    }
+
+    fn push_history(env: &Env, passenger: &Address, flight_id: &BytesN<32>) {
+        let count_key = DataKey::HistoryCount(passenger.clone());
+        let count: u32 = Self::fetch(env, &count_key).unwrap_or(0);
+
+        let bucket_key = DataKey::PassengerHistory(passenger.clone(), count / HISTORY_BUCKET_SIZE);
+        let mut bucket: Vec<BytesN<32>> = Self::fetch(env, &bucket_key).unwrap_or(Vec::new(env));
+        bucket.push_back(flight_id.clone());
+        Self::persist(env, &bucket_key, &bucket);
+        Self::persist(env, &count_key, &(count + 1));
+    }
+
+    /// Maintenance for registries that grew before archiving existed: moves up to
+    /// `max_items` departed flights into the passenger's history. Returns how many
+    /// were moved; call again until it returns 0.
+    pub fn archive_registry(env: Env, admin: Address, passenger: Address, max_items: u32) -> u32 {
+        Self::require_admin(&env, &admin);
+        Self::audit(
+            &env,
+            AuditAction::ConfigChanged,
+            &admin,
+            None,
+            max_items as i128,
+        );
+
+        let pass_reg_key = DataKey::PassengerRegistry(passenger.clone());
+        let reg: Vec<BytesN<32>> = Self::fetch(&env, &pass_reg_key).unwrap_or(Vec::new(&env));
+
+        let takeoff = Symbol::new(&env, "takeoff");
+        let mut active: Vec<BytesN<32>> = Vec::new(&env);
+        let mut moved: u32 = 0;
+        for id in reg.iter() {
+            let departed = moved < max_items
+                && Self::load_flight(&env, &id).is_some_and(|f| f.status == takeoff);
+            if departed {
+                Self::push_history(&env, &passenger, &id);
+                moved += 1;
+            } else {
+                active.push_back(id);
+            }
+        }
+
+        if moved > 0 {
+            Self::persist(&env, &pass_reg_key, &active);
+        }
+        moved
+    }
+
+    /// Past trips of a passenger, oldest first
+    pub fn get_history(env: Env, passenger: Address, page: u32, limit: u32) -> Vec<BytesN<32>> {
+        Self::require_private_read(&env, &passenger);
+        let count: u32 = Self::fetch(&env, &DataKey::HistoryCount(passenger.clone())).unwrap_or(0);
+
+        let limit = limit.min(MAX_PAGE_LIMIT);
+        let start = page.saturating_mul(limit);
+        let end = start.saturating_add(limit).min(count);
+
+        let mut out: Vec<BytesN<32>> = Vec::new(&env);
+        let mut bucket: Vec<BytesN<32>> = Vec::new(&env);
+        let mut bucket_idx = u32::MAX;
+        for i in start..end {
+            if i / HISTORY_BUCKET_SIZE != bucket_idx {
+                bucket_idx = i / HISTORY_BUCKET_SIZE;
+                bucket = Self::fetch(
+                    &env,
+                    &DataKey::PassengerHistory(passenger.clone(), bucket_idx),
+                )
+                .unwrap_or(Vec::new(&env));
+            }
+            out.push_back(bucket.get_unchecked(i % HISTORY_BUCKET_SIZE));
+        }
+        out
+    }
+
+    /// Settlement report written when the flight departed
+    pub fn get_report(env: Env, flight_id: BytesN<32>) -> Option<RevenueReport> {
+        Self::fetch(&env, &DataKeyExt::Report(flight_id))
+    }
+
+    /// No-show settlements made after the report was written, oldest first
+    pub fn get_report_supplements(env: Env, flight_id: BytesN<32>) -> Vec<NoShowSettlement> {
+        Self::fetch(&env, &DataKeyExt::ReportSupplements(flight_id)).unwrap_or(Vec::new(&env))
+    }
+
+    /// Snapshot a departing flight's accounting; a report is never rewritten
+    fn write_report(env: &Env, flight_id: &BytesN<32>) {
+        let key = DataKeyExt::Report(flight_id.clone());
+        if env.storage().persistent().has(&key) {
+            return;
+        }
+        let balance: FlightBalance =
+            Self::fetch(env, &DataKey::Balance(flight_id.clone())).unwrap_or_default();
+        let report = RevenueReport {
+            gross: balance.collected - balance.transferred,
+            refunded: balance.refunded,
+            fees_retained: balance.fees,
+            compensation: balance.compensation,
+            net_to_airline: balance.net_held,
+            generated_at: env.ledger().timestamp(),
+        };
+        Self::persist(env, &key, &report);
+    }
+
+    /// Lower the platform commission; raising it goes through
+    /// `queue_config_change`
+    pub fn set_platform_commission(env: Env, admin: Address, bps: u32) {
+        Self::require_admin(&env, &admin);
+        Self::audit(&env, AuditAction::FeeChanged, &admin, None, bps as i128);
+
+        Self::apply_now(&env, ConfigChange::PlatformCommission(bps));
+    }
+
+    pub fn get_platform_commission(env: Env) -> u32 {
+        env.storage()
+            .instance()
+            .get(&DataKeyExt::PlatformCommission)
+            .unwrap_or(0)
+    }
+
+    /// Split a flight's settlement between several recipients, e.g. codeshare
+    /// carriers. Only while the flight is still open for booking.
+    pub fn set_payout_split(env: Env, admin: Address, flight_id: BytesN<32>, split: PayoutSplit) {
+        Self::require_admin(&env, &admin);
+        Self::audit(
+            &env,
+            AuditAction::FeeChanged,
+            &admin,
+            Some(flight_id.clone()),
+            split.shares.len() as i128,
+        );
+
+        let flight = Self::load_flight(&env, &flight_id)
+            .unwrap_or_else(|| panic_with_error!(&env, FlyStellarError::FlightNotFound));
+        if flight.status != Symbol::new(&env, "booking") {
+            panic_with_error!(&env, FlyStellarError::InvalidStatus);
+        }
+
+        let mut total: u32 = 0;
+        let mut seen: Vec<Address> = Vec::new(&env);
+        for (recipient, bps) in split.shares.iter() {
+            if bps == 0 || seen.contains(&recipient) {
+                panic_with_error!(&env, FlyStellarError::InvalidInput);
+            }
+            total = total.saturating_add(bps);
+            seen.push_back(recipient);
+        }
+        if split.shares.len() > MAX_PAYOUT_RECIPIENTS || total != BPS_DENOMINATOR {
+            panic_with_error!(&env, FlyStellarError::InvalidInput);
+        }
+        Self::persist(&env, &DataKeyExt::PayoutSplit(flight_id), &split);
+    }
+
+    /// Recipients `settle_flight` pays. Without a split set, the flight's
+    /// creator takes everything.
+    pub fn get_payout_split(env: Env, flight_id: BytesN<32>) -> PayoutSplit {
+        if let Some(split) = Self::fetch(&env, &DataKeyExt::PayoutSplit(flight_id.clone())) {
+            return split;
+        }
+        let flight = Self::load_flight(&env, &flight_id)
+            .unwrap_or_else(|| panic_with_error!(&env, FlyStellarError::FlightNotFound));
+        PayoutSplit {
+            shares: Vec::from_array(&env, [(flight.created_by, BPS_DENOMINATOR)]),
+        }
+    }
+
+    /// Hand `bps` of what a flight holds to the admin before departure. The
+    /// airline takes the refund risk: cancellations and refunds that would
+    /// need more than is left fail with `InsufficientEscrow` until the admin
+    /// returns funds through `top_up_escrow`. Returns the amount released.
+    pub fn release_escrow(env: Env, admin: Address, flight_id: BytesN<32>, bps: u32) -> i128 {
+        Self::require_admin(&env, &admin);
+        Self::audit(
+            &env,
+            AuditAction::FundsMoved,
+            &admin,
+            Some(flight_id.clone()),
+            bps as i128,
+        );
+
+        let flight = Self::load_flight(&env, &flight_id)
+            .unwrap_or_else(|| panic_with_error!(&env, FlyStellarError::FlightNotFound));
+        if flight.status != Symbol::new(&env, "booking") {
+            panic_with_error!(&env, FlyStellarError::InvalidStatus);
+        }
+        Self::require_not_frozen(&env, &flight_id);
+        if bps == 0 || bps > BPS_DENOMINATOR {
+            panic_with_error!(&env, FlyStellarError::InvalidInput);
+        }
+
+        let balance_key = DataKey::Balance(flight_id);
+        let mut balance: FlightBalance = Self::fetch(&env, &balance_key).unwrap_or_default();
+        let amount = balance.net_held * bps as i128 / BPS_DENOMINATOR as i128;
+        if amount > 0 {
+            // Whatever stays behind must still cover what the other flights owe
+            let held = Self::token_client(&env).balance(&env.current_contract_address());
+            if held - amount < Self::get_liabilities(env.clone()) - balance.net_held {
+                panic_with_error!(&env, FlyStellarError::InsufficientEscrow);
+            }
+            Self::token_client(&env).transfer(&env.current_contract_address(), &admin, &amount);
+            balance.paid_out += amount;
+            balance.net_held -= amount;
+            Self::persist(&env, &balance_key, &balance);
+            Self::add_liabilities(&env, -amount);
+        }
+        amount
+    }
+
+    /// Return released escrow to a flight so refunds blocked by
+    /// `release_escrow` can go through, e.g. after the airline cancels or a
+    /// no-show sweep finds the flight empty. At most what was paid out of the
+    /// flight can be returned.
+    pub fn top_up_escrow(env: Env, admin: Address, flight_id: BytesN<32>, amount: i128) {
+        Self::require_admin(&env, &admin);
+        Self::audit(
+            &env,
+            AuditAction::FundsMoved,
+            &admin,
+            Some(flight_id.clone()),
+            amount,
+        );
+
+        let flight = Self::load_flight(&env, &flight_id)
+            .unwrap_or_else(|| panic_with_error!(&env, FlyStellarError::FlightNotFound));
+        let balance_key = DataKey::Balance(flight_id);
+        let mut balance: FlightBalance = Self::fetch(&env, &balance_key).unwrap_or_default();
+        if amount <= 0 || amount > balance.paid_out {
+            panic_with_error!(&env, FlyStellarError::InvalidInput);
+        }
+
+        Self::token_client(&env).transfer(&admin, env.current_contract_address(), &amount);
+        balance.paid_out -= amount;
+        balance.net_held += amount;
+        Self::persist(&env, &balance_key, &balance);
+        if flight.status != Symbol::new(&env, "takeoff") {
+            Self::add_liabilities(&env, amount);
+        }
+    }
+
+    /// Pay out what a departed flight still holds once every no-show is swept:
+    /// the platform commission goes to the fee pot and the rest to the payout
+    /// split. Returns the amount paid to recipients.
+    pub fn settle_flight(env: Env, admin: Address, flight_id: BytesN<32>) -> i128 {
+        Self::require_admin(&env, &admin);
+        Self::audit(
+            &env,
+            AuditAction::FundsMoved,
+            &admin,
+            Some(flight_id.clone()),
+            0,
+        );
+
+        let flight = Self::load_flight(&env, &flight_id)
+            .unwrap_or_else(|| panic_with_error!(&env, FlyStellarError::FlightNotFound));
+        if flight.status != Symbol::new(&env, "takeoff") {
+            panic_with_error!(&env, FlyStellarError::InvalidStatus);
+        }
+        Self::require_not_frozen(&env, &flight_id);
+        let passengers: Vec<PassengerRecord> =
+            Self::fetch(&env, &DataKey::PassengerList(flight_id.clone())).unwrap_or(Vec::new(&env));
+        if passengers.iter().any(|rec| !rec.settled && !rec.checked_in) {
+            panic_with_error!(&env, FlyStellarError::NotSettled);
+        }
+        let held = Self::get_flight_balance(env.clone(), flight_id.clone()).net_held;
+        let token_escrow = Self::get_token_escrow(env.clone(), flight_id.clone());
+        if held <= 0 && token_escrow.is_empty() {
+            panic_with_error!(&env, FlyStellarError::InvalidStatus);
+        }
+
+        let split = Self::get_payout_split(env.clone(), flight_id.clone());
+        let (commission, payouts) = Self::split_payout(&env, &split, held.max(0));
+        let net = held.max(0) - commission;
+        // Never pay out money the contract owes to other flights' passengers
+        let token = Self::token_client(&env);
+        let surplus =
+            token.balance(&env.current_contract_address()) - Self::get_liabilities(env.clone());
+        if net > surplus {
+            panic_with_error!(&env, FlyStellarError::InsufficientEscrow);
+        }
+        Self::pay_split(&env, &token, &payouts);
+        // Fares paid in accepted tokens are split the same way, in kind
+        for (token, amount) in token_escrow.iter() {
+            let (token_commission, token_payouts) = Self::split_payout(&env, &split, amount);
+            let client = token::Client::new(&env, &token);
+            let owed: i128 = env
+                .storage()
+                .instance()
+                .get(&DataKeyExt::TokenLiabilities(token.clone()))
+                .unwrap_or(0);
+            if amount - token_commission
+                > client.balance(&env.current_contract_address()) - (owed - amount)
+            {
+                panic_with_error!(&env, FlyStellarError::InsufficientEscrow);
+            }
+            Self::pay_split(&env, &client, &token_payouts);
+            Self::record_token_flows(&env, &flight_id, &token, -amount, token_commission);
+        }
+        Self::add_to_fee_pot(&env, commission);
+        Self::record_flows(&env, &flight_id, 0, 0, commission);
+        let balance_key = DataKey::Balance(flight_id.clone());
+        let mut balance: FlightBalance = Self::fetch(&env, &balance_key).unwrap_or_default();
+        balance.paid_out += net;
+        balance.net_held -= net;
+        Self::persist(&env, &balance_key, &balance);
+        // Settled trips move out of the passengers' active registries
+        for rec in passengers.iter() {
+            Self::archive_trip(&env, &rec.passenger, &flight_id);
+        }
+
+        FlightSettled {
+            flight_id,
+            commission,
+            payouts,
+        }
+        .publish(&env);
+        net
+    }
+
+    /// Take the platform commission off `held` and share the rest by `split`,
+    /// rounding dust going to the first recipient
+    fn split_payout(env: &Env, split: &PayoutSplit, held: i128) -> (i128, Vec<(Address, i128)>) {
+        let commission =
+            held * Self::get_platform_commission(env.clone()) as i128 / BPS_DENOMINATOR as i128;
+        let net = held - commission;
+        let mut payouts: Vec<(Address, i128)> = Vec::new(env);
+        let mut assigned: i128 = 0;
+        for (recipient, bps) in split.shares.iter() {
+            let share = net * bps as i128 / BPS_DENOMINATOR as i128;
+            assigned += share;
+            payouts.push_back((recipient, share));
+        }
+        let (first, share) = payouts.get_unchecked(0);
+        payouts.set(0, (first, share + net - assigned));
+        (commission, payouts)
+    }
+
+    fn pay_split(env: &Env, token: &token::Client, payouts: &Vec<(Address, i128)>) {
+        for (recipient, amount) in payouts.iter() {
+            if amount > 0 {
+                token.transfer(&env.current_contract_address(), &recipient, &amount);
+            }
+        }
+    }
+
+    pub fn get_airline_payout(env: Env, flight_id: BytesN<32>) -> i128 {
+        Self::fetch(&env, &DataKey::AirlinePayout(flight_id)).unwrap_or(0)
+    }
+
+    pub fn get_fee_pot(env: Env) -> i128 {
+        env.storage().instance().get(&DataKey::FeePot).unwrap_or(0)
+    }
+
+    /// Freeze a flight during a dispute: while frozen, nobody can book onto it,
+    /// cancel off it or rebook to or from it. Its status, bookings and escrow
+    /// are left as they are.
+    pub fn set_flight_frozen(env: Env, admin: Address, flight_id: BytesN<32>, frozen: bool) {
+        Self::require_admin(&env, &admin);
+        Self::audit(
+            &env,
+            AuditAction::FlightStatusChanged,
+            &admin,
+            Some(flight_id.clone()),
+            frozen as i128,
+        );
+
+        if Self::load_flight(&env, &flight_id).is_none() {
+            panic_with_error!(&env, FlyStellarError::FlightNotFound);
+        }
+        let key = DataKeyExt::Frozen(flight_id);
+        if frozen {
+            Self::persist(&env, &key, &true);
+        } else {
+            env.storage().persistent().remove(&key);
+        }
+    }
+
+    pub fn is_flight_frozen(env: Env, flight_id: BytesN<32>) -> bool {
+        env.storage()
+            .persistent()
+            .has(&DataKeyExt::Frozen(flight_id))
+    }
+
+    fn require_not_frozen(env: &Env, flight_id: &BytesN<32>) {
+        if Self::is_flight_frozen(env.clone(), flight_id.clone()) {
+            panic_with_error!(env, FlyStellarError::FlightFrozen);
+        }
+    }
+
+    /// Move a flight to `takeoff` or `cancelled`. When `expected_status` is given the
+    /// write only applies if the flight is still in that status (compare-and-swap).
+    /// Either status voids the seat holds still live; they carry no payment, so
+    /// nothing is refunded.
+    pub fn update_flight_status(
+        env: Env,
+        admin: Address,
+        flight_id: BytesN<32>,
+        new_status: Symbol,
+        expected_status: Option<Symbol>,
+    ) {
+        Self::require_admin(&env, &admin);
+        Self::audit(
+            &env,
+            AuditAction::FlightStatusChanged,
+            &admin,
+            Some(flight_id.clone()),
+            0,
+        );
+        if let Err(err) = Self::apply_status(&env, &flight_id, new_status, expected_status) {
+            panic_with_error!(&env, err);
+        }
+    }
+
+    /// Move several flights to `takeoff` or `cancelled` at once, e.g. when weather
+    /// grounds a whole airport. Entries are applied independently: the result
+    /// holds 0 for each applied update, or the `FlyStellarError` code that
+    /// `update_flight_status` would have failed with, so one bad entry does not
+    /// block the rest. At most `MAX_STATUS_BATCH` entries per call.
+    pub fn update_statuses(
+        env: Env,
+        caller: Address,
+        updates: Vec<(BytesN<32>, Symbol)>,
+    ) -> Vec<u32> {
+        Self::require_admin_or_operator(&env, &caller);
+        if updates.len() > MAX_STATUS_BATCH {
+            panic_with_error!(&env, FlyStellarError::InvalidInput);
+        }
+
+        let mut results: Vec<u32> = Vec::new(&env);
+        for (flight_id, new_status) in updates.iter() {
+            let code = match Self::apply_status(&env, &flight_id, new_status, None) {
+                Ok(()) => {
+                    Self::audit(
+                        &env,
+                        AuditAction::FlightStatusChanged,
+                        &caller,
+                        Some(flight_id),
+                        0,
+                    );
+                    0
+                }
+                Err(err) => err as u32,
+            };
+            results.push_back(code);
+        }
+        results
+    }
+
+    /// Validate and apply one status transition, writing nothing unless it is
+    /// accepted
+    fn apply_status(
+        env: &Env,
+        flight_id: &BytesN<32>,
+        new_status: Symbol,
+        expected_status: Option<Symbol>,
+    ) -> Result<(), FlyStellarError> {
+        Self::check_no_pending_refunds(env, flight_id)?;
+
+        let mut flight: FlightDetails =
+            Self::load_flight(env, flight_id).ok_or(FlyStellarError::FlightNotFound)?;
+
+        let takeoff = Symbol::new(env, "takeoff");
+        let cancelled = Symbol::new(env, "cancelled");
+
+        if new_status != takeoff && new_status != cancelled {
+            return Err(FlyStellarError::InvalidStatus);
+        }
+
+        if let Some(expected) = expected_status {
+            if flight.status != expected {
+                return Err(FlyStellarError::StatusConflict);
+            }
+        }
+        // takeoff and cancelled are terminal; only an open flight can change status
+        if flight.status != Symbol::new(env, "booking") {
+            return Err(FlyStellarError::InvalidStatus);
+        }
+
+        // Status history only moves forward; a clock running backwards is refused
+        let now = env.ledger().timestamp();
+        if now < flight.status_changed_at {
+            return Err(FlyStellarError::StaleStatus);
+        }
+
+        // Departed or cancelled, the flight frees its number for that day
+        Self::release_number(env, &flight);
+        Self::void_holds(env, flight_id);
+
+        if new_status == takeoff {
+            Self::write_report(env, flight_id);
+        }
+
+        let owed_before = Self::flight_liabilities(env, &flight);
+        flight.status = new_status.clone();
+        Self::add_liabilities(env, Self::flight_liabilities(env, &flight) - owed_before);
+        flight.status_changed_at = now;
+        Self::save_flight_state(env, &flight);
+        StatusChanged {
+            flight_id: flight_id.clone(),
+            status: new_status,
+        }
+        .publish(env);
+        Ok(())
+    }
+
+    /// Day a flight's number is claimed for: its scheduled departure day,
+    /// before any delay
+    fn number_day(flight: &FlightDetails) -> u64 {
+        (flight.depart_at - flight.delay_secs) / SECS_PER_DAY
+    }
+
+    /// Drop a flight's claim on its number, if it still holds it
+    fn release_number(env: &Env, flight: &FlightDetails) {
+        let number_key = DataKey::FlightNumber(flight.flight_number.clone());
+        if let Some(mut days) = Self::fetch::<Map<u64, BytesN<32>>>(env, &number_key) {
+            let day = Self::number_day(flight);
+            if days.get(day) == Some(flight.id.clone()) {
+                days.remove(day);
+                Self::persist(env, &number_key, &days);
+            }
+        }
+    }
+
+    /// Next flight with this number whose scheduled departure day is today or later
+    pub fn get_flight_by_number(env: Env, number: Symbol) -> Option<FlightDetails> {
+        let days: Map<u64, BytesN<32>> = Self::fetch(&env, &DataKey::FlightNumber(number))?;
+        let today = env.ledger().timestamp() / SECS_PER_DAY;
+        for (day, id) in days.iter() {
+            if day >= today {
+                return Self::load_flight(&env, &id);
+            }
+        }
+        None
+    }
+
+    /// Flight with this number scheduled on `day`, counted in days since the
+    /// Unix epoch. Flights that have departed or been cancelled no longer hold
+    /// their number.
+    pub fn get_flight_on_day(env: Env, number: Symbol, day: u64) -> Option<FlightDetails> {
+        let days: Map<u64, BytesN<32>> = Self::fetch(&env, &DataKey::FlightNumber(number))?;
+        Self::load_flight(&env, &days.get(day)?)
+    }
+
+    pub fn get_flights_search(env: Env, src: Symbol, dest: Symbol) -> Vec<FlightDetails> {
+        let route_key = DataKey::RouteRegistry(src, dest);
+        let ids: Vec<BytesN<32>> = Self::fetch(&env, &route_key).unwrap_or(Vec::new(&env));
+
+        let mut out: Vec<FlightDetails> = Vec::new(&env);
+        for id in ids.iter() {
+            if let Some(f) = Self::load_flight(&env, &id) {
+                out.push_back(f);
+            }
+        }
+        out
+    }
+
+    /// Record a route the first time a flight is scheduled on it
+    fn push_route(env: &Env, src: &Symbol, dest: &Symbol) {
+        let count: u32 = env
+            .storage()
+            .instance()
+            .get(&DataKey::RouteCount)
+            .unwrap_or(0);
+
+        let bucket_key = DataKey::RouteList(count / ROUTE_BUCKET_SIZE);
+        let mut bucket: Vec<(Symbol, Symbol)> =
+            Self::fetch(env, &bucket_key).unwrap_or(Vec::new(env));
+        bucket.push_back((src.clone(), dest.clone()));
+        Self::persist(env, &bucket_key, &bucket);
+        env.storage()
+            .instance()
+            .set(&DataKey::RouteCount, &(count + 1));
+    }
+
+    /// Every route that has had a flight, in the order they were first seen
+    pub fn list_routes(env: Env, page: u32, limit: u32) -> Vec<(Symbol, Symbol)> {
+        let count: u32 = env
+            .storage()
+            .instance()
+            .get(&DataKey::RouteCount)
+            .unwrap_or(0);
+
+        let limit = limit.min(MAX_PAGE_LIMIT);
+        let start = page.saturating_mul(limit);
+        let end = start.saturating_add(limit).min(count);
+
+        let mut out: Vec<(Symbol, Symbol)> = Vec::new(&env);
+        let mut bucket: Vec<(Symbol, Symbol)> = Vec::new(&env);
+        let mut bucket_idx = u32::MAX;
+        for i in start..end {
+            if i / ROUTE_BUCKET_SIZE != bucket_idx {
+                bucket_idx = i / ROUTE_BUCKET_SIZE;
+                bucket =
+                    Self::fetch(&env, &DataKey::RouteList(bucket_idx)).unwrap_or(Vec::new(&env));
+            }
+            out.push_back(bucket.get_unchecked(i % ROUTE_BUCKET_SIZE));
+        }
+        out
+    }
+
+    /// Flights currently stored on `src -> dest`
+    pub fn route_flight_count(env: Env, src: Symbol, dest: Symbol) -> u32 {
+        let ids: Vec<BytesN<32>> =
+            Self::fetch(&env, &DataKey::RouteRegistry(src, dest)).unwrap_or(Vec::new(&env));
+        let mut count = 0;
+        for id in ids.iter() {
+            if env.storage().persistent().has(&DataKey::Flight(id)) {
+                count += 1;
+            }
+        }
+        count
+    }
+
+    /// Whether any flight has ever been registered on `src -> dest`
+    pub fn route_exists(env: Env, src: Symbol, dest: Symbol) -> bool {
+        env.storage()
+            .persistent()
+            .has(&DataKey::RouteRegistry(src, dest))
+    }
+
+    /// Same as `get_flights_search`, but fails with `RouteNotFound` for unknown routes
+    pub fn get_flights_search_checked(env: Env, src: Symbol, dest: Symbol) -> Vec<FlightDetails> {
+        if !Self::route_exists(env.clone(), src.clone(), dest.clone()) {
+            panic_with_error!(&env, FlyStellarError::RouteNotFound);
+        }
+        Self::get_flights_search(env, src, dest)
+    }
+
+    /// Full booking records of a flight, including each passenger's type, one
+    /// page at a time alongside the total number of records
+    pub fn get_manifest(
+        env: Env,
+        admin: Address,
+        flight_id: BytesN<32>,
+        start: u32,
+        limit: u32,
+    ) -> (Vec<PassengerRecord>, u32) {
+        Self::require_admin(&env, &admin);
+
+        Self::passenger_page(&env, flight_id, start, limit)
+    }
+
+    /// The flight and its whole passenger list in one snapshot, for archiving a
+    /// flight off-chain before cleanup
+    pub fn export_flight(
+        env: Env,
+        admin: Address,
+        flight_id: BytesN<32>,
+    ) -> (FlightDetails, Vec<PassengerRecord>) {
+        Self::require_admin(&env, &admin);
+
+        let flight = Self::load_flight(&env, &flight_id)
+            .unwrap_or_else(|| panic_with_error!(&env, FlyStellarError::FlightNotFound));
+        let records =
+            Self::fetch(&env, &DataKey::PassengerList(flight_id)).unwrap_or(Vec::new(&env));
+        (flight, records)
+    }
+
+    /// Move a departed or cancelled flight whose bookings are all settled into a
+    /// single `DataKeyExt::Archive` entry. Its live entries are deleted and it
+    /// leaves the route, flight-number and passenger indexes, so it no longer
+    /// shows up in searches and cannot be booked. Cancelled flights must have
+    /// refunded everyone; departed ones must have swept every no-show.
+    pub fn archive_flight(env: Env, admin: Address, flight_id: BytesN<32>) {
+        Self::require_admin(&env, &admin);
+        Self::audit(
+            &env,
+            AuditAction::FlightEdited,
+            &admin,
+            Some(flight_id.clone()),
+            0,
+        );
+
+        let flight = Self::load_flight(&env, &flight_id)
+            .unwrap_or_else(|| panic_with_error!(&env, FlyStellarError::FlightNotFound));
+        let cancelled = flight.status == Symbol::new(&env, "cancelled");
+        if !cancelled && flight.status != Symbol::new(&env, "takeoff") {
+            panic_with_error!(&env, FlyStellarError::InvalidStatus);
+        }
+        Self::require_not_frozen(&env, &flight_id);
+
+        let passengers: Vec<PassengerRecord> =
+            Self::fetch(&env, &DataKey::PassengerList(flight_id.clone())).unwrap_or(Vec::new(&env));
+        let balance: FlightBalance =
+            Self::fetch(&env, &DataKey::Balance(flight_id.clone())).unwrap_or_default();
+        for rec in passengers.iter() {
+            if cancelled && !rec.settled {
+                panic_with_error!(&env, FlyStellarError::RefundsPending);
+            }
+            if !rec.settled && !rec.checked_in {
+                panic_with_error!(&env, FlyStellarError::NotSettled);
+            }
+        }
+        // Nothing may be left in escrow: refunded if cancelled, paid out by
+        // `settle_flight` if departed
+        let token_escrow = Self::get_token_escrow(env.clone(), flight_id.clone());
+        if balance.net_held != 0 || !token_escrow.is_empty() {
+            if cancelled {
+                panic_with_error!(&env, FlyStellarError::RefundsPending);
+            }
+            panic_with_error!(&env, FlyStellarError::NotSettled);
+        }
+
+        let archive = ArchivedFlight {
+            flight: flight.clone(),
+            passengers: passengers.clone(),
+            balance,
+            airline_payout: Self::get_airline_payout(env.clone(), flight_id.clone()),
+            report: Self::fetch_optional(&env, &DataKeyExt::Report(flight_id.clone())),
+            supplements: Self::get_report_supplements(env.clone(), flight_id.clone()),
+            ops: Self::fetch_optional(&env, &DataKey::Ops(flight_id.clone())),
+            charter: Self::fetch_optional(&env, &DataKey::Charter(flight_id.clone())),
+            archived_at: env.ledger().timestamp(),
+        };
+        Self::persist(&env, &DataKeyExt::Archive(flight_id.clone()), &archive);
+
+        for rec in passengers.iter() {
+            Self::archive_trip(&env, &rec.passenger, &flight_id);
+            env.storage()
+                .persistent()
+                .remove(&DataKeyExt::Ticket(flight_id.clone(), rec.passenger));
+        }
+        Self::remove_from_route(&env, &flight);
+
+        let storage = env.storage().persistent();
+        let id = flight_id;
+        storage.remove(&DataKey::Flight(id.clone()));
+        storage.remove(&DataKey::FlightState(id.clone()));
+        storage.remove(&DataKey::PassengerList(id.clone()));
+        storage.remove(&DataKey::SeatMap(id.clone()));
+        storage.remove(&DataKey::AirlinePayout(id.clone()));
+        storage.remove(&DataKey::SweepCursor(id.clone()));
+        storage.remove(&DataKey::RefundCursor(id.clone()));
+        storage.remove(&DataKey::Balance(id.clone()));
+        storage.remove(&DataKey::Ops(id.clone()));
+        storage.remove(&DataKey::Charter(id.clone()));
+        storage.remove(&DataKey::CancelCutoff(id.clone()));
+        storage.remove(&DataKey::HoldList(id.clone()));
+        storage.remove(&DataKey::RescheduleWindow(id.clone()));
+        storage.remove(&DataKeyExt::OccupancyMark(id.clone()));
+        storage.remove(&DataKeyExt::Report(id.clone()));
+        storage.remove(&DataKeyExt::BookingIndex(id.clone()));
+        storage.remove(&DataKeyExt::Promo(id.clone()));
+        storage.remove(&DataKeyExt::TokenEscrow(id.clone()));
+        storage.remove(&DataKeyExt::ReportSupplements(id));
+    }
+
+    /// An archived flight. Passenger records are left out in privacy mode.
+    pub fn get_archived(env: Env, flight_id: BytesN<32>) -> Option<ArchivedFlight> {
+        let mut archive: ArchivedFlight = Self::fetch(&env, &DataKeyExt::Archive(flight_id))?;
+        if Self::get_privacy_mode(env.clone()) {
+            archive.passengers = Vec::new(&env);
+        }
+        Some(archive)
+    }
+
+    /// Restore an archived flight's live entries and put it back on its route.
+    /// Passenger registries and the flight-number slot are not restored: the
+    /// trip stays in each passenger's history and the number may be reused.
+    pub fn unarchive_flight(env: Env, admin: Address, flight_id: BytesN<32>) {
+        Self::require_admin(&env, &admin);
+        Self::audit(
+            &env,
+            AuditAction::FlightEdited,
+            &admin,
+            Some(flight_id.clone()),
+            1,
+        );
+
+        let archive_key = DataKeyExt::Archive(flight_id.clone());
+        let archive: ArchivedFlight = Self::fetch(&env, &archive_key)
+            .unwrap_or_else(|| panic_with_error!(&env, FlyStellarError::FlightNotFound));
+        let flight = archive.flight;
+        if env
+            .storage()
+            .persistent()
+            .has(&DataKey::Flight(flight_id.clone()))
+        {
+            panic_with_error!(&env, FlyStellarError::FlightAlreadyExists);
+        }
+
+        // Written ahead of `save_flight` so the seats-sold total, which kept
+        // counting the archived flight, is not bumped a second time
+        let state = FlightState {
+            status: flight.status.clone(),
+            passenger_count: flight.passenger_count,
+            charter_holder: flight.charter_holder.clone(),
+            status_changed_at: flight.status_changed_at,
+        };
+        Self::persist(&env, &DataKey::FlightState(flight_id.clone()), &state);
+        Self::save_flight(&env, &flight);
+
+        let mut seat_map: Map<u32, PassengerId> = Map::new(&env);
+        let mut index = BookingIndex {
+            next_seq: 1,
+            holders: Map::new(&env),
+        };
+        for rec in archive.passengers.iter() {
+            if rec.seat != 0 {
+                seat_map.set(rec.seat, rec.passenger.clone());
+            }
+            index.holders.set(rec.booking_seq, rec.passenger.clone());
+            index.next_seq = index.next_seq.max(rec.booking_seq + 1);
+        }
+        Self::persist(&env, &DataKey::SeatMap(flight_id.clone()), &seat_map);
+        Self::persist(&env, &DataKeyExt::BookingIndex(flight_id.clone()), &index);
+        Self::persist(
+            &env,
+            &DataKey::PassengerList(flight_id.clone()),
+            &archive.passengers,
+        );
+        // Settlement is complete, so the sweep cursor sits at the end
+        if flight.status == Symbol::new(&env, "takeoff") {
+            Self::persist(
+                &env,
+                &DataKey::SweepCursor(flight_id.clone()),
+                &archive.passengers.len(),
+            );
+        }
+        Self::persist(&env, &DataKey::Balance(flight_id.clone()), &archive.balance);
+        if archive.airline_payout != 0 {
+            Self::persist(
+                &env,
+                &DataKey::AirlinePayout(flight_id.clone()),
+                &archive.airline_payout,
+            );
+        }
+        if let Some(report) = archive.report.first() {
+            Self::persist(&env, &DataKeyExt::Report(flight_id.clone()), &report);
+        }
+        if !archive.supplements.is_empty() {
+            Self::persist(
+                &env,
+                &DataKeyExt::ReportSupplements(flight_id.clone()),
+                &archive.supplements,
+            );
+        }
+        if let Some(ops) = archive.ops.first() {
+            Self::persist(&env, &DataKey::Ops(flight_id.clone()), &ops);
+        }
+        if let Some(charter) = archive.charter.first() {
+            Self::persist(&env, &DataKey::Charter(flight_id.clone()), &charter);
+        }
+
+        let route_key = DataKey::RouteRegistry(flight.src.clone(), flight.dest.clone());
+        let mut ids: Vec<BytesN<32>> = Self::fetch(&env, &route_key).unwrap_or(Vec::new(&env));
+        ids.push_back(flight_id);
+        Self::persist(&env, &route_key, &ids);
+        env.storage().persistent().remove(&archive_key);
     }
 
-    fn require_admin(env: &Env) -> Address {
-        let admin = Self::get_admin(env);
-        admin.require_auth();
-        admin
+    fn remove_from_route(env: &Env, flight: &FlightDetails) {
+        let route_key = DataKey::RouteRegistry(flight.src.clone(), flight.dest.clone());
+        let mut ids: Vec<BytesN<32>> = Self::fetch(env, &route_key).unwrap_or(Vec::new(env));
+        if let Some(i) = ids.first_index_of(&flight.id) {
+            ids.remove(i);
+            Self::persist(env, &route_key, &ids);
+        }
     }
 
-    pub fn create_flight(
+    /// Booking records `start..start + limit` in booking order, plus the total
+    /// number of records. Unavailable in privacy mode; use `get_manifest`.
+    pub fn get_passengers_page(
         env: Env,
         flight_id: BytesN<32>,
-        max_passengers: u32,
-        distance: i128,
-        src: Symbol,
-        dest: Symbol,
-    ) {
-        log!(&env, "🟦 [START] create_flight called");
-
-        // Step 1: Admin authentication
-        log!(&env, "🔐 Checking admin auth...");
-        Self::require_admin(&env);
-        log!(&env, "✅ Admin authenticated successfully");
+        start: u32,
+        limit: u32,
+    ) -> (Vec<PassengerRecord>, u32) {
+        if Self::get_privacy_mode(env.clone()) {
+            panic_with_error!(&env, FlyStellarError::Unauthorized);
+        }
 
-        // Step 2: Input validation
-        log!(
-            &env,
-            "📥 Inputs => max_passengers={}, distance={}, src={}, dest={}",
-            max_passengers,
-            distance,
-            src,
-            dest
-        );
+        Self::passenger_page(&env, flight_id, start, limit)
+    }
 
-        if max_passengers == 0 || distance <= 0 {
-            log!(
-                &env,
-                "❌ Invalid input: max_passengers={} distance={}",
-                max_passengers,
-                distance
-            );
-            panic_with_error!(&env, FlyStellarError::InvalidInput);
-        }
+    /// At most `MAX_PAGE_LIMIT` records; an out-of-range `start` gives an empty page
+    fn passenger_page(
+        env: &Env,
+        flight_id: BytesN<32>,
+        start: u32,
+        limit: u32,
+    ) -> (Vec<PassengerRecord>, u32) {
+        let pass_list: Vec<PassengerRecord> =
+            Self::fetch(env, &DataKey::PassengerList(flight_id)).unwrap_or(Vec::new(env));
 
-        // Step 3: Check if flight already exists
-        let flight_key = DataKey::Flight(flight_id.clone());
-        if env.storage().persistent().has(&flight_key) {
-            log!(&env, "⚠️ Flight already exists with ID {:?}", flight_id);
-            panic_with_error!(&env, FlyStellarError::FlightAlreadyExists);
+        let total = pass_list.len();
+        let end = start.saturating_add(limit.min(MAX_PAGE_LIMIT)).min(total);
+        if start >= end {
+            return (Vec::new(env), total);
         }
-        log!(&env, "🆕 Flight key {:?} is new, proceeding...", flight_id);
+        (pass_list.slice(start..end), total)
+    }
 
-        // Step 4: Calculate escrow
-        log!(
+    /// Hide passenger addresses behind per-flight hashes. Bookings made while it
+    /// is on store `sha256(passenger || flight_id)` in the passenger list, seat
+    /// map, booking index and ticket, for payer and passenger alike, and must be
+    /// paid in the base token; refunds to a hashed payer wait for
+    /// `claim_private_refund`. `get_passengers` hashes older plain entries too.
+    /// Reads that map seats or bookings to addresses are refused, reads of a
+    /// passenger's own bookings need their signature, and events naming a
+    /// passenger are not published.
+    pub fn set_privacy_mode(env: Env, admin: Address, enabled: bool) {
+        Self::require_admin(&env, &admin);
+        Self::audit(
             &env,
-            "💰 Calculating escrow = max_passengers({}) * distance({})",
-            max_passengers,
-            distance
+            AuditAction::ConfigChanged,
+            &admin,
+            None,
+            enabled as i128,
         );
-        let escrow = (max_passengers as i128)
-            .checked_mul(distance)
-            .expect("escrow overflow");
-        log!(&env, "✅ Escrow amount calculated: {}", escrow);
 
-        // Step 5: Create flight details struct
-        let details = FlightDetails {
-            id: flight_id.clone(),
-            max_passengers,
-            distance,
-            src: src.clone(),
-            dest: dest.clone(),
-            status: Symbol::new(&env, "booking"),
-            escrow_amount: escrow,
-            passenger_count: 0,
-        };
-        log!(&env, "🧱 FlightDetails struct created successfully");
+        env.storage()
+            .instance()
+            .set(&DataKey::PrivacyMode, &enabled);
+    }
 
-        // Step 6: Save to storage
-        env.storage().persistent().set(&flight_key, &details);
-        log!(&env, "💾 Stored FlightDetails in persistent storage");
+    pub fn get_privacy_mode(env: Env) -> bool {
+        env.storage()
+            .instance()
+            .get(&DataKey::PrivacyMode)
+            .unwrap_or(false)
+    }
 
-        // Step 7: Add to route registry
-        let route_key = DataKey::RouteRegistry(src.clone(), dest.clone());
-        log!(
+    /// Notify `hook` of every booking and cancellation, see `HookConfig`
+    pub fn set_hook(env: Env, admin: Address, hook: Address, strict: bool) {
+        Self::require_admin(&env, &admin);
+        Self::audit(
             &env,
-            "🔍 Fetching existing route registry for {} -> {}",
-            src,
-            dest
+            AuditAction::ConfigChanged,
+            &admin,
+            None,
+            strict as i128,
         );
-        let mut registry: Vec<BytesN<32>> = env
-            .storage()
-            .persistent()
-            .get(&route_key)
-            .unwrap_or(Vec::new(&env));
-        registry.push_back(flight_id.clone());
-        env.storage().persistent().set(&route_key, &registry);
-        log!(&env, "🗺️ Updated route registry for {} -> {}", src, dest);
 
-        // Step 8: Add to global registry
-        log!(&env, "🌍 Fetching global registry...");
-        let mut global: Vec<BytesN<32>> = env
-            .storage()
-            .persistent()
-            .get(&DataKey::GlobalRegistry)
-            .unwrap_or(Vec::new(&env));
-        global.push_back(flight_id.clone());
         env.storage()
-            .persistent()
-            .set(&DataKey::GlobalRegistry, &global);
-        log!(
-            &env,
-            "🌍 Global registry updated with new flight {:?}",
-            flight_id
-        );
-
-        // Step 9: Completion
-        log!(&env, "✅ [END] Flight successfully created!");
+            .instance()
+            .set(&DataKey::Hook, &HookConfig { hook, strict });
     }
 
-    /// Buy a ticket for a flight
-    pub fn buy_ticket(env: Env, flight_id: BytesN<32>, passenger: Address, details: Symbol) {
-        // Passenger must authorize this action
-        passenger.require_auth();
+    pub fn clear_hook(env: Env, admin: Address) {
+        Self::require_admin(&env, &admin);
+        Self::audit(&env, AuditAction::ConfigChanged, &admin, None, 0);
 
-        let flight_key = DataKey::Flight(flight_id.clone());
+        env.storage().instance().remove(&DataKey::Hook);
+    }
 
-        // Get flight details
-        let mut flight: FlightDetails = env
-            .storage()
-            .persistent()
-            .get(&flight_key)
-            .expect("Flight not found");
+    pub fn get_hook(env: Env) -> Option<HookConfig> {
+        env.storage().instance().get(&DataKey::Hook)
+    }
 
-        // Validate flight status and capacity
-        if flight.status != Symbol::new(&env, "booking") {
-            panic_with_error!(&env, FlyStellarError::InvalidStatus);
-        }
-        if flight.passenger_count >= flight.max_passengers {
-            panic_with_error!(&env, FlyStellarError::FlightFull);
+    fn notify_hook(
+        env: &Env,
+        method: &str,
+        flight_id: &BytesN<32>,
+        passenger: &Address,
+        amount: i128,
+    ) {
+        let config: HookConfig = match env.storage().instance().get(&DataKey::Hook) {
+            Some(config) => config,
+            None => return,
+        };
+        let func = Symbol::new(env, method);
+        let args = (flight_id.clone(), passenger.clone(), amount).into_val(env);
+        if config.strict {
+            env.invoke_contract::<()>(&config.hook, &func, args);
+        } else if env
+            .try_invoke_contract::<(), soroban_sdk::Error>(&config.hook, &func, args)
+            .is_err()
+        {
+            debug_log!(env, "⚠️ Hook {} failed, ignoring", method);
         }
+    }
 
-        let fare = flight.distance;
-        if fare <= 0 {
-            panic_with_error!(&env, FlyStellarError::InvalidFare);
+    /// `sha256(passenger || flight_id)`, the identity shown in privacy mode
+    pub fn passenger_hash(env: Env, flight_id: BytesN<32>, passenger: Address) -> BytesN<32> {
+        let mut preimage = passenger.to_xdr(&env);
+        preimage.append(&flight_id.into());
+        env.crypto().sha256(&preimage).into()
+    }
+
+    /// Everyone booked on a flight, one entry per booking
+    pub fn get_passengers(env: Env, flight_id: BytesN<32>) -> Vec<PassengerId> {
+        let pass_list: Vec<PassengerRecord> =
+            Self::fetch(&env, &DataKey::PassengerList(flight_id.clone())).unwrap_or(Vec::new(&env));
+
+        let private = Self::get_privacy_mode(env.clone());
+        let mut out: Vec<PassengerId> = Vec::new(&env);
+        for rec in pass_list.iter() {
+            out.push_back(match rec.passenger {
+                PassengerId::Plain(passenger) if private => PassengerId::Hashed(
+                    Self::passenger_hash(env.clone(), flight_id.clone(), passenger),
+                ),
+                id => id,
+            });
         }
+        out
+    }
 
-        // TODO: require token transfer of `fare` from `passenger` to contract escrow here.
-        // Example: token_client.transfer(&passenger, &env.current_contract_address(), &fare);
+    /// Whether anyone holds a booking on the flight
+    pub fn has_passengers(env: Env, flight_id: BytesN<32>) -> bool {
+        let pass_list: Vec<PassengerRecord> =
+            Self::fetch(&env, &DataKey::PassengerList(flight_id)).unwrap_or(Vec::new(&env));
+        !pass_list.is_empty()
+    }
 
-        // Create passenger record
-        let record = PassengerRecord {
-            passenger: passenger.clone(),
-            paid: fare,
-            details,
-        };
+    /// Whether `passenger` holds a booking on the flight
+    pub fn is_booked(env: Env, flight_id: BytesN<32>, passenger: Address) -> bool {
+        let holder = Self::holder(&env, &flight_id, &passenger);
+        let pass_list: Vec<PassengerRecord> =
+            Self::fetch(&env, &DataKey::PassengerList(flight_id)).unwrap_or(Vec::new(&env));
+        pass_list.iter().any(|rec| holder.is(&rec.passenger))
+    }
 
-        let pass_list_key = DataKey::PassengerList(flight_id.clone());
-        let mut pass_list: Vec<PassengerRecord> = env
-            .storage()
-            .persistent()
-            .get(&pass_list_key)
-            .unwrap_or(Vec::new(&env));
-        pass_list.push_back(record);
-        env.storage().persistent().set(&pass_list_key, &pass_list);
+    /// The passenger's earliest live booking on a flight, including the
+    /// `booking_seq` support refers to
+    pub fn get_ticket(env: Env, flight_id: BytesN<32>, passenger: Address) -> PassengerRecord {
+        Self::require_private_read(&env, &passenger);
+        let holder = Self::holder(&env, &flight_id, &passenger);
+        let pass_list: Vec<PassengerRecord> =
+            Self::fetch(&env, &DataKey::PassengerList(flight_id)).unwrap_or(Vec::new(&env));
+        pass_list
+            .iter()
+            .find(|rec| holder.is(&rec.passenger))
+            .unwrap_or_else(|| panic_with_error!(&env, FlyStellarError::PassengerNotFound))
+    }
 
-        // Add to passenger's flight registry
-        let pass_reg_key = DataKey::PassengerRegistry(passenger.clone());
-        let mut pass_registry: Vec<BytesN<32>> = env
-            .storage()
-            .persistent()
-            .get(&pass_reg_key)
-            .unwrap_or(Vec::new(&env));
-        pass_registry.push_back(flight_id.clone());
-        env.storage()
-            .persistent()
-            .set(&pass_reg_key, &pass_registry);
+    /// Who holds booking number `booking_seq` on a flight, if it is still live
+    pub fn get_booking_holder(
+        env: Env,
+        flight_id: BytesN<32>,
+        booking_seq: u32,
+    ) -> Option<PassengerId> {
+        if Self::get_privacy_mode(env.clone()) {
+            panic_with_error!(&env, FlyStellarError::Unauthorized);
+        }
+        Self::booking_index(&env, &flight_id)
+            .holders
+            .get(booking_seq)
+    }
 
-        // Update passenger count
-        flight.passenger_count = flight
-            .passenger_count
-            .checked_add(1)
-            .expect("passenger count overflow");
-        env.storage().persistent().set(&flight_key, &flight);
+    /// Seats already taken on a flight, mapped to their holders. Unavailable in
+    /// privacy mode; use `get_taken_seats`.
+    pub fn get_seat_map(env: Env, flight_id: BytesN<32>) -> Map<u32, PassengerId> {
+        if Self::get_privacy_mode(env.clone()) {
+            panic_with_error!(&env, FlyStellarError::Unauthorized);
+        }
+        Self::fetch(&env, &DataKey::SeatMap(flight_id)).unwrap_or(Map::new(&env))
     }
 
-    /// Cancel a ticket and get refund
-    pub fn cancel_ticket(env: Env, flight_id: BytesN<32>, passenger: Address) {
-        // Passenger must authorize cancellation
-        passenger.require_auth();
+    /// Seats already taken on a flight, in seat order
+    pub fn get_taken_seats(env: Env, flight_id: BytesN<32>) -> Vec<u32> {
+        let seat_map: Map<u32, PassengerId> =
+            Self::fetch(&env, &DataKey::SeatMap(flight_id)).unwrap_or(Map::new(&env));
+        seat_map.keys()
+    }
 
-        let flight_key = DataKey::Flight(flight_id.clone());
+    /// In privacy mode, only `passenger` may read their own bookings
+    fn require_private_read(env: &Env, passenger: &Address) {
+        if Self::get_privacy_mode(env.clone()) {
+            passenger.require_auth();
+        }
+    }
 
-        // Get flight details
-        let mut flight: FlightDetails = env
-            .storage()
-            .persistent()
-            .get(&flight_key)
-            .expect("Flight not found");
+    /// Page through a route's flights ordered by `sort`, ties broken by flight id
+    pub fn get_flights_search_page(
+        env: Env,
+        src: Symbol,
+        dest: Symbol,
+        sort: SortKey,
+        start: u32,
+        limit: u32,
+    ) -> SearchPage {
+        Self::get_flights_search_filtered(env, src, dest, sort, 0, start, limit)
+    }
 
-        // Get passenger list
-        let pass_list_key = DataKey::PassengerList(flight_id.clone());
-        let pass_list: Vec<PassengerRecord> = env
-            .storage()
-            .persistent()
-            .get(&pass_list_key)
-            .expect("No passengers");
+    /// `get_flights_search_page` restricted by the `SEARCH_EXCLUDE_*` bits in
+    /// `exclude`. Filtering happens before paging, so `total` counts matches.
+    pub fn get_flights_search_filtered(
+        env: Env,
+        src: Symbol,
+        dest: Symbol,
+        sort: SortKey,
+        exclude: u32,
+        start: u32,
+        limit: u32,
+    ) -> SearchPage {
+        let now = env.ledger().timestamp();
+        let booking = Symbol::new(&env, "booking");
+        let ids: Vec<BytesN<32>> =
+            Self::fetch(&env, &DataKey::RouteRegistry(src, dest)).unwrap_or(Vec::new(&env));
+        let truncated = ids.len() > MAX_SORT_CANDIDATES;
 
-        let mut new_list: Vec<PassengerRecord> = Vec::new(&env);
-        let mut found = false;
-        let admin: Address = env.storage().instance().get(&DataKey::Admin).unwrap();
+        // Insertion sort of the bounded candidate set on (key, id)
+        let mut sorted: Vec<(i128, FlightDetails)> = Vec::new(&env);
+        for id in ids.iter().take(MAX_SORT_CANDIDATES as usize) {
+            let flight: FlightDetails = match Self::load_flight(&env, &id) {
+                Some(f) => f,
+                None => continue,
+            };
+            if (exclude & SEARCH_EXCLUDE_FULL != 0
+                && flight.passenger_count >= flight.max_passengers)
+                || (exclude & SEARCH_EXCLUDE_DEPARTED != 0 && flight.depart_at <= now)
+                || (exclude & SEARCH_EXCLUDE_CLOSED != 0 && flight.status != booking)
+            {
+                continue;
+            }
+            let key = match sort {
+                SortKey::PriceAsc => Self::base_fare(&env, &flight),
+                SortKey::PriceDesc => -Self::base_fare(&env, &flight),
+                SortKey::DepartureAsc => flight.depart_at as i128,
+            };
 
-        // Process refund (90% to passenger, 10% admin fee)
-        for rec in pass_list.iter() {
-            if rec.passenger == passenger {
-                found = true;
-                let refund_90 = rec.paid * 9 / 10;
-                let admin_fee = rec.paid - refund_90;
-                // TODO: Implement token transfers
-                // token_client.transfer(&env.current_contract_address(), &passenger, &refund_90);
-                // token_client.transfer(&env.current_contract_address(), &admin, &admin_fee);
-                let _ = (refund_90, admin_fee); // Suppress unused warning
-            } else {
-                new_list.push_back(rec);
+            let mut pos = sorted.len();
+            while pos > 0 {
+                let (prev_key, prev) = sorted.get_unchecked(pos - 1);
+                if prev_key < key || (prev_key == key && prev.id <= flight.id) {
+                    break;
+                }
+                pos -= 1;
             }
+            sorted.insert(pos, (key, flight));
         }
 
-        if !found {
-            panic_with_error!(&env, FlyStellarError::PassengerNotFound);
+        let total = sorted.len();
+        let end = start.saturating_add(limit.min(MAX_PAGE_LIMIT)).min(total);
+        let mut flights: Vec<FlightDetails> = Vec::new(&env);
+        for i in start.min(end)..end {
+            flights.push_back(sorted.get_unchecked(i).1);
         }
 
-        env.storage().persistent().set(&pass_list_key, &new_list);
+        SearchPage {
+            flights,
+            total,
+            candidate_cap: MAX_SORT_CANDIDATES,
+            truncated,
+        }
+    }
 
-        flight.passenger_count = flight.passenger_count.saturating_sub(1);
-        env.storage().persistent().set(&flight_key, &flight);
+    /// Up to `k` flights on the route departing closest to `target`, nearest
+    /// first, ties broken by earlier departure then flight id. `k` is capped
+    /// at `MAX_PAGE_LIMIT`.
+    pub fn get_nearest_departures(
+        env: Env,
+        src: Symbol,
+        dest: Symbol,
+        target: u64,
+        k: u32,
+    ) -> Vec<FlightDetails> {
+        let k = k.min(MAX_PAGE_LIMIT);
+        let ids: Vec<BytesN<32>> =
+            Self::fetch(&env, &DataKey::RouteRegistry(src, dest)).unwrap_or(Vec::new(&env));
 
-        let pass_reg_key = DataKey::PassengerRegistry(passenger.clone());
-        if env.storage().persistent().has(&pass_reg_key) {
-            let reg: Vec<BytesN<32>> = env.storage().persistent().get(&pass_reg_key).unwrap();
-            let mut new_reg: Vec<BytesN<32>> = Vec::new(&env);
-            for id in reg.iter() {
-                if id != flight_id {
-                    new_reg.push_back(id);
+        // Insertion into a list of at most `k`, dropping whatever falls off the end
+        let mut out: Vec<(u64, FlightDetails)> = Vec::new(&env);
+        for id in ids.iter() {
+            let flight: FlightDetails = match Self::load_flight(&env, &id) {
+                Some(f) => f,
+                None => continue,
+            };
+            let gap = flight.depart_at.abs_diff(target);
+            let rank = (gap, flight.depart_at, flight.id.clone());
+
+            let mut pos = out.len();
+            while pos > 0 {
+                let (prev_gap, prev) = out.get_unchecked(pos - 1);
+                if (prev_gap, prev.depart_at, prev.id) <= rank {
+                    break;
+                }
+                pos -= 1;
+            }
+            if pos < k {
+                out.insert(pos, (gap, flight));
+                if out.len() > k {
+                    out.pop_back();
                 }
             }
-            env.storage().persistent().set(&pass_reg_key, &new_reg);
         }
-    }
 
-    pub fn update_flight_status(env: Env, flight_id: BytesN<32>, new_status: Symbol) {
-        Self::require_admin(&env);
-
-        let flight_key = DataKey::Flight(flight_id.clone());
+        let mut flights: Vec<FlightDetails> = Vec::new(&env);
+        for (_, flight) in out.iter() {
+            flights.push_back(flight);
+        }
+        flights
+    }
 
-        let mut flight: FlightDetails = env
-            .storage()
-            .persistent()
-            .get(&flight_key)
-            .expect("Flight not found");
+    /// Flights departing between now and `within_secs` from now, earliest first.
+    /// At most `MAX_PAGE_LIMIT` flights are returned.
+    pub fn get_upcoming_departures(env: Env, within_secs: u64) -> Vec<FlightDetails> {
+        let now = env.ledger().timestamp();
+        let until = now.saturating_add(within_secs);
+        let ids = Self::all_flight_ids(&env);
 
-        let takeoff = Symbol::new(&env, "takeoff");
-        let cancelled = Symbol::new(&env, "cancelled");
+        // Insertion sort on (depart_at, id), dropping anything past the cap
+        let mut out: Vec<FlightDetails> = Vec::new(&env);
+        for id in ids.iter() {
+            let flight: FlightDetails = match Self::load_flight(&env, &id) {
+                Some(f) => f,
+                None => continue,
+            };
+            if flight.depart_at < now || flight.depart_at > until {
+                continue;
+            }
 
-        if new_status != takeoff && new_status != cancelled {
-            panic_with_error!(&env, FlyStellarError::InvalidStatus);
+            let mut pos = out.len();
+            while pos > 0 {
+                let prev = out.get_unchecked(pos - 1);
+                if (prev.depart_at, prev.id.clone()) <= (flight.depart_at, flight.id.clone()) {
+                    break;
+                }
+                pos -= 1;
+            }
+            if pos < MAX_PAGE_LIMIT {
+                out.insert(pos, flight);
+                if out.len() > MAX_PAGE_LIMIT {
+                    out.pop_back();
+                }
+            }
         }
-
-        flight.status = new_status;
-        env.storage().persistent().set(&flight_key, &flight);
+        out
     }
 
-    pub fn get_flights_search(env: Env, src: Symbol, dest: Symbol) -> Vec<FlightDetails> {
-        let route_key = DataKey::RouteRegistry(src, dest);
-        let ids: Vec<BytesN<32>> = env
-            .storage()
-            .persistent()
-            .get(&route_key)
-            .unwrap_or(Vec::new(&env));
+    pub fn get_flights_admin(env: Env, admin: Address) -> Vec<FlightDetails> {
+        Self::require_admin(&env, &admin);
 
+        let ids = Self::all_flight_ids(&env);
         let mut out: Vec<FlightDetails> = Vec::new(&env);
         for id in ids.iter() {
-            let flight_key = DataKey::Flight(id);
-            if let Some(f) = env
-                .storage()
-                .persistent()
-                .get::<_, FlightDetails>(&flight_key)
-            {
+            if let Some(f) = Self::load_flight(&env, &id) {
                 out.push_back(f);
             }
         }
         out
     }
 
-    pub fn get_flights_admin(env: Env) -> Vec<FlightDetails> {
-        Self::require_admin(&env);
+    /// Number of live flights in each status, e.g. `[(booking, 40), (takeoff,
+    /// 12)]`. Walks the whole registry but reads only each flight's state
+    /// entry; archived flights are not counted.
+    pub fn status_histogram(env: Env, admin: Address) -> Vec<(Symbol, u32)> {
+        Self::require_admin(&env, &admin);
 
-        let ids: Vec<BytesN<32>> = env
-            .storage()
-            .persistent()
-            .get(&DataKey::GlobalRegistry)
-            .unwrap_or(Vec::new(&env));
+        let mut counts: Map<Symbol, u32> = Map::new(&env);
+        for id in Self::all_flight_ids(&env).iter() {
+            if let Some(state) = Self::fetch::<FlightState>(&env, &DataKey::FlightState(id)) {
+                let seen = counts.get(state.status.clone()).unwrap_or(0);
+                counts.set(state.status, seen + 1);
+            }
+        }
+        let mut out = Vec::new(&env);
+        for entry in counts.iter() {
+            out.push_back(entry);
+        }
+        out
+    }
+
+    /// Up to `MAX_PAGE_LIMIT` flights `operator` created, oldest first. Visible to
+    /// admins and to that operator.
+    pub fn get_flights_by_operator(
+        env: Env,
+        caller: Address,
+        operator: Address,
+    ) -> Vec<FlightDetails> {
+        if caller == operator {
+            Self::require_admin_or_operator(&env, &caller);
+        } else {
+            Self::require_admin(&env, &caller);
+        }
 
         let mut out: Vec<FlightDetails> = Vec::new(&env);
-        for id in ids.iter() {
-            let flight_key = DataKey::Flight(id);
-            if let Some(f) = env
-                .storage()
-                .persistent()
-                .get::<_, FlightDetails>(&flight_key)
-            {
-                out.push_back(f);
+        for id in Self::all_flight_ids(&env).iter() {
+            if out.len() >= MAX_PAGE_LIMIT {
+                break;
+            }
+            if let Some(f) = Self::load_flight(&env, &id) {
+                if f.created_by == operator {
+                    out.push_back(f);
+                }
             }
         }
         out
     }
 
-    pub fn get_flight_admin(env: Env, flight_id: BytesN<32>) -> FlightDetails {
-        Self::require_admin(&env);
-
-        let flight_key = DataKey::Flight(flight_id);
+    fn total_flights(env: &Env) -> u32 {
         env.storage()
+            .instance()
+            .get(&DataKey::TotalFlights)
+            .unwrap_or(0)
+    }
+
+    /// Every flight id in creation order, from whichever registry is current.
+    /// Index slots whose flight is gone are left for readers to skip.
+    fn all_flight_ids(env: &Env) -> Vec<BytesN<32>> {
+        if let Some(global) = Self::fetch(env, &DataKey::GlobalRegistry) {
+            return global;
+        }
+        let mut ids: Vec<BytesN<32>> = Vec::new(env);
+        for i in 0..Self::total_flights(env) {
+            if let Some(id) = Self::fetch(env, &DataKey::FlightIndex(i)) {
+                ids.push_back(id);
+            }
+        }
+        ids
+    }
+
+    /// Copy up to `max_items` flights from the legacy registry vector into the
+    /// index, removing the vector once everything is copied. Returns how many
+    /// remain; call again until it returns 0.
+    pub fn migrate_flight_index(env: Env, admin: Address, max_items: u32) -> u32 {
+        Self::require_admin(&env, &admin);
+        Self::audit(
+            &env,
+            AuditAction::ConfigChanged,
+            &admin,
+            None,
+            max_items as i128,
+        );
+
+        let global: Vec<BytesN<32>> = match Self::fetch(&env, &DataKey::GlobalRegistry) {
+            Some(global) => global,
+            None => return 0,
+        };
+        // Slots already written double as the migration cursor
+        let done = Self::total_flights(&env);
+        let end = done.saturating_add(max_items).min(global.len());
+        for i in done..end {
+            Self::persist(&env, &DataKey::FlightIndex(i), &global.get_unchecked(i));
+        }
+        env.storage().instance().set(&DataKey::TotalFlights, &end);
+
+        if end == global.len() {
+            env.storage().persistent().remove(&DataKey::GlobalRegistry);
+        }
+        global.len() - end
+    }
+
+    pub fn get_flight(env: Env, flight_id: BytesN<32>) -> FlightDetails {
+        Self::load_flight(&env, &flight_id)
+            .unwrap_or_else(|| panic_with_error!(&env, FlyStellarError::FlightNotFound))
+    }
+
+    /// `get_flight` for each of up to `MAX_BULK_IDS` ids, in order, with `None`
+    /// in place of unknown ids
+    pub fn get_flights_by_ids(env: Env, ids: Vec<BytesN<32>>) -> Vec<Option<FlightDetails>> {
+        Self::require_bulk_ids(&env, &ids);
+        let mut out = Vec::new(&env);
+        for id in ids.iter() {
+            out.push_back(Self::load_flight(&env, &id));
+        }
+        out
+    }
+
+    /// `get_ticket` for the passenger on each of up to `MAX_BULK_IDS` flights,
+    /// in order, with `None` where they hold no booking
+    pub fn get_tickets_by_ids(
+        env: Env,
+        passenger: Address,
+        ids: Vec<BytesN<32>>,
+    ) -> Vec<Option<PassengerRecord>> {
+        Self::require_bulk_ids(&env, &ids);
+        Self::require_private_read(&env, &passenger);
+        let mut out = Vec::new(&env);
+        for id in ids.iter() {
+            let holder = Self::holder(&env, &id, &passenger);
+            let pass_list: Vec<PassengerRecord> =
+                Self::fetch(&env, &DataKey::PassengerList(id)).unwrap_or(Vec::new(&env));
+            out.push_back(pass_list.iter().find(|rec| holder.is(&rec.passenger)));
+        }
+        out
+    }
+
+    fn require_bulk_ids(env: &Env, ids: &Vec<BytesN<32>>) {
+        if ids.len() > MAX_BULK_IDS {
+            panic_with_error!(env, FlyStellarError::InvalidInput);
+        }
+    }
+
+    /// Lightweight status read for clients polling for changes
+    pub fn get_flight_status(env: Env, flight_id: BytesN<32>) -> Symbol {
+        Self::get_flight(env, flight_id).status
+    }
+
+    /// Set gate, terminal, aircraft and airline for a flight that has not departed.
+    /// Stored apart from `FlightDetails` so updates don't rewrite the whole flight.
+    pub fn set_ops_info(env: Env, admin: Address, flight_id: BytesN<32>, info: OpsInfo) {
+        Self::require_admin(&env, &admin);
+        Self::audit(
+            &env,
+            AuditAction::FlightEdited,
+            &admin,
+            Some(flight_id.clone()),
+            0,
+        );
+
+        let flight = Self::get_flight(env.clone(), flight_id.clone());
+        if flight.status != Symbol::new(&env, "booking") {
+            panic_with_error!(&env, FlyStellarError::InvalidStatus);
+        }
+
+        Self::persist(&env, &DataKey::Ops(flight_id.clone()), &info);
+        OpsInfoUpdated { flight_id, info }.publish(&env);
+    }
+
+    pub fn get_ops_info(env: Env, flight_id: BytesN<32>) -> Option<OpsInfo> {
+        if !env
+            .storage()
             .persistent()
-            .get(&flight_key)
-            .expect("Flight not found")
+            .has(&DataKey::Flight(flight_id.clone()))
+        {
+            panic_with_error!(&env, FlyStellarError::FlightNotFound);
+        }
+        Self::fetch(&env, &DataKey::Ops(flight_id))
+    }
+
+    pub fn get_flight_admin(env: Env, admin: Address, flight_id: BytesN<32>) -> FlightDetails {
+        Self::require_admin(&env, &admin);
+
+        Self::load_flight(&env, &flight_id).expect("Flight not found")
+    }
+
+    fn add_lifetime(env: &Env, key: &DataKeyExt, amount: i128) {
+        if amount == 0 {
+            return;
+        }
+        let total: i128 = Self::fetch(env, key).unwrap_or(0);
+        Self::persist(env, key, &(total + amount));
+    }
+
+    /// Lifetime `(spend, refunds, active bookings)` of a passenger. Spend and
+    /// refunds are fares in base-token units, booking fees excluded; active
+    /// bookings counts the distinct flights the passenger holds a booking on
+    /// that are still open for booking.
+    pub fn get_passenger_stats(env: Env, passenger: Address) -> (i128, i128, u32) {
+        Self::require_private_read(&env, &passenger);
+        let spend: i128 =
+            Self::fetch(&env, &DataKeyExt::LifetimeSpend(passenger.clone())).unwrap_or(0);
+        let refunds: i128 =
+            Self::fetch(&env, &DataKeyExt::LifetimeRefund(passenger.clone())).unwrap_or(0);
+        let ids: Vec<BytesN<32>> =
+            Self::fetch(&env, &DataKey::PassengerRegistry(passenger)).unwrap_or(Vec::new(&env));
+        let booking = Symbol::new(&env, "booking");
+        let mut active: Vec<BytesN<32>> = Vec::new(&env);
+        for id in ids.iter() {
+            if active.contains(&id) {
+                continue;
+            }
+            if Self::load_flight(&env, &id).is_some_and(|f| f.status == booking) {
+                active.push_back(id);
+            }
+        }
+        (spend, refunds, active.len())
     }
 
     pub fn get_flights_pass(env: Env, passenger: Address) -> Vec<FlightDetails> {
+        Self::require_private_read(&env, &passenger);
         let pass_reg_key = DataKey::PassengerRegistry(passenger);
-        let ids: Vec<BytesN<32>> = env
-            .storage()
-            .persistent()
-            .get(&pass_reg_key)
-            .unwrap_or(Vec::new(&env));
+        let ids: Vec<BytesN<32>> = Self::fetch(&env, &pass_reg_key).unwrap_or(Vec::new(&env));
 
         let mut out: Vec<FlightDetails> = Vec::new(&env);
         for id in ids.iter() {
-            let flight_key = DataKey::Flight(id);
-            if let Some(f) = env
-                .storage()
-                .persistent()
-                .get::<_, FlightDetails>(&flight_key)
-            {
+            if let Some(f) = Self::load_flight(&env, &id) {
                 out.push_back(f);
             }
         }
         out
     }
 }
+
+pub mod testutils;
+
+mod test;
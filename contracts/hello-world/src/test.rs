@@ -1,21 +1,6000 @@
 #![cfg(test)]
 
 use super::*;
-use soroban_sdk::{vec, Env, String};
+use crate::testutils::{flight_id, flight_number, TestFixture, DEPART, PASSENGER_FUNDS};
+use soroban_sdk::testutils::{storage::Persistent as _, Address as _, Events, Ledger};
+use soroban_sdk::{contract, contractimpl, symbol_short, token, Bytes, Env, IntoVal};
+
+/// Queue a timelocked config change and apply it, then rewind the clock so the
+/// caller's schedule is unaffected
+fn apply_change(env: &Env, client: &FlyStellarClient, admin: &Address, change: ConfigChange) {
+    let now = env.ledger().timestamp();
+    client.queue_config_change(admin, &change);
+    env.ledger().set_timestamp(now + CONFIG_CHANGE_DELAY_SECS);
+    client.apply_config_change(admin, &change.param());
+    env.ledger().set_timestamp(now);
+}
+
+#[test]
+fn test_version() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    assert_eq!(fx.client.version(), CONTRACT_VERSION);
+    assert_eq!(fx.client.version(), 1);
+}
+
+#[test]
+fn test_route_exists() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let src = symbol_short!("DEL");
+    let dest = symbol_short!("BOM");
+
+    assert!(!fx.client.route_exists(&src, &dest));
+    assert_eq!(
+        fx.client.try_get_flights_search_checked(&src, &dest),
+        Err(Ok(FlyStellarError::RouteNotFound.into()))
+    );
+
+    fx.client.create_flight(
+        &fx.admin,
+        &flight_id(&env, 1),
+        &flight_number(&env, 1),
+        &10,
+        &100,
+        &src,
+        &dest,
+        &DEPART,
+    );
+
+    assert!(fx.client.route_exists(&src, &dest));
+    assert!(!fx.client.route_exists(&dest, &src));
+    assert_eq!(fx.client.get_flights_search_checked(&src, &dest).len(), 1);
+}
+
+fn passengers(env: &Env, client: &FlyStellarClient, id: &BytesN<32>) -> Vec<PassengerRecord> {
+    env.as_contract(&client.address, || {
+        env.storage()
+            .persistent()
+            .get(&DataKey::PassengerList(id.clone()))
+            .unwrap_or(Vec::new(env))
+    })
+}
+
+#[test]
+fn test_voucher_discounts_fare() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let code = flight_id(&env, 9);
+    let id = fx.create_flight(1, 10, 100);
+    fx.client
+        .create_voucher(&fx.admin, &code, &VoucherKind::Percent, &25, &1, &1_000);
+
+    let alice = fx.new_passenger();
+    fx.client.buy_ticket(
+        &id,
+        &alice,
+        &symbol_short!("veg"),
+        &1,
+        &Some(code.clone()),
+        &PassengerType::Adult,
+        &None,
+    );
+
+    let rec = passengers(&env, &fx.client, &id).get(0).unwrap();
+    assert_eq!(rec.paid, 75);
+    assert_eq!(rec.voucher, Some(code.clone()));
+    assert_eq!(fx.client.get_voucher(&code).unwrap().uses_left, 0);
+}
+
+#[test]
+fn test_fixed_voucher_never_goes_below_zero() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let code = flight_id(&env, 9);
+    let id = fx.create_flight(1, 10, 100);
+    fx.client
+        .create_voucher(&fx.admin, &code, &VoucherKind::Fixed, &500, &1, &1_000);
+
+    let alice = fx.new_passenger();
+    fx.client.buy_ticket(
+        &id,
+        &alice,
+        &symbol_short!("veg"),
+        &1,
+        &Some(code),
+        &PassengerType::Adult,
+        &None,
+    );
+    assert_eq!(passengers(&env, &fx.client, &id).get(0).unwrap().paid, 0);
+}
+
+#[test]
+fn test_voucher_errors() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let single = flight_id(&env, 9);
+    let multi = flight_id(&env, 8);
+    let revoked = flight_id(&env, 7);
+    let id = fx.create_flight(1, 10, 100);
+    fx.client
+        .create_voucher(&fx.admin, &single, &VoucherKind::Fixed, &10, &1, &1_000);
+    fx.client
+        .create_voucher(&fx.admin, &multi, &VoucherKind::Fixed, &10, &5, &1_000);
+    fx.client
+        .create_voucher(&fx.admin, &revoked, &VoucherKind::Fixed, &10, &5, &1_000);
+    fx.client.revoke_voucher(&fx.admin, &revoked);
+
+    let alice = fx.new_passenger();
+    let bob = fx.new_passenger();
+    let carol = fx.new_passenger();
+    let details = symbol_short!("veg");
+
+    // Reuse of a single-use voucher
+    fx.client.buy_ticket(
+        &id,
+        &alice,
+        &details,
+        &1,
+        &Some(single.clone()),
+        &PassengerType::Adult,
+        &None,
+    );
+    assert_eq!(
+        fx.client.try_buy_ticket(
+            &id,
+            &bob,
+            &details,
+            &2,
+            &Some(single),
+            &PassengerType::Adult,
+            &None
+        ),
+        Err(Ok(FlyStellarError::VoucherExhausted.into()))
+    );
+
+    // Stacking a second voucher on the same passenger's flight
+    assert_eq!(
+        fx.client.try_buy_ticket(
+            &id,
+            &alice,
+            &details,
+            &2,
+            &Some(multi.clone()),
+            &PassengerType::Adult,
+            &None
+        ),
+        Err(Ok(FlyStellarError::VoucherNotStackable.into()))
+    );
+
+    // Revoked voucher
+    assert_eq!(
+        fx.client.try_buy_ticket(
+            &id,
+            &bob,
+            &details,
+            &2,
+            &Some(revoked),
+            &PassengerType::Adult,
+            &None
+        ),
+        Err(Ok(FlyStellarError::VoucherNotFound.into()))
+    );
+
+    // Expired voucher
+    env.ledger().set_timestamp(1_001);
+    assert_eq!(
+        fx.client.try_buy_ticket(
+            &id,
+            &carol,
+            &details,
+            &3,
+            &Some(multi),
+            &PassengerType::Adult,
+            &None
+        ),
+        Err(Ok(FlyStellarError::VoucherExpired.into()))
+    );
+}
+
+#[test]
+fn test_seat_map() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let id = fx.create_flight(1, 5, 100);
+
+    let alice = fx.new_passenger();
+    let bob = fx.new_passenger();
+    let details = symbol_short!("veg");
+
+    fx.client.buy_ticket(
+        &id,
+        &alice,
+        &details,
+        &3,
+        &None,
+        &PassengerType::Adult,
+        &None,
+    );
+    assert_eq!(
+        fx.client.get_seat_map(&id).get(3),
+        Some(PassengerId::Plain(alice.clone()))
+    );
+    assert_eq!(passengers(&env, &fx.client, &id).get(0).unwrap().seat, 3);
+
+    assert_eq!(
+        fx.client
+            .try_buy_ticket(&id, &bob, &details, &3, &None, &PassengerType::Adult, &None),
+        Err(Ok(FlyStellarError::SeatTaken.into()))
+    );
+    assert_eq!(
+        fx.client
+            .try_buy_ticket(&id, &bob, &details, &0, &None, &PassengerType::Adult, &None),
+        Err(Ok(FlyStellarError::InvalidInput.into()))
+    );
+    assert_eq!(
+        fx.client
+            .try_buy_ticket(&id, &bob, &details, &6, &None, &PassengerType::Adult, &None),
+        Err(Ok(FlyStellarError::InvalidInput.into()))
+    );
+
+    fx.client.cancel_ticket(&id, &alice, &None);
+    assert!(!fx.client.get_seat_map(&id).contains_key(3));
+
+    fx.client
+        .buy_ticket(&id, &bob, &details, &3, &None, &PassengerType::Adult, &None);
+    assert_eq!(
+        fx.client.get_seat_map(&id).get(3),
+        Some(PassengerId::Plain(bob))
+    );
+}
+
+#[test]
+fn test_sweep_no_shows() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let id = fx.create_flight(1, 5, 100);
+
+    let details = symbol_short!("veg");
+    let flyer = fx.new_passenger();
+    let no_show_a = fx.new_passenger();
+    let no_show_b = fx.new_passenger();
+    let cancelled = fx.new_passenger();
+    fx.client.buy_ticket(
+        &id,
+        &flyer,
+        &details,
+        &1,
+        &None,
+        &PassengerType::Adult,
+        &None,
+    );
+    fx.client.buy_ticket(
+        &id,
+        &no_show_a,
+        &details,
+        &2,
+        &None,
+        &PassengerType::Adult,
+        &None,
+    );
+    fx.client.buy_ticket(
+        &id,
+        &cancelled,
+        &details,
+        &3,
+        &None,
+        &PassengerType::Adult,
+        &None,
+    );
+    fx.client.buy_ticket(
+        &id,
+        &no_show_b,
+        &details,
+        &4,
+        &None,
+        &PassengerType::Adult,
+        &None,
+    );
+    fx.client.check_in(&id, &flyer);
+    fx.client.cancel_ticket(&id, &cancelled, &None);
+
+    // Only departed flights can be swept
+    assert_eq!(
+        fx.client.try_sweep_no_shows(&fx.admin, &id, &10),
+        Err(Ok(FlyStellarError::InvalidStatus.into()))
+    );
+
+    apply_change(
+        &env,
+        &fx.client,
+        &fx.admin,
+        ConfigChange::NoShowShare(8_000),
+    );
+    fx.client
+        .update_flight_status(&fx.admin, &id, &symbol_short!("takeoff"), &None);
+
+    // Check-in closes and cancellations are refused once departed
+    assert_eq!(
+        fx.client.try_check_in(&id, &no_show_a),
+        Err(Ok(FlyStellarError::InvalidStatus.into()))
+    );
+    assert_eq!(
+        fx.client.try_cancel_ticket(&id, &no_show_a, &None),
+        Err(Ok(FlyStellarError::InvalidStatus.into()))
+    );
+
+    // The cancellation already left its 10% fee in the pot
+    assert_eq!(fx.client.get_fee_pot(), 10);
+
+    // Batched: the first call covers the checked-in flyer and one no-show
+    assert_eq!(fx.client.sweep_no_shows(&fx.admin, &id, &2), 1);
+    assert_eq!(fx.client.get_airline_payout(&id), 80);
+    assert_eq!(fx.client.get_fee_pot(), 30);
+
+    assert_eq!(fx.client.sweep_no_shows(&fx.admin, &id, &2), 0);
+    assert_eq!(fx.client.get_airline_payout(&id), 160);
+    assert_eq!(fx.client.get_fee_pot(), 50);
+
+    // Idempotent once everything is settled
+    assert_eq!(fx.client.sweep_no_shows(&fx.admin, &id, &2), 0);
+    assert_eq!(fx.client.get_airline_payout(&id), 160);
+    assert_eq!(fx.client.get_fee_pot(), 50);
+
+    let list = passengers(&env, &fx.client, &id);
+    assert_eq!(list.len(), 3);
+    assert!(list.get(0).unwrap().checked_in);
+    assert!(!list.get(0).unwrap().settled);
+    assert!(list.get(1).unwrap().settled);
+    assert!(list.get(2).unwrap().settled);
+}
+
+#[test]
+fn test_initialize_once() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let other = Address::generate(&env);
+    assert_eq!(
+        fx.client.try_initialize(&other, &other),
+        Err(Ok(FlyStellarError::AlreadyInitialized.into()))
+    );
+}
+
+#[test]
+fn test_initialize_rejects_non_token() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let admin = Address::generate(&env);
+    let client = FlyStellarClient::new(&env, &env.register(FlyStellar, ()));
+
+    // Neither a plain address nor a contract without the token interface
+    let not_a_contract = Address::generate(&env);
+    let not_a_token = env.register(FlyStellar, ());
+    for token in [not_a_contract, not_a_token] {
+        assert_eq!(
+            client.try_initialize(&admin, &token),
+            Err(Ok(FlyStellarError::InvalidToken.into()))
+        );
+    }
+    assert!(client.try_get_token().is_err());
+
+    let asset = env.register_stellar_asset_contract_v2(admin.clone());
+    client.initialize(&admin, &asset.address());
+
+    // Accepted payment tokens are probed the same way
+    assert_eq!(
+        client.try_queue_config_change(
+            &admin,
+            &ConfigChange::AcceptedToken(Address::generate(&env), 1, 1)
+        ),
+        Err(Ok(FlyStellarError::InvalidToken.into()))
+    );
+}
+
+#[test]
+fn test_flight_balance_reconciles_with_token() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let id = fx.create_flight(1, 5, 100);
+
+    assert_eq!(
+        fx.client.try_get_flight_balance(&flight_id(&env, 2)),
+        Err(Ok(FlyStellarError::FlightNotFound.into()))
+    );
+    assert_eq!(fx.client.get_flight_balance(&id), FlightBalance::default());
+
+    let details = symbol_short!("veg");
+    let flyer = fx.new_passenger();
+    let canceller = fx.new_passenger();
+    let no_show = fx.new_passenger();
+    fx.client.buy_ticket(
+        &id,
+        &flyer,
+        &details,
+        &1,
+        &None,
+        &PassengerType::Adult,
+        &None,
+    );
+    fx.client.buy_ticket(
+        &id,
+        &canceller,
+        &details,
+        &2,
+        &None,
+        &PassengerType::Adult,
+        &None,
+    );
+    fx.client.buy_ticket(
+        &id,
+        &no_show,
+        &details,
+        &3,
+        &None,
+        &PassengerType::Adult,
+        &None,
+    );
+    assert_eq!(fx.balance(&flyer), 9_900);
+
+    fx.client.cancel_ticket(&id, &canceller, &None);
+    assert_eq!(fx.balance(&canceller), 9_990);
+
+    fx.client.check_in(&id, &flyer);
+    fx.client
+        .update_flight_status(&fx.admin, &id, &symbol_short!("takeoff"), &None);
+    fx.client.sweep_no_shows(&fx.admin, &id, &10);
+
+    let flight_balance = fx.client.get_flight_balance(&id);
+    assert_eq!(
+        flight_balance,
+        FlightBalance {
+            collected: 300,
+            refunded: 90,
+            fees: 20,
+            net_held: 190,
+            compensation: 0,
+            paid_out: 0,
+            transferred: 0,
+        }
+    );
+    assert_eq!(
+        fx.balance(&fx.client.address),
+        flight_balance.net_held + fx.client.get_fee_pot()
+    );
+}
+
+fn imported(who: &Address, paid: i128, seat: u32) -> PassengerRecord {
+    PassengerRecord {
+        passenger: PassengerId::Plain(who.clone()),
+        payer: PassengerId::Plain(who.clone()),
+        paid,
+        details: symbol_short!("legacy"),
+        seat,
+        voucher: None,
+        checked_in: false,
+        settled: false,
+        compensation: 0,
+        booked_at: 0,
+        passenger_type: PassengerType::Adult,
+        pay_token: None,
+        token_paid: paid,
+        fee_paid: 0,
+        // Reassigned on import; the tests below import in seat order
+        booking_seq: seat,
+        dispute: None,
+        promo: false,
+    }
+}
+
+#[test]
+fn test_import_passengers() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let id = fx.create_flight(1, 3, 100);
+
+    let a = Address::generate(&env);
+    let b = Address::generate(&env);
+    let c = Address::generate(&env);
+    let records = Vec::from_array(
+        &env,
+        [
+            imported(&a, 100, 1),
+            imported(&b, 80, 2),
+            imported(&c, 120, 3),
+        ],
+    );
+    fx.client.import_passengers(&fx.admin, &id, &records);
+    // Imported seats cross occupancy thresholds like bookings do
+    let crossed = env
+        .events()
+        .all()
+        .iter()
+        .filter(|(_, topics, _)| {
+            *topics
+                == (
+                    symbol_short!("flight"),
+                    symbol_short!("occupancy"),
+                    id.clone(),
+                )
+                    .into_val(&env)
+        })
+        .count();
+    assert_eq!(crossed, 3);
+
+    assert_eq!(
+        fx.client.get_flight_admin(&fx.admin, &id).passenger_count,
+        3
+    );
+    assert_eq!(passengers(&env, &fx.client, &id), records);
+    assert_eq!(fx.client.get_flights_pass(&b).get(0).unwrap().id, id);
+    assert_eq!(fx.client.get_flight_balance(&id).collected, 300);
+    assert_eq!(
+        fx.client.get_seat_map(&id).get(3),
+        Some(PassengerId::Plain(c))
+    );
+
+    // Capacity is enforced
+    let d = Address::generate(&env);
+    assert_eq!(
+        fx.client.try_import_passengers(
+            &fx.admin,
+            &id,
+            &Vec::from_array(&env, [imported(&d, 100, 1)])
+        ),
+        Err(Ok(FlyStellarError::FlightFull.into()))
+    );
+
+    // One lap infant per adult, as when booking
+    let infant = |who: &Address| PassengerRecord {
+        seat: 0,
+        passenger_type: PassengerType::Infant,
+        ..imported(who, 15, 0)
+    };
+    fx.client
+        .import_passengers(&fx.admin, &id, &Vec::from_array(&env, [infant(&a)]));
+    assert_eq!(
+        fx.client
+            .try_import_passengers(&fx.admin, &id, &Vec::from_array(&env, [infant(&a)])),
+        Err(Ok(FlyStellarError::InvalidInput.into()))
+    );
+    assert_eq!(
+        fx.client
+            .try_import_passengers(&fx.admin, &id, &Vec::from_array(&env, [infant(&d)])),
+        Err(Ok(FlyStellarError::InvalidInput.into()))
+    );
+    assert_eq!(passengers(&env, &fx.client, &id).len(), 4);
+}
+
+#[test]
+fn test_update_ticket_details() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let id = fx.create_flight(1, 5, 100);
+
+    let alice = fx.new_passenger();
+    let bob = fx.new_passenger();
+    fx.client.buy_ticket(
+        &id,
+        &alice,
+        &symbol_short!("veg"),
+        &1,
+        &None,
+        &PassengerType::Adult,
+        &None,
+    );
+
+    assert_eq!(
+        fx.client
+            .try_update_ticket_details(&id, &alice, &symbol_short!("")),
+        Err(Ok(FlyStellarError::InvalidInput.into()))
+    );
+    fx.client
+        .update_ticket_details(&id, &alice, &symbol_short!("vegan"));
+    let (contract, topics, data) = env.events().all().last().unwrap();
+    assert_eq!(contract, fx.client.address);
+    assert_eq!(
+        topics,
+        (
+            symbol_short!("ticket"),
+            symbol_short!("details"),
+            id.clone(),
+            alice.clone()
+        )
+            .into_val(&env)
+    );
+    let details: Symbol = data.into_val(&env);
+    assert_eq!(details, symbol_short!("vegan"));
+    assert_eq!(
+        passengers(&env, &fx.client, &id).get(0).unwrap().details,
+        symbol_short!("vegan")
+    );
+
+    assert_eq!(
+        fx.client
+            .try_update_ticket_details(&id, &bob, &symbol_short!("veg")),
+        Err(Ok(FlyStellarError::PassengerNotFound.into()))
+    );
+
+    fx.client.check_in(&id, &alice);
+    assert_eq!(
+        fx.client
+            .try_update_ticket_details(&id, &alice, &symbol_short!("veg")),
+        Err(Ok(FlyStellarError::InvalidStatus.into()))
+    );
+}
+
+#[test]
+fn test_update_flight_status_compare_and_swap() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let id = fx.create_flight(1, 5, 100);
+
+    fx.client.update_flight_status(
+        &fx.admin,
+        &id,
+        &symbol_short!("cancelled"),
+        &Some(symbol_short!("booking")),
+    );
+    assert_eq!(
+        fx.client.get_flight_admin(&fx.admin, &id).status,
+        symbol_short!("cancelled")
+    );
+
+    // A racing writer that still believes the flight is booking is rejected
+    assert_eq!(
+        fx.client.try_update_flight_status(
+            &fx.admin,
+            &id,
+            &symbol_short!("takeoff"),
+            &Some(symbol_short!("booking")),
+        ),
+        Err(Ok(FlyStellarError::StatusConflict.into()))
+    );
+    assert_eq!(
+        fx.client.get_flight_admin(&fx.admin, &id).status,
+        symbol_short!("cancelled")
+    );
+}
+
+#[test]
+fn test_overbooking_and_bump() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let details = symbol_short!("veg");
+    let id = fx.create_flight(1, 10, 100);
+
+    assert_eq!(
+        fx.client.try_set_overbook(&fx.admin, &id, &1_501),
+        Err(Ok(FlyStellarError::InvalidInput.into()))
+    );
+    fx.client.set_overbook(&fx.admin, &id, &1_000);
+
+    // Fill every physical seat
+    let mut booked = Vec::new(&env);
+    for seat in 1..=10u32 {
+        let who = fx.new_passenger();
+        fx.client.buy_ticket(
+            &id,
+            &who,
+            &details,
+            &seat,
+            &None,
+            &PassengerType::Adult,
+            &None,
+        );
+        booked.push_back(who);
+    }
+
+    // One extra (10% of 10) booking is allowed, without a seat
+    let extra = fx.new_passenger();
+    let late = fx.new_passenger();
+    assert_eq!(
+        fx.client.try_buy_ticket(
+            &id,
+            &extra,
+            &details,
+            &3,
+            &None,
+            &PassengerType::Adult,
+            &None
+        ),
+        Err(Ok(FlyStellarError::SeatTaken.into()))
+    );
+    fx.client.buy_ticket(
+        &id,
+        &extra,
+        &details,
+        &0,
+        &None,
+        &PassengerType::Adult,
+        &None,
+    );
+    assert_eq!(
+        fx.client.get_flight_admin(&fx.admin, &id).passenger_count,
+        11
+    );
+    assert_eq!(fx.client.get_seat_map(&id).len(), 10);
+    assert_eq!(
+        fx.client.try_buy_ticket(
+            &id,
+            &late,
+            &details,
+            &0,
+            &None,
+            &PassengerType::Adult,
+            &None
+        ),
+        Err(Ok(FlyStellarError::FlightFull.into()))
+    );
+
+    // Build up the fee pot so compensation can be paid
+    let canceller = booked.get(0).unwrap();
+    fx.client.cancel_ticket(&id, &canceller, &None);
+    fx.client.buy_ticket(
+        &id,
+        &late,
+        &details,
+        &1,
+        &None,
+        &PassengerType::Adult,
+        &None,
+    );
+    assert_eq!(fx.client.get_fee_pot(), 10);
+
+    assert_eq!(
+        fx.client.try_bump_passenger(&fx.admin, &id, &extra, &11),
+        Err(Ok(FlyStellarError::InvalidInput.into()))
+    );
+    fx.client.bump_passenger(&fx.admin, &id, &extra, &10);
+    assert_eq!(fx.balance(&extra), 10_010);
+    assert_eq!(fx.client.get_fee_pot(), 0);
+    assert_eq!(
+        fx.client.get_flight_admin(&fx.admin, &id).passenger_count,
+        10
+    );
+    assert_eq!(fx.client.get_flights_pass(&extra).len(), 0);
+
+    // Back at physical capacity, nobody else can be bumped
+    assert_eq!(
+        fx.client.try_bump_passenger(&fx.admin, &id, &late, &0),
+        Err(Ok(FlyStellarError::InvalidStatus.into()))
+    );
+    assert_eq!(
+        fx.balance(&fx.client.address),
+        fx.client.get_flight_balance(&id).net_held + fx.client.get_fee_pot()
+    );
+}
+
+#[test]
+fn test_promotional_flight_is_free() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let id = fx.create_flight(1, 5, 100);
+    fx.client.set_promotional(&fx.admin, &id, &true);
+    assert!(fx.client.get_flight_admin(&fx.admin, &id).promotional);
+
+    let alice = fx.new_passenger();
+    fx.client.buy_ticket(
+        &id,
+        &alice,
+        &symbol_short!("veg"),
+        &1,
+        &None,
+        &PassengerType::Adult,
+        &None,
+    );
+
+    let rec = passengers(&env, &fx.client, &id).get(0).unwrap();
+    assert_eq!(rec.passenger, PassengerId::Plain(alice.clone()));
+    assert_eq!(rec.paid, 0);
+    assert_eq!(fx.balance(&alice), 10_000);
+    assert_eq!(fx.balance(&fx.client.address), 0);
+    assert_eq!(
+        fx.client.get_flight_admin(&fx.admin, &id).passenger_count,
+        1
+    );
+
+    // Back to a regular flight, the fare is charged again
+    fx.client.set_promotional(&fx.admin, &id, &false);
+    let bob = fx.new_passenger();
+    fx.client.buy_ticket(
+        &id,
+        &bob,
+        &symbol_short!("veg"),
+        &2,
+        &None,
+        &PassengerType::Adult,
+        &None,
+    );
+    assert_eq!(fx.balance(&bob), 9_900);
+}
+
+#[test]
+fn test_create_flight_rejects_past_departure() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    env.ledger().set_timestamp(DEPART);
+    assert_eq!(
+        fx.client.try_create_flight(
+            &fx.admin,
+            &flight_id(&env, 1),
+            &flight_number(&env, 1),
+            &5,
+            &100,
+            &symbol_short!("DEL"),
+            &symbol_short!("BOM"),
+            &DEPART
+        ),
+        Err(Ok(FlyStellarError::InvalidInput.into()))
+    );
+}
+
+#[test]
+fn test_delay_compensation() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let details = symbol_short!("veg");
+    let id = fx.create_flight(1, 5, 100);
+
+    let funder = fx.new_passenger();
+    fx.client.fund_fee_pot(&funder, &1_000);
+    fx.client.set_delay_tiers(
+        &fx.admin,
+        &Vec::from_array(
+            &env,
+            [
+                DelayTier {
+                    min_delay_secs: 3_600,
+                    flat: 10,
+                    bps: 0,
+                },
+                DelayTier {
+                    min_delay_secs: 3 * 3_600,
+                    flat: 10,
+                    bps: 5_000,
+                },
+            ],
+        ),
+    );
+
+    let alice = fx.new_passenger();
+    let bob = fx.new_passenger();
+    fx.client.buy_ticket(
+        &id,
+        &alice,
+        &details,
+        &1,
+        &None,
+        &PassengerType::Adult,
+        &None,
+    );
+    fx.client
+        .buy_ticket(&id, &bob, &details, &2, &None, &PassengerType::Adult, &None);
+
+    // Below the first tier: departure moves, nobody is credited
+    fx.client.mark_delayed(&fx.admin, &id, &(DEPART + 1_800));
+    let flight = fx.client.get_flight(&id);
+    assert_eq!(flight.depart_at, DEPART + 1_800);
+    assert_eq!(flight.delay_secs, 1_800);
+    assert_eq!(
+        fx.client.try_claim_refund(&alice),
+        Err(Ok(FlyStellarError::NoPendingRefund.into()))
+    );
+
+    // Crossing the first tier credits the flat amount
+    fx.client.mark_delayed(&fx.admin, &id, &(DEPART + 3_600));
+    assert_eq!(fx.client.get_flight(&id).delay_secs, 3_600);
+    assert_eq!(fx.client.get_fee_pot(), 980);
+
+    // Reaching the second tier tops up to its amount rather than stacking
+    fx.client
+        .mark_delayed(&fx.admin, &id, &(DEPART + 4 * 3_600));
+    assert_eq!(fx.client.get_fee_pot(), 880);
+    assert_eq!(fx.client.claim_refund(&alice), 60);
+    assert_eq!(fx.balance(&alice), 9_960);
+
+    // A further delay within the same tier credits nothing more
+    fx.client
+        .mark_delayed(&fx.admin, &id, &(DEPART + 5 * 3_600));
+    assert_eq!(fx.client.get_fee_pot(), 880);
+
+    // Cancelling after a qualifying delay refunds the full fare
+    fx.client.cancel_ticket(&id, &bob, &None);
+    assert_eq!(fx.balance(&bob), 10_000);
+    assert_eq!(fx.client.claim_refund(&bob), 60);
+    assert_eq!(fx.balance(&bob), 10_060);
+    assert_eq!(fx.client.get_fee_pot(), 880);
+
+    assert_eq!(
+        fx.client.try_mark_delayed(&fx.admin, &id, &DEPART),
+        Err(Ok(FlyStellarError::InvalidInput.into()))
+    );
+}
+
+#[test]
+fn test_get_flight_status() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let id = flight_id(&env, 1);
+    assert_eq!(
+        fx.client.try_get_flight_status(&id),
+        Err(Ok(FlyStellarError::FlightNotFound.into()))
+    );
+
+    fx.create_flight(1, 5, 100);
+    assert_eq!(fx.client.get_flight_status(&id), symbol_short!("booking"));
+
+    fx.client
+        .update_flight_status(&fx.admin, &id, &symbol_short!("cancelled"), &None);
+    assert_eq!(fx.client.get_flight_status(&id), symbol_short!("cancelled"));
+}
+
+#[test]
+fn test_ops_info() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let id = flight_id(&env, 1);
+    let info = OpsInfo {
+        gate: symbol_short!("B12"),
+        terminal: symbol_short!("T2"),
+        aircraft: symbol_short!("A320"),
+        airline: symbol_short!("FS"),
+    };
+    assert_eq!(
+        fx.client.try_set_ops_info(&fx.admin, &id, &info),
+        Err(Ok(FlyStellarError::FlightNotFound.into()))
+    );
+    assert_eq!(
+        fx.client.try_get_ops_info(&id),
+        Err(Ok(FlyStellarError::FlightNotFound.into()))
+    );
+
+    fx.create_flight(1, 5, 100);
+    assert_eq!(fx.client.get_ops_info(&id), None);
+
+    fx.client.set_ops_info(&fx.admin, &id, &info);
+    let (_, topics, data) = env.events().all().last().unwrap();
+    assert_eq!(
+        topics,
+        (symbol_short!("flight"), symbol_short!("ops"), id.clone()).into_val(&env)
+    );
+    let published: OpsInfo = data.into_val(&env);
+    assert_eq!(published, info);
+    assert_eq!(fx.client.get_ops_info(&id), Some(info.clone()));
+
+    fx.client
+        .update_flight_status(&fx.admin, &id, &symbol_short!("takeoff"), &None);
+    assert_eq!(
+        fx.client.try_set_ops_info(&fx.admin, &id, &info),
+        Err(Ok(FlyStellarError::InvalidStatus.into()))
+    );
+}
+
+#[test]
+fn test_route_fare_overrides_distance() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let src = symbol_short!("DEL");
+    let dest = symbol_short!("BOM");
+    let id = flight_id(&env, 1);
+    let other = flight_id(&env, 2);
+    fx.client.create_flight(
+        &fx.admin,
+        &id,
+        &flight_number(&env, 1),
+        &5,
+        &100,
+        &src,
+        &dest,
+        &DEPART,
+    );
+    fx.client.create_flight(
+        &fx.admin,
+        &other,
+        &flight_number(&env, 2),
+        &5,
+        &100,
+        &dest,
+        &src,
+        &DEPART,
+    );
+
+    assert_eq!(
+        fx.client.try_set_route_fare(&fx.admin, &src, &dest, &0),
+        Err(Ok(FlyStellarError::InvalidInput.into()))
+    );
+    fx.client.set_route_fare(&fx.admin, &src, &dest, &250);
+    assert_eq!(fx.client.get_route_fare(&src, &dest), Some(250));
+
+    let alice = fx.new_passenger();
+    fx.client.buy_ticket(
+        &id,
+        &alice,
+        &symbol_short!("veg"),
+        &1,
+        &None,
+        &PassengerType::Adult,
+        &None,
+    );
+    assert_eq!(fx.balance(&alice), 9_750);
+    assert_eq!(passengers(&env, &fx.client, &id).get(0).unwrap().paid, 250);
+
+    // The reverse route still prices by distance
+    fx.client.buy_ticket(
+        &other,
+        &alice,
+        &symbol_short!("veg"),
+        &1,
+        &None,
+        &PassengerType::Adult,
+        &None,
+    );
+    assert_eq!(fx.balance(&alice), 9_650);
+}
+
+#[test]
+fn test_sorted_search_pages() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let src = symbol_short!("DEL");
+    let dest = symbol_short!("BOM");
+    // (id, distance, departure)
+    let specs = [(1u8, 300i128, 5u64), (2, 100, 3), (3, 200, 1), (4, 100, 2)];
+    for (n, distance, hours) in specs {
+        fx.client.create_flight(
+            &fx.admin,
+            &flight_id(&env, n),
+            &flight_number(&env, n),
+            &5,
+            &distance,
+            &src,
+            &dest,
+            &(DEPART + hours * 3_600),
+        );
+    }
+    let ids = |page: &SearchPage| {
+        let mut out = Vec::new(&env);
+        for f in page.flights.iter() {
+            out.push_back(f.id.get(0).unwrap() as u32);
+        }
+        out
+    };
+
+    // Equal fares (flights 2 and 4) break ties by id
+    let page = fx
+        .client
+        .get_flights_search_page(&src, &dest, &SortKey::PriceAsc, &0, &2);
+    assert_eq!(ids(&page), Vec::from_array(&env, [2u32, 4]));
+    assert_eq!(page.total, 4);
+    assert!(!page.truncated);
+    assert_eq!(page.candidate_cap, 100);
+    let page = fx
+        .client
+        .get_flights_search_page(&src, &dest, &SortKey::PriceAsc, &2, &2);
+    assert_eq!(ids(&page), Vec::from_array(&env, [3u32, 1]));
+
+    let page = fx
+        .client
+        .get_flights_search_page(&src, &dest, &SortKey::PriceDesc, &0, &10);
+    assert_eq!(ids(&page), Vec::from_array(&env, [1u32, 3, 2, 4]));
+
+    let page = fx
+        .client
+        .get_flights_search_page(&src, &dest, &SortKey::DepartureAsc, &1, &2);
+    assert_eq!(ids(&page), Vec::from_array(&env, [4u32, 2]));
+
+    // Past the end is an empty page
+    let page = fx
+        .client
+        .get_flights_search_page(&src, &dest, &SortKey::DepartureAsc, &9, &2);
+    assert_eq!(page.flights.len(), 0);
+    assert_eq!(page.total, 4);
+}
+
+#[test]
+fn test_operator_list() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let a = Address::generate(&env);
+    let b = Address::generate(&env);
+
+    fx.client.add_operator(&fx.admin, &a);
+    fx.client.add_operator(&fx.admin, &b);
+    fx.client.add_operator(&fx.admin, &a);
+    assert_eq!(
+        fx.client.list_operators(&fx.admin),
+        Vec::from_array(&env, [a.clone(), b.clone()])
+    );
+    assert!(fx.client.is_operator(&a));
+
+    fx.client.remove_operator(&fx.admin, &a);
+    fx.client.remove_operator(&fx.admin, &a);
+    assert_eq!(
+        fx.client.list_operators(&fx.admin),
+        Vec::from_array(&env, [b.clone()])
+    );
+    assert!(!fx.client.is_operator(&a));
+
+    for _ in 1..20 {
+        fx.client.add_operator(&fx.admin, &Address::generate(&env));
+    }
+    assert_eq!(fx.client.list_operators(&fx.admin).len(), 20);
+    assert_eq!(
+        fx.client.try_add_operator(&fx.admin, &a),
+        Err(Ok(FlyStellarError::OperatorLimitReached.into()))
+    );
+}
+
+#[test]
+fn test_settled_flights_move_to_history() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let details = symbol_short!("veg");
+    let upcoming = flight_id(&env, 2);
+    let done = fx.create_flight(1, 5, 100);
+    fx.client.create_flight(
+        &fx.admin,
+        &upcoming,
+        &flight_number(&env, 2),
+        &5,
+        &100,
+        &symbol_short!("BOM"),
+        &symbol_short!("DEL"),
+        &DEPART,
+    );
+
+    let alice = fx.new_passenger();
+    for seat in 1..=2 {
+        fx.client.buy_ticket(
+            &done,
+            &alice,
+            &details,
+            &seat,
+            &None,
+            &PassengerType::Adult,
+            &None,
+        );
+    }
+    fx.client.buy_ticket(
+        &upcoming,
+        &alice,
+        &details,
+        &1,
+        &None,
+        &PassengerType::Adult,
+        &None,
+    );
+    fx.client.check_in(&done, &alice);
+    // Two seats on one flight are one registry entry
+    assert_eq!(fx.client.get_flights_pass(&alice).len(), 2);
+
+    // With everyone checked in there is nothing to sweep; settling archives
+    fx.client
+        .update_flight_status(&fx.admin, &done, &symbol_short!("takeoff"), &None);
+    fx.client.settle_flight(&fx.admin, &done);
+
+    let active = fx.client.get_flights_pass(&alice);
+    assert_eq!(active.len(), 1);
+    assert_eq!(active.get(0).unwrap().id, upcoming);
+    assert_eq!(
+        fx.client.get_history(&alice, &0, &10),
+        Vec::from_array(&env, [done.clone()])
+    );
+}
+
+#[test]
+fn test_archive_registry_migrates_in_batches() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let details = symbol_short!("veg");
+    let alice = fx.new_passenger();
+    for n in 1..=3u8 {
+        let id = flight_id(&env, n);
+        fx.client.create_flight(
+            &fx.admin,
+            &id,
+            &flight_number(&env, n),
+            &5,
+            &10,
+            &symbol_short!("DEL"),
+            &symbol_short!("BOM"),
+            &DEPART,
+        );
+        fx.client.buy_ticket(
+            &id,
+            &alice,
+            &details,
+            &1,
+            &None,
+            &PassengerType::Adult,
+            &None,
+        );
+    }
+    let open = fx.create_flight(4, 5, 10);
+    fx.client.buy_ticket(
+        &open,
+        &alice,
+        &details,
+        &1,
+        &None,
+        &PassengerType::Adult,
+        &None,
+    );
+    for n in 1..=3u8 {
+        fx.client.update_flight_status(
+            &fx.admin,
+            &flight_id(&env, n),
+            &symbol_short!("takeoff"),
+            &None,
+        );
+    }
+
+    assert_eq!(fx.client.archive_registry(&fx.admin, &alice, &2), 2);
+    assert_eq!(fx.client.archive_registry(&fx.admin, &alice, &2), 1);
+    assert_eq!(fx.client.archive_registry(&fx.admin, &alice, &2), 0);
+
+    assert_eq!(fx.client.get_flights_pass(&alice).len(), 1);
+    assert_eq!(fx.client.get_history(&alice, &0, &2).len(), 2);
+    assert_eq!(
+        fx.client.get_history(&alice, &1, &2),
+        Vec::from_array(&env, [flight_id(&env, 3)])
+    );
+}
+
+#[test]
+fn test_cancel_refunds_to_other_address() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let id = fx.create_flight(1, 5, 100);
+
+    let hot = fx.new_passenger();
+    let cold = Address::generate(&env);
+    fx.client.buy_ticket(
+        &id,
+        &hot,
+        &symbol_short!("veg"),
+        &1,
+        &None,
+        &PassengerType::Adult,
+        &None,
+    );
+    fx.client.cancel_ticket(&id, &hot, &Some(cold.clone()));
+
+    assert_eq!(fx.balance(&hot), 9_900);
+    assert_eq!(fx.balance(&cold), 90);
+    assert_eq!(fx.client.get_flights_pass(&hot).len(), 0);
+}
+
+#[test]
+fn test_admin_set() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let second = Address::generate(&env);
+    let outsider = Address::generate(&env);
+
+    assert_eq!(
+        fx.client.try_add_admin(&outsider, &outsider),
+        Err(Ok(FlyStellarError::Unauthorized.into()))
+    );
+
+    fx.client.add_admin(&fx.admin, &second);
+    fx.client.add_admin(&fx.admin, &second);
+    assert_eq!(
+        fx.client.get_admins(),
+        Vec::from_array(&env, [fx.admin.clone(), second.clone()])
+    );
+
+    // The new fx.admin can act on its own and remove the original one
+    fx.client.add_operator(&second, &outsider);
+    fx.client.remove_admin(&second, &fx.admin);
+    assert_eq!(
+        fx.client.get_admins(),
+        Vec::from_array(&env, [second.clone()])
+    );
+    assert_eq!(
+        fx.client.try_remove_operator(&fx.admin, &outsider),
+        Err(Ok(FlyStellarError::Unauthorized.into()))
+    );
+
+    assert_eq!(
+        fx.client.try_remove_admin(&second, &second),
+        Err(Ok(FlyStellarError::LastAdmin.into()))
+    );
+}
+
+#[test]
+fn test_buy_ticket_rejects_empty_details() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let id = fx.create_flight(1, 5, 100);
+
+    let alice = fx.new_passenger();
+    assert_eq!(
+        fx.client.try_buy_ticket(
+            &id,
+            &alice,
+            &symbol_short!(""),
+            &1,
+            &None,
+            &PassengerType::Adult,
+            &None
+        ),
+        Err(Ok(FlyStellarError::InvalidInput.into()))
+    );
+    assert_eq!(fx.balance(&alice), 10_000);
+
+    fx.client.buy_ticket(
+        &id,
+        &alice,
+        &symbol_short!("veg"),
+        &1,
+        &None,
+        &PassengerType::Adult,
+        &None,
+    );
+    assert_eq!(
+        passengers(&env, &fx.client, &id).get(0).unwrap().details,
+        symbol_short!("veg")
+    );
+}
+
+#[test]
+fn test_agent_books_on_behalf_of_passenger() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let id = fx.create_flight(1, 5, 100);
+
+    let agency = fx.new_passenger();
+    let employee = Address::generate(&env);
+    assert_eq!(
+        fx.client.try_buy_ticket_for(
+            &id,
+            &agency,
+            &employee,
+            &symbol_short!("veg"),
+            &1,
+            &None,
+            &PassengerType::Adult
+        ),
+        Err(Ok(FlyStellarError::Unauthorized.into()))
+    );
+
+    fx.client.authorize_agent(&employee, &agency);
+    let receipt = fx.client.buy_ticket_for(
+        &id,
+        &agency,
+        &employee,
+        &symbol_short!("veg"),
+        &1,
+        &None,
+        &PassengerType::Adult,
+    );
+    assert_eq!(
+        (receipt.flight_id, receipt.paid, receipt.seat),
+        (id.clone(), 100, 1)
+    );
+    assert_eq!(fx.balance(&agency), 9_900);
+    assert_eq!(fx.client.get_flights_pass(&employee).len(), 1);
+    assert_eq!(fx.client.get_flights_pass(&agency).len(), 0);
+    let rec = passengers(&env, &fx.client, &id).get(0).unwrap();
+    assert_eq!(rec.passenger, PassengerId::Plain(employee.clone()));
+    assert_eq!(rec.payer, PassengerId::Plain(agency.clone()));
+    assert_eq!(
+        fx.client.get_seat_map(&id).get(1),
+        Some(PassengerId::Plain(employee.clone()))
+    );
+
+    // The passenger cancels, but the refund returns to the agency that paid
+    fx.client.cancel_ticket(&id, &employee, &None);
+    assert_eq!(fx.balance(&agency), 9_990);
+    assert_eq!(fx.balance(&employee), 0);
+
+    fx.client.revoke_agent(&employee, &agency);
+    assert_eq!(
+        fx.client.try_buy_ticket_for(
+            &id,
+            &agency,
+            &employee,
+            &symbol_short!("veg"),
+            &1,
+            &None,
+            &PassengerType::Adult
+        ),
+        Err(Ok(FlyStellarError::Unauthorized.into()))
+    );
+}
+
+#[test]
+fn test_sales_in_window() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let id = fx.create_flight(1, 5, 100);
+
+    for (seat, at) in [(1u32, 1_000u64), (2, 2_000), (3, 3_000), (4, 4_000)] {
+        env.ledger().set_timestamp(at);
+        let p = fx.new_passenger();
+        fx.client.buy_ticket(
+            &id,
+            &p,
+            &symbol_short!("veg"),
+            &seat,
+            &None,
+            &PassengerType::Adult,
+            &None,
+        );
+    }
+
+    assert_eq!(fx.client.sales_in_window(&id, &2_000, &3_000), (2, 200));
+    assert_eq!(fx.client.sales_in_window(&id, &0, &10_000), (4, 400));
+    assert_eq!(fx.client.sales_in_window(&id, &4_001, &5_000), (0, 0));
+    assert_eq!(
+        fx.client.try_sales_in_window(&id, &3_000, &2_000),
+        Err(Ok(FlyStellarError::InvalidInput.into()))
+    );
+}
+
+#[test]
+fn test_get_fare_matches_charge() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let id = fx.create_flight(1, 5, 100);
+    fx.client.set_route_fare(
+        &fx.admin,
+        &symbol_short!("DEL"),
+        &symbol_short!("BOM"),
+        &250,
+    );
+
+    let alice = fx.new_passenger();
+    let quoted = fx
+        .client
+        .get_fare(&id, &Some(alice.clone()), &None, &PassengerType::Adult);
+    assert_eq!(quoted, 250);
+    fx.client.buy_ticket(
+        &id,
+        &alice,
+        &symbol_short!("veg"),
+        &1,
+        &None,
+        &PassengerType::Adult,
+        &None,
+    );
+    assert_eq!(fx.balance(&alice), 10_000 - quoted);
+
+    assert_eq!(
+        fx.client.try_get_fare(
+            &id,
+            &None,
+            &Some(symbol_short!("first")),
+            &PassengerType::Adult
+        ),
+        Err(Ok(FlyStellarError::InvalidInput.into()))
+    );
+    assert_eq!(
+        fx.client
+            .try_get_fare(&flight_id(&env, 9), &None, &None, &PassengerType::Adult),
+        Err(Ok(FlyStellarError::FlightNotFound.into()))
+    );
+    fx.client
+        .update_flight_status(&fx.admin, &id, &symbol_short!("takeoff"), &None);
+    assert_eq!(
+        fx.client
+            .try_get_fare(&id, &None, &None, &PassengerType::Adult),
+        Err(Ok(FlyStellarError::InvalidStatus.into()))
+    );
+}
+
+#[test]
+fn test_rejects_calls_before_initialize() {
+    let env = Env::default();
+    env.mock_all_auths();
+    let contract_id = env.register(FlyStellar, ());
+    let client = FlyStellarClient::new(&env, &contract_id);
+    let who = Address::generate(&env);
+    assert_eq!(client.get_admins().len(), 0);
+
+    assert_eq!(
+        client.try_buy_ticket(
+            &flight_id(&env, 1),
+            &who,
+            &symbol_short!("veg"),
+            &1,
+            &None,
+            &PassengerType::Adult,
+            &None
+        ),
+        Err(Ok(FlyStellarError::NotInitialized.into()))
+    );
+    assert_eq!(
+        client.try_create_flight(
+            &who,
+            &flight_id(&env, 1),
+            &flight_number(&env, 1),
+            &5,
+            &100,
+            &symbol_short!("DEL"),
+            &symbol_short!("BOM"),
+            &DEPART,
+        ),
+        Err(Ok(FlyStellarError::NotInitialized.into()))
+    );
+}
+
+#[test]
+fn test_cancellation_cutoff() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let id = fx.create_flight(1, 5, 100);
+    assert_eq!(fx.client.get_cancel_deadline(&id), DEPART);
+
+    apply_change(
+        &env,
+        &fx.client,
+        &fx.admin,
+        ConfigChange::CancelPolicy(CancelPolicy {
+            cutoff_secs: 3_600,
+            forfeit: false,
+        }),
+    );
+    let deadline = DEPART - 3_600;
+    assert_eq!(fx.client.get_cancel_deadline(&id), deadline);
+
+    let alice = fx.new_passenger();
+    let bob = fx.new_passenger();
+    fx.client.buy_ticket(
+        &id,
+        &alice,
+        &symbol_short!("veg"),
+        &1,
+        &None,
+        &PassengerType::Adult,
+        &None,
+    );
+    fx.client.buy_ticket(
+        &id,
+        &bob,
+        &symbol_short!("veg"),
+        &2,
+        &None,
+        &PassengerType::Adult,
+        &None,
+    );
+
+    // One second before the deadline the normal 90% refund applies
+    env.ledger().set_timestamp(deadline - 1);
+    fx.client.cancel_ticket(&id, &alice, &None);
+    assert_eq!(fx.balance(&alice), 9_990);
+
+    // At the deadline the refusing policy blocks the cancellation
+    env.ledger().set_timestamp(deadline);
+    assert_eq!(
+        fx.client.try_cancel_ticket(&id, &bob, &None),
+        Err(Ok(FlyStellarError::CancellationClosed.into()))
+    );
+
+    // The forfeiting policy accepts it but keeps the whole fare
+    apply_change(
+        &env,
+        &fx.client,
+        &fx.admin,
+        ConfigChange::CancelPolicy(CancelPolicy {
+            cutoff_secs: 3_600,
+            forfeit: true,
+        }),
+    );
+    let pot = fx.client.get_fee_pot();
+    fx.client.cancel_ticket(&id, &bob, &None);
+    assert_eq!(fx.balance(&bob), 9_900);
+    assert_eq!(fx.client.get_fee_pot(), pot + 100);
+    assert_eq!(fx.client.get_flight(&id).passenger_count, 0);
+
+    // A per-flight override replaces the default
+    fx.client
+        .set_flight_cancel_cutoff(&fx.admin, &id, &Some(60));
+    assert_eq!(fx.client.get_cancel_deadline(&id), DEPART - 60);
+    fx.client.set_flight_cancel_cutoff(&fx.admin, &id, &None);
+    assert_eq!(fx.client.get_cancel_deadline(&id), deadline);
+}
+
+#[test]
+fn test_airline_cancellation_ignores_cutoff() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let id = fx.create_flight(1, 5, 100);
+    apply_change(
+        &env,
+        &fx.client,
+        &fx.admin,
+        ConfigChange::CancelPolicy(CancelPolicy {
+            cutoff_secs: 3_600,
+            forfeit: false,
+        }),
+    );
+
+    let alice = fx.new_passenger();
+    fx.client.buy_ticket(
+        &id,
+        &alice,
+        &symbol_short!("veg"),
+        &1,
+        &None,
+        &PassengerType::Adult,
+        &None,
+    );
+    fx.client
+        .update_flight_status(&fx.admin, &id, &symbol_short!("cancelled"), &None);
+
+    env.ledger().set_timestamp(DEPART - 1);
+    fx.client.cancel_ticket(&id, &alice, &None);
+    assert_eq!(fx.balance(&alice), 10_000);
+}
+
+#[test]
+fn test_upcoming_departures() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    for (n, depart) in [(1u8, 5_000u64), (2, 2_000), (3, 9_000), (4, 3_000)] {
+        fx.client.create_flight(
+            &fx.admin,
+            &flight_id(&env, n),
+            &flight_number(&env, n),
+            &5,
+            &100,
+            &symbol_short!("DEL"),
+            &symbol_short!("BOM"),
+            &depart,
+        );
+    }
+
+    env.ledger().set_timestamp(2_500);
+    let upcoming = fx.client.get_upcoming_departures(&2_500);
+    assert_eq!(upcoming.len(), 2);
+    assert_eq!(upcoming.get(0).unwrap().id, flight_id(&env, 4));
+    assert_eq!(upcoming.get(1).unwrap().id, flight_id(&env, 1));
+
+    assert_eq!(fx.client.get_upcoming_departures(&0).len(), 0);
+    assert_eq!(fx.client.get_upcoming_departures(&u64::MAX).len(), 3);
+}
+
+#[test]
+fn test_seat_holds() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let id = fx.create_flight(1, 2, 100);
+    fx.client.set_hold_ledgers(&fx.admin, &10);
+
+    let alice = fx.new_passenger();
+    let bob = fx.new_passenger();
+    let carol = fx.new_passenger();
+
+    let seq = env.ledger().sequence();
+    assert_eq!(fx.client.hold_seat(&id, &alice), seq + 10);
+    assert_eq!(
+        fx.client.try_hold_seat(&id, &alice),
+        Err(Ok(FlyStellarError::HoldExists.into()))
+    );
+    fx.client.hold_seat(&id, &bob);
+
+    // Both places are held, so nobody else can book or hold
+    assert_eq!(
+        fx.client.try_buy_ticket(
+            &id,
+            &carol,
+            &symbol_short!("veg"),
+            &1,
+            &None,
+            &PassengerType::Adult,
+            &None
+        ),
+        Err(Ok(FlyStellarError::FlightFull.into()))
+    );
+    assert_eq!(
+        fx.client.try_hold_seat(&id, &carol),
+        Err(Ok(FlyStellarError::FlightFull.into()))
+    );
+
+    // A holder books against their own hold
+    assert_eq!(
+        fx.client
+            .get_fare(&id, &Some(alice.clone()), &None, &PassengerType::Adult),
+        100
+    );
+    fx.client.buy_ticket(
+        &id,
+        &alice,
+        &symbol_short!("veg"),
+        &1,
+        &None,
+        &PassengerType::Adult,
+        &None,
+    );
+
+    // Releasing early frees the place
+    fx.client.release_hold(&id, &bob);
+    fx.client.hold_seat(&id, &carol);
+
+    // An expired hold frees the place without any call
+    env.ledger().set_sequence_number(seq + 11);
+    fx.client.buy_ticket(
+        &id,
+        &bob,
+        &symbol_short!("veg"),
+        &2,
+        &None,
+        &PassengerType::Adult,
+        &None,
+    );
+    assert_eq!(fx.client.get_flight(&id).passenger_count, 2);
+}
+
+#[test]
+fn test_rebook_between_flights() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let dear = flight_id(&env, 2);
+    let full = flight_id(&env, 3);
+    let cheap = fx.create_flight(1, 5, 100);
+    fx.client.create_flight(
+        &fx.admin,
+        &dear,
+        &flight_number(&env, 2),
+        &5,
+        &250,
+        &symbol_short!("DEL"),
+        &symbol_short!("GOI"),
+        &DEPART,
+    );
+    fx.client.create_flight(
+        &fx.admin,
+        &full,
+        &flight_number(&env, 3),
+        &1,
+        &100,
+        &symbol_short!("DEL"),
+        &symbol_short!("BLR"),
+        &DEPART,
+    );
+
+    let alice = fx.new_passenger();
+    let bob = fx.new_passenger();
+    fx.client.buy_ticket(
+        &cheap,
+        &alice,
+        &symbol_short!("veg"),
+        &3,
+        &None,
+        &PassengerType::Adult,
+        &None,
+    );
+    fx.client.buy_ticket(
+        &full,
+        &bob,
+        &symbol_short!("veg"),
+        &1,
+        &None,
+        &PassengerType::Adult,
+        &None,
+    );
+
+    // Moving up charges only the difference
+    fx.client.rebook(&cheap, &dear, &alice);
+    assert_eq!(fx.balance(&alice), 9_750);
+    let (from, to) = (
+        fx.client.get_flight_balance(&cheap),
+        fx.client.get_flight_balance(&dear),
+    );
+    assert_eq!(
+        (from.collected, from.refunded, from.transferred),
+        (100, 0, 100)
+    );
+    assert_eq!((to.collected, to.refunded, to.transferred), (150, 0, -100));
+    assert_eq!(fx.client.get_flight(&cheap).passenger_count, 0);
+    assert_eq!(fx.client.get_flight(&dear).passenger_count, 1);
+    assert_eq!(
+        fx.client.get_seat_map(&dear).get(1),
+        Some(PassengerId::Plain(alice.clone()))
+    );
+    assert_eq!(fx.client.get_flights_pass(&alice).len(), 1);
+
+    // Moving down refunds the difference
+    fx.client.rebook(&dear, &cheap, &alice);
+    assert_eq!(fx.balance(&alice), 9_900);
+    assert_eq!(fx.client.get_flight(&dear).passenger_count, 0);
+    assert_eq!(fx.client.get_flight(&cheap).passenger_count, 1);
+    assert_eq!(fx.client.get_flight_balance(&dear).net_held, 0);
+    assert_eq!(fx.client.get_flight_balance(&cheap).net_held, 100);
+    assert_eq!(fx.client.get_flight_balance(&dear).refunded, 150);
+
+    // A full target flight reverts the whole move
+    assert_eq!(
+        fx.client.try_rebook(&cheap, &full, &alice),
+        Err(Ok(FlyStellarError::FlightFull.into()))
+    );
+    assert_eq!(fx.client.get_flight(&cheap).passenger_count, 1);
+    assert_eq!(fx.balance(&alice), 9_900);
+
+    // Paying the difference on a gifted ticket makes the passenger its payer
+    let gifter = fx.new_passenger();
+    let carol = fx.new_passenger();
+    fx.client
+        .gift_ticket(&cheap, &gifter, &carol, &symbol_short!("veg"));
+    fx.client.rebook(&cheap, &dear, &carol);
+    let rec = fx.client.get_ticket(&dear, &carol);
+    assert_eq!(rec.payer, PassengerId::Plain(carol.clone()));
+    fx.client.cancel_ticket(&dear, &carol, &None);
+    assert_eq!(fx.balance(&gifter), PASSENGER_FUNDS - 100);
+    assert_eq!(fx.balance(&carol), PASSENGER_FUNDS - 150 + 225);
+}
+
+#[test]
+fn test_flight_numbers() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let number = symbol_short!("FS204");
+    let day = 86_400;
+    fx.client.create_flight(
+        &fx.admin,
+        &flight_id(&env, 1),
+        &number,
+        &5,
+        &100,
+        &symbol_short!("DEL"),
+        &symbol_short!("BOM"),
+        &(day + 3_600),
+    );
+    let (_, topics, data) = env.events().all().last().unwrap();
+    assert_eq!(
+        topics,
+        (
+            symbol_short!("flight"),
+            symbol_short!("created"),
+            flight_id(&env, 1),
+            number.clone()
+        )
+            .into_val(&env)
+    );
+    let depart: u64 = data.into_val(&env);
+    assert_eq!(depart, day + 3_600);
+
+    // Same number on the same day is rejected, the next day is fine
+    assert_eq!(
+        fx.client.try_create_flight(
+            &fx.admin,
+            &flight_id(&env, 2),
+            &number,
+            &5,
+            &100,
+            &symbol_short!("DEL"),
+            &symbol_short!("BOM"),
+            &(day + 7_200),
+        ),
+        Err(Ok(FlyStellarError::FlightAlreadyExists.into()))
+    );
+    fx.client.create_flight(
+        &fx.admin,
+        &flight_id(&env, 3),
+        &number,
+        &5,
+        &100,
+        &symbol_short!("DEL"),
+        &symbol_short!("BOM"),
+        &(2 * day + 3_600),
+    );
+
+    assert_eq!(
+        fx.client.get_flight_by_number(&number).unwrap().id,
+        flight_id(&env, 1)
+    );
+    assert_eq!(
+        fx.client
+            .get_flights_search(&symbol_short!("DEL"), &symbol_short!("BOM"))
+            .get(0)
+            .unwrap()
+            .flight_number,
+        number
+    );
+    assert_eq!(
+        fx.client.get_flight_by_number(&symbol_short!("FS999")),
+        None
+    );
+
+    // Cancelling frees the number for a replacement on that day
+    fx.client.update_flight_status(
+        &fx.admin,
+        &flight_id(&env, 1),
+        &symbol_short!("cancelled"),
+        &None,
+    );
+    assert_eq!(
+        fx.client.get_flight_by_number(&number).unwrap().id,
+        flight_id(&env, 3)
+    );
+    fx.client.create_flight(
+        &fx.admin,
+        &flight_id(&env, 2),
+        &number,
+        &5,
+        &100,
+        &symbol_short!("DEL"),
+        &symbol_short!("BOM"),
+        &(day + 7_200),
+    );
+
+    // Once the day is over the lookup moves on to the next departure
+    env.ledger().set_timestamp(2 * day);
+    assert_eq!(
+        fx.client.get_flight_by_number(&number).unwrap().id,
+        flight_id(&env, 3)
+    );
+}
+
+#[test]
+fn test_privacy_mode_hides_addresses() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let id = fx.create_flight(1, 5, 100);
+    let alice = fx.new_passenger();
+    let bob = fx.new_passenger();
+    fx.client.buy_ticket(
+        &id,
+        &alice,
+        &symbol_short!("veg"),
+        &1,
+        &None,
+        &PassengerType::Adult,
+        &None,
+    );
+
+    assert_eq!(
+        fx.client.get_passengers(&id),
+        Vec::from_array(&env, [PassengerId::Plain(alice.clone())])
+    );
+
+    fx.client.set_privacy_mode(&fx.admin, &true);
+    let listed = fx.client.get_passengers(&id);
+    let hash = fx.client.passenger_hash(&id, &alice);
+    assert_eq!(
+        listed,
+        Vec::from_array(&env, [PassengerId::Hashed(hash.clone())])
+    );
+    assert!(!listed.contains(PassengerId::Plain(alice.clone())));
+    // The hash is bound to the flight, so it can't be matched across flights
+    assert_ne!(fx.client.passenger_hash(&flight_id(&env, 2), &alice), hash);
+
+    assert!(fx.client.is_booked(&id, &alice));
+    assert!(!fx.client.is_booked(&id, &bob));
+
+    // Nothing else maps seats, bookings or events back to an address
+    fx.client.buy_ticket(
+        &id,
+        &bob,
+        &symbol_short!("veg"),
+        &2,
+        &None,
+        &PassengerType::Adult,
+        &None,
+    );
+    let topics: Vec<Val> = (
+        symbol_short!("ticket"),
+        symbol_short!("booked"),
+        bob.clone(),
+    )
+        .into_val(&env);
+    assert!(!env.events().all().iter().any(|(_, t, _)| t == topics));
+    assert_eq!(
+        fx.client.try_get_seat_map(&id),
+        Err(Ok(FlyStellarError::Unauthorized.into()))
+    );
+    assert_eq!(
+        fx.client.get_taken_seats(&id),
+        Vec::from_array(&env, [1, 2])
+    );
+    assert_eq!(
+        fx.client.try_get_booking_holder(&id, &1),
+        Err(Ok(FlyStellarError::Unauthorized.into()))
+    );
+    assert_eq!(fx.client.get_ticket(&id, &bob).seat, 2);
+    assert_eq!(env.auths()[0].0, bob);
+}
+
+/// Whether `needle` appears anywhere in `haystack`
+fn contains_bytes(haystack: &Bytes, needle: &Bytes) -> bool {
+    (0..=haystack.len().saturating_sub(needle.len()))
+        .any(|i| haystack.slice(i..i + needle.len()) == *needle)
+}
+
+#[test]
+fn test_privacy_mode_stores_hashes() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let id = fx.create_flight(1, 5, 100);
+    let alice = fx.new_passenger();
+    let payer = fx.new_passenger();
+    let guest = Address::generate(&env);
+    fx.client.set_privacy_mode(&fx.admin, &true);
+    fx.book(&id, &alice, 1);
+    fx.client
+        .gift_ticket(&id, &payer, &guest, &symbol_short!("veg"));
+
+    // No address is readable from the ledger entries behind the flight
+    let (list, seats, index) = env.as_contract(&fx.client.address, || {
+        let storage = env.storage().persistent();
+        let list: Vec<PassengerRecord> = storage.get(&DataKey::PassengerList(id.clone())).unwrap();
+        let seats: Map<u32, PassengerId> = storage.get(&DataKey::SeatMap(id.clone())).unwrap();
+        let index: BookingIndex = storage.get(&DataKeyExt::BookingIndex(id.clone())).unwrap();
+        (list, seats, index)
+    });
+    let stored = [
+        list.clone().to_xdr(&env),
+        seats.to_xdr(&env),
+        index.to_xdr(&env),
+    ];
+    for who in [&alice, &payer, &guest] {
+        let needle = who.clone().to_xdr(&env);
+        assert!(stored.iter().all(|raw| !contains_bytes(raw, &needle)));
+    }
+    let hashed = |who: &Address| PassengerId::Hashed(fx.client.passenger_hash(&id, who));
+    let gift = list.get(1).unwrap();
+    assert_eq!(
+        (gift.passenger, gift.payer),
+        (hashed(&guest), hashed(&payer))
+    );
+
+    // Callers are matched through the hash
+    assert!(fx.client.is_booked(&id, &alice));
+    assert_eq!(fx.client.get_ticket(&id, &alice).passenger, hashed(&alice));
+    assert!(fx.client.verify_ticket(&id, &alice));
+    fx.client
+        .flag_ticket(&fx.admin, &id, &alice, &symbol_short!("chgback"));
+    let flagged: Vec<Val> = (symbol_short!("ticket"), symbol_short!("flagged")).into_val(&env);
+    assert!(!env
+        .events()
+        .all()
+        .iter()
+        .any(|(_, t, _)| t.slice(0..2) == flagged));
+    assert!(!fx.client.verify_ticket(&id, &alice));
+    assert_eq!(
+        fx.client.try_cancel_ticket(&id, &alice, &None),
+        Err(Ok(FlyStellarError::TicketDisputed.into()))
+    );
+    fx.client
+        .resolve_dispute(&fx.admin, &id, &alice, &DisputeOutcome::Clear);
+    assert_eq!(fx.client.get_flights_pass(&alice).len(), 1);
+    assert_eq!(env.auths()[0].0, alice);
+
+    // Refunds to a hashed payer wait until they claim them
+    fx.client
+        .update_flight_status(&fx.admin, &id, &symbol_short!("cancelled"), &None);
+    fx.client.refund_batch(&id, &5);
+    assert_eq!(fx.balance(&payer), PASSENGER_FUNDS - 100);
+    assert_eq!(fx.client.get_private_refund(&id, &payer), 100);
+    assert_eq!(fx.client.claim_private_refund(&id, &payer), 100);
+    assert_eq!(fx.balance(&payer), PASSENGER_FUNDS);
+    assert_eq!(fx.client.get_private_refund(&id, &alice), 100);
+}
+
+#[test]
+fn test_charter_flight() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let id = fx.create_flight(1, 5, 100);
+    let alice = fx.new_passenger();
+    let buyer = fx.new_passenger();
+    let late = fx.new_passenger();
+    fx.client.buy_ticket(
+        &id,
+        &alice,
+        &symbol_short!("veg"),
+        &1,
+        &None,
+        &PassengerType::Adult,
+        &None,
+    );
+
+    // The four remaining seats are sold at the per-seat fare
+    fx.client.charter_flight(&id, &buyer);
+    assert_eq!(fx.balance(&buyer), 9_600);
+    let flight = fx.client.get_flight(&id);
+    assert_eq!(flight.passenger_count, 5);
+    assert_eq!(flight.charter_holder, Some(buyer.clone()));
+    assert_eq!(
+        fx.client.get_charter(&id),
+        Some(Charter {
+            holder: buyer.clone(),
+            seats: 4,
+            paid: 400
+        })
+    );
+    assert_eq!(
+        fx.client.try_buy_ticket(
+            &id,
+            &late,
+            &symbol_short!("veg"),
+            &2,
+            &None,
+            &PassengerType::Adult,
+            &None
+        ),
+        Err(Ok(FlyStellarError::InvalidStatus.into()))
+    );
+    assert_eq!(
+        fx.client.try_charter_flight(&id, &late),
+        Err(Ok(FlyStellarError::InvalidStatus.into()))
+    );
+    assert_eq!(
+        fx.client.try_cancel_charter(&id, &late),
+        Err(Ok(FlyStellarError::Unauthorized.into()))
+    );
+
+    // Cancelling keeps the usual 10% fee on the whole amount
+    fx.client.cancel_charter(&id, &buyer);
+    assert_eq!(fx.balance(&buyer), 9_960);
+    assert_eq!(fx.client.get_fee_pot(), 40);
+    assert_eq!(fx.client.get_flight(&id).passenger_count, 1);
+    assert_eq!(fx.client.get_flight(&id).charter_holder, None);
+    assert_eq!(fx.client.get_charter(&id), None);
+
+    // An fx.admin-set charter price replaces the per-seat total
+    fx.client.set_charter_price(&fx.admin, &id, &Some(250));
+    fx.client.charter_flight(&id, &late);
+    assert_eq!(fx.balance(&late), 9_750);
+    assert!(fx.client.is_booked(&id, &alice));
+}
+
+#[test]
+fn test_max_fare_ceiling() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let src = symbol_short!("DEL");
+    let dest = symbol_short!("BOM");
+    assert_eq!(
+        fx.client.try_create_flight(
+            &fx.admin,
+            &flight_id(&env, 1),
+            &flight_number(&env, 1),
+            &5,
+            &1_000_000_000_000_000_000_000_000_000_000,
+            &src,
+            &dest,
+            &DEPART,
+        ),
+        Err(Ok(FlyStellarError::InvalidInput.into()))
+    );
+
+    fx.client.set_max_fare(&fx.admin, &500);
+    assert_eq!(
+        fx.client.try_create_flight(
+            &fx.admin,
+            &flight_id(&env, 1),
+            &flight_number(&env, 1),
+            &5,
+            &501,
+            &src,
+            &dest,
+            &DEPART,
+        ),
+        Err(Ok(FlyStellarError::InvalidInput.into()))
+    );
+    fx.client.create_flight(
+        &fx.admin,
+        &flight_id(&env, 1),
+        &flight_number(&env, 1),
+        &5,
+        &500,
+        &src,
+        &dest,
+        &DEPART,
+    );
+
+    assert_eq!(
+        fx.client.try_set_route_fare(&fx.admin, &src, &dest, &501),
+        Err(Ok(FlyStellarError::InvalidInput.into()))
+    );
+    fx.client.set_route_fare(&fx.admin, &src, &dest, &400);
+}
+
+#[test]
+fn test_passenger_type_fares() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let id = fx.create_flight(1, 2, 100);
+    apply_change(
+        &env,
+        &fx.client,
+        &fx.admin,
+        ConfigChange::PassengerTypeFares(PassengerTypeFares {
+            child_bps: 5_000,
+            infant_fee: 15,
+        }),
+    );
+    assert_eq!(
+        fx.client.get_fare(&id, &None, &None, &PassengerType::Child),
+        50
+    );
+    assert_eq!(
+        fx.client
+            .get_fare(&id, &None, &None, &PassengerType::Infant),
+        15
+    );
+
+    let parent = fx.new_passenger();
+    let kid = fx.new_passenger();
+    let details = symbol_short!("veg");
+
+    // An infant needs an adult already on the booking
+    assert_eq!(
+        fx.client.try_buy_ticket(
+            &id,
+            &parent,
+            &details,
+            &0,
+            &None,
+            &PassengerType::Infant,
+            &None
+        ),
+        Err(Ok(FlyStellarError::InvalidInput.into()))
+    );
+    fx.client.buy_ticket(
+        &id,
+        &parent,
+        &details,
+        &1,
+        &None,
+        &PassengerType::Adult,
+        &None,
+    );
+    fx.client.buy_ticket(
+        &id,
+        &parent,
+        &details,
+        &0,
+        &None,
+        &PassengerType::Infant,
+        &None,
+    );
+    assert_eq!(
+        fx.client.try_buy_ticket(
+            &id,
+            &parent,
+            &details,
+            &0,
+            &None,
+            &PassengerType::Infant,
+            &None
+        ),
+        Err(Ok(FlyStellarError::InvalidInput.into()))
+    );
+    fx.client
+        .buy_ticket(&id, &kid, &details, &2, &None, &PassengerType::Child, &None);
+
+    // The infant took no seat, so the two seats are the adult's and the child's
+    assert_eq!(fx.client.get_flight(&id).passenger_count, 2);
+    assert_eq!(fx.balance(&parent), 10_000 - 100 - 15);
+    assert_eq!(fx.balance(&kid), 10_000 - 50);
+    let (manifest, total) = fx.client.get_manifest(&fx.admin, &id, &0, &10);
+    assert_eq!(total, 3);
+    assert_eq!(manifest.len(), 3);
+    assert_eq!(
+        manifest.get(1).unwrap().passenger_type,
+        PassengerType::Infant
+    );
+    assert_eq!(
+        manifest.get(2).unwrap().passenger_type,
+        PassengerType::Child
+    );
+
+    // Cancelling refunds 90% of what each record actually paid
+    fx.client.cancel_ticket(&id, &parent, &None);
+    assert_eq!(fx.balance(&parent), 10_000 - 100 - 15 + 90 + 13);
+    assert_eq!(fx.client.get_flight(&id).passenger_count, 1);
+}
+
+#[test]
+fn test_operators_create_flights() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let first = Address::generate(&env);
+    let second = Address::generate(&env);
+    let outsider = Address::generate(&env);
+    fx.client.add_operator(&fx.admin, &first);
+    fx.client.add_operator(&fx.admin, &second);
+
+    for (n, op) in [(1u8, &first), (2, &second), (3, &fx.admin)] {
+        fx.client.create_flight(
+            op,
+            &flight_id(&env, n),
+            &flight_number(&env, n),
+            &5,
+            &100,
+            &symbol_short!("DEL"),
+            &symbol_short!("BOM"),
+            &DEPART,
+        );
+    }
+    assert_eq!(
+        fx.client
+            .get_flight_admin(&fx.admin, &flight_id(&env, 1))
+            .created_by,
+        first
+    );
+    assert_eq!(
+        fx.client
+            .get_flight_admin(&fx.admin, &flight_id(&env, 2))
+            .created_by,
+        second
+    );
+    assert_eq!(
+        fx.client
+            .get_flight_admin(&fx.admin, &flight_id(&env, 3))
+            .created_by,
+        fx.admin
+    );
+
+    assert_eq!(
+        fx.client.try_create_flight(
+            &outsider,
+            &flight_id(&env, 4),
+            &flight_number(&env, 4),
+            &5,
+            &100,
+            &symbol_short!("DEL"),
+            &symbol_short!("BOM"),
+            &DEPART,
+        ),
+        Err(Ok(FlyStellarError::Unauthorized.into()))
+    );
+}
+
+#[test]
+fn test_refund_batch_resumes() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let id = fx.create_flight(1, 5, 100);
+    let mut booked = Vec::new(&env);
+    for seat in 1..=5u32 {
+        let p = fx.new_passenger();
+        fx.client.buy_ticket(
+            &id,
+            &p,
+            &symbol_short!("veg"),
+            &seat,
+            &None,
+            &PassengerType::Adult,
+            &None,
+        );
+        booked.push_back(p);
+    }
+
+    assert_eq!(
+        fx.client.try_refund_batch(&id, &2),
+        Err(Ok(FlyStellarError::InvalidStatus.into()))
+    );
+    fx.client
+        .update_flight_status(&fx.admin, &id, &symbol_short!("cancelled"), &None);
+
+    assert_eq!(fx.client.refund_batch(&id, &2), 3);
+    assert_eq!(fx.balance(&booked.get(0).unwrap()), 10_000);
+    assert_eq!(fx.balance(&booked.get(2).unwrap()), 9_900);
+
+    // Half-way through, nothing may reshuffle or settle the flight
+    assert_eq!(
+        fx.client
+            .try_cancel_ticket(&id, &booked.get(4).unwrap(), &None),
+        Err(Ok(FlyStellarError::RefundsPending.into()))
+    );
+    assert_eq!(
+        fx.client
+            .try_update_flight_status(&fx.admin, &id, &symbol_short!("takeoff"), &None),
+        Err(Ok(FlyStellarError::RefundsPending.into()))
+    );
+
+    assert_eq!(fx.client.refund_batch(&id, &2), 1);
+    let (_, topics, _) = env.events().all().last().unwrap();
+    assert_eq!(
+        topics,
+        (
+            symbol_short!("booking"),
+            symbol_short!("refunded"),
+            id.clone(),
+            booked.get(3).unwrap()
+        )
+            .into_val(&env)
+    );
+    assert_eq!(fx.client.refund_batch(&id, &2), 0);
+    assert_eq!(fx.client.refund_batch(&id, &2), 0);
+    for p in booked.iter() {
+        assert_eq!(fx.balance(&p), 10_000);
+    }
+    assert_eq!(fx.client.get_flight_balance(&id).net_held, 0);
+
+    // A refunded passenger dropping their booking afterwards gets nothing twice
+    fx.client.cancel_ticket(&id, &booked.get(0).unwrap(), &None);
+    assert_eq!(fx.balance(&booked.get(0).unwrap()), 10_000);
+    assert_eq!(fx.client.get_fee_pot(), 0);
+}
+
+#[test]
+fn test_reconcile_flight_reports_drift() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let id = fx.create_flight(1, 5, 100);
+    for seat in 1..=2u32 {
+        let p = fx.new_passenger();
+        fx.client.buy_ticket(
+            &id,
+            &p,
+            &symbol_short!("veg"),
+            &seat,
+            &None,
+            &PassengerType::Adult,
+            &None,
+        );
+    }
+    assert_eq!(fx.client.reconcile_flight(&fx.admin, &id), (200, 200));
+    assert_eq!(env.events().all().len(), 0);
+
+    // Cancellations and releases keep the books matching
+    let gone = fx.new_passenger();
+    fx.client.buy_ticket(
+        &id,
+        &gone,
+        &symbol_short!("veg"),
+        &3,
+        &None,
+        &PassengerType::Adult,
+        &None,
+    );
+    fx.client.cancel_ticket(&id, &gone, &None);
+    fx.client.release_escrow(&fx.admin, &id, &5_000);
+    assert_eq!(fx.client.reconcile_flight(&fx.admin, &id), (200, 200));
+    assert_eq!(env.events().all().len(), 0);
+
+    // Corrupt one record behind the contract's back
+    env.as_contract(&fx.client.address, || {
+        let key = DataKey::PassengerList(id.clone());
+        let mut list: Vec<PassengerRecord> = env.storage().persistent().get(&key).unwrap();
+        let mut rec = list.get(0).unwrap();
+        rec.paid = 70;
+        list.set(0, rec);
+        env.storage().persistent().set(&key, &list);
+    });
+
+    assert_eq!(fx.client.reconcile_flight(&fx.admin, &id), (200, 170));
+    let (_, topics, _) = env.events().all().last().unwrap();
+    assert_eq!(
+        topics,
+        (symbol_short!("escrow"), symbol_short!("drift"), id.clone()).into_val(&env)
+    );
+}
+
+#[test]
+fn test_booking_writes_only_flight_state() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let id = fx.create_flight(1, 5, 100);
+    let alice = fx.new_passenger();
+    fx.client.buy_ticket(
+        &id,
+        &alice,
+        &symbol_short!("veg"),
+        &1,
+        &None,
+        &PassengerType::Adult,
+        &None,
+    );
+    let written = env.cost_estimate().resources().write_bytes;
+
+    // Before the split every booking rewrote the whole stitched record
+    let flight = fx.client.get_flight(&id);
+    let full = flight.clone().to_xdr(&env).len();
+    let state = FlightState {
+        status: flight.status.clone(),
+        passenger_count: flight.passenger_count,
+        charter_holder: flight.charter_holder.clone(),
+        status_changed_at: flight.status_changed_at,
+    }
+    .to_xdr(&env)
+    .len();
+    // 144 vs 656 bytes for the flight entry. That was ~15% of a booking's
+    // total writes; entries bookings have since gained, such as lifetime
+    // spend, bring the share down to ~13%, so the byte saving is pinned too.
+    assert!(state * 4 < full);
+    assert!(full - state >= 500);
+    let before = written + (full - state);
+    assert!((before - written) * 100 / before >= 12);
+}
+
+#[test]
+fn test_max_flights_cap() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let src = symbol_short!("DEL");
+    let dest = symbol_short!("BOM");
+    assert_eq!(fx.client.get_max_flights(), 10_000);
+
+    fx.client.set_max_flights(&fx.admin, &2);
+    for n in 1..=2u8 {
+        fx.client.create_flight(
+            &fx.admin,
+            &flight_id(&env, n),
+            &flight_number(&env, n),
+            &5,
+            &100,
+            &src,
+            &dest,
+            &DEPART,
+        );
+    }
+    assert_eq!(
+        fx.client.try_create_flight(
+            &fx.admin,
+            &flight_id(&env, 3),
+            &flight_number(&env, 3),
+            &5,
+            &100,
+            &src,
+            &dest,
+            &DEPART,
+        ),
+        Err(Ok(FlyStellarError::GlobalCapacityReached.into()))
+    );
+    assert_eq!(fx.client.get_flights_admin(&fx.admin).len(), 2);
+}
+
+/// Records every notification it receives as `(method, passenger, amount)`
+#[contract]
+pub struct RecordingHook;
+
+#[contractimpl]
+impl RecordingHook {
+    pub fn on_booking(env: Env, _flight_id: BytesN<32>, passenger: Address, amount: i128) {
+        Self::record(&env, symbol_short!("booking"), passenger, amount);
+    }
+
+    pub fn on_cancellation(env: Env, _flight_id: BytesN<32>, passenger: Address, amount: i128) {
+        Self::record(&env, symbol_short!("cancel"), passenger, amount);
+    }
+
+    pub fn calls(env: Env) -> Vec<(Symbol, Address, i128)> {
+        env.storage()
+            .instance()
+            .get(&symbol_short!("calls"))
+            .unwrap_or(Vec::new(&env))
+    }
+
+    fn record(env: &Env, method: Symbol, passenger: Address, amount: i128) {
+        let mut calls = Self::calls(env.clone());
+        calls.push_back((method, passenger, amount));
+        env.storage()
+            .instance()
+            .set(&symbol_short!("calls"), &calls);
+    }
+}
+
+/// Rejects every notification
+#[contract]
+pub struct FailingHook;
+
+#[contractimpl]
+impl FailingHook {
+    pub fn on_booking(_env: Env, _flight_id: BytesN<32>, _passenger: Address, _amount: i128) {
+        panic!("hook down");
+    }
+}
+
+#[test]
+fn test_hook_notified_on_booking_and_cancellation() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let id = fx.create_flight(1, 5, 100);
+    let hook_id = env.register(RecordingHook, ());
+    let hook = RecordingHookClient::new(&env, &hook_id);
+    fx.client.set_hook(&fx.admin, &hook_id, &true);
+    assert_eq!(
+        fx.client.get_hook(),
+        Some(HookConfig {
+            hook: hook_id.clone(),
+            strict: true
+        })
+    );
+
+    let alice = fx.new_passenger();
+    fx.client.buy_ticket(
+        &id,
+        &alice,
+        &symbol_short!("veg"),
+        &1,
+        &None,
+        &PassengerType::Adult,
+        &None,
+    );
+    fx.client.cancel_ticket(&id, &alice, &None);
+    assert_eq!(
+        hook.calls(),
+        Vec::from_array(
+            &env,
+            [
+                (symbol_short!("booking"), alice.clone(), 100),
+                (symbol_short!("cancel"), alice.clone(), 90),
+            ]
+        )
+    );
+
+    fx.client.clear_hook(&fx.admin);
+    fx.client.buy_ticket(
+        &id,
+        &alice,
+        &symbol_short!("veg"),
+        &1,
+        &None,
+        &PassengerType::Adult,
+        &None,
+    );
+    assert_eq!(hook.calls().len(), 2);
+}
+
+#[test]
+fn test_failing_hook_blocks_only_in_strict_mode() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let id = fx.create_flight(1, 5, 100);
+    let hook_id = env.register(FailingHook, ());
+    let alice = fx.new_passenger();
+
+    fx.client.set_hook(&fx.admin, &hook_id, &true);
+    assert!(fx
+        .client
+        .try_buy_ticket(
+            &id,
+            &alice,
+            &symbol_short!("veg"),
+            &1,
+            &None,
+            &PassengerType::Adult,
+            &None,
+        )
+        .is_err());
+    assert_eq!(fx.client.get_flight(&id).passenger_count, 0);
+
+    // Lenient mode swallows both the panicking booking hook and the missing
+    // cancellation hook
+    fx.client.set_hook(&fx.admin, &hook_id, &false);
+    fx.client.buy_ticket(
+        &id,
+        &alice,
+        &symbol_short!("veg"),
+        &1,
+        &None,
+        &PassengerType::Adult,
+        &None,
+    );
+    assert_eq!(fx.client.get_flight(&id).passenger_count, 1);
+    fx.client.cancel_ticket(&id, &alice, &None);
+    assert_eq!(fx.client.get_flight(&id).passenger_count, 0);
+}
+
+#[test]
+fn test_compute_fare_flat() {
+    assert_eq!(compute_fare(100, 10_000, 0, (0, 5)), 100);
+    assert_eq!(compute_fare(100, 10_000, 0, (5, 5)), 100);
+    assert_eq!(compute_fare(0, 10_000, 5_000, (5, 5)), 0);
+}
+
+#[test]
+fn test_compute_fare_class_only() {
+    assert_eq!(compute_fare(100, 7_500, 0, (2, 5)), 75);
+    assert_eq!(compute_fare(100, 25_000, 0, (2, 5)), 250);
+    // 99 * 0.3333 = 32.997, rounded down
+    assert_eq!(compute_fare(99, 3_333, 0, (0, 5)), 32);
+}
+
+#[test]
+fn test_compute_fare_surge_only() {
+    // The surge ramps with occupancy and tops out on a full flight
+    assert_eq!(compute_fare(100, 10_000, 2_000, (0, 4)), 100);
+    assert_eq!(compute_fare(100, 10_000, 2_000, (2, 4)), 110);
+    assert_eq!(compute_fare(100, 10_000, 2_000, (4, 4)), 120);
+    // Overbooked places pay no more than a full flight
+    assert_eq!(compute_fare(100, 10_000, 2_000, (5, 4)), 120);
+    // No capacity, no surge
+    assert_eq!(compute_fare(100, 10_000, 2_000, (0, 0)), 100);
+}
+
+#[test]
+fn test_compute_fare_combined() {
+    // 200 * 1.5 * (1 + 0.1 * 1/2) = 315
+    assert_eq!(compute_fare(200, 15_000, 1_000, (1, 2)), 315);
+    // 7 * 0.75 * (1 + 0.1 * 1/3) = 5.425, rounded down once rather than per step
+    assert_eq!(compute_fare(7, 7_500, 1_000, (1, 3)), 5);
+    // 3 * 0.5 * (1 + 1 * 1/3) = 2 exactly, where stepwise rounding gives 1
+    assert_eq!(compute_fare(3, 5_000, 10_000, (1, 3)), 2);
+}
+
+#[test]
+fn test_insufficient_funds_checked_before_transfer() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let id = fx.create_flight(1, 5, 100);
+    let broke = Address::generate(&env);
+    token::StellarAssetClient::new(&env, &fx.client.get_token()).mint(&broke, &99);
+
+    assert!(!fx.client.can_afford(&id, &broke));
+    assert_eq!(
+        fx.client.try_buy_ticket(
+            &id,
+            &broke,
+            &symbol_short!("veg"),
+            &1,
+            &None,
+            &PassengerType::Adult,
+            &None
+        ),
+        Err(Ok(FlyStellarError::InsufficientFunds.into()))
+    );
+
+    token::StellarAssetClient::new(&env, &fx.client.get_token()).mint(&broke, &1);
+    assert!(fx.client.can_afford(&id, &broke));
+    fx.client.buy_ticket(
+        &id,
+        &broke,
+        &symbol_short!("veg"),
+        &1,
+        &None,
+        &PassengerType::Adult,
+        &None,
+    );
+    assert_eq!(fx.balance(&broke), 0);
+
+    // A flight that cannot be quoted is simply unaffordable
+    let rich = fx.new_passenger();
+    assert!(fx.client.can_afford(&id, &rich));
+    assert!(!fx.client.can_afford(&flight_id(&env, 9), &rich));
+    // The booking fee is charged on top of the fare
+    let snug = Address::generate(&env);
+    token::StellarAssetClient::new(&env, &fx.client.get_token()).mint(&snug, &100);
+    assert!(fx.client.can_afford(&id, &snug));
+    fx.client.set_booking_fee(&fx.admin, &5);
+    assert!(!fx.client.can_afford(&id, &snug));
+    assert_eq!(
+        fx.client.try_buy_ticket(
+            &id,
+            &snug,
+            &symbol_short!("veg"),
+            &2,
+            &None,
+            &PassengerType::Adult,
+            &None
+        ),
+        Err(Ok(FlyStellarError::InsufficientFunds.into()))
+    );
+    token::StellarAssetClient::new(&env, &fx.client.get_token()).mint(&snug, &5);
+    assert!(fx.client.can_afford(&id, &snug));
+    let unset = FlyStellarClient::new(&env, &env.register(FlyStellar, ()));
+    assert!(!unset.can_afford(&id, &rich));
+    fx.client
+        .update_flight_status(&fx.admin, &id, &symbol_short!("cancelled"), &None);
+    assert!(!fx.client.can_afford(&id, &rich));
+}
+
+#[test]
+fn test_total_seats_sold() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let mut ids = Vec::new(&env);
+    for n in 1..=2u8 {
+        let id = flight_id(&env, n);
+        fx.client.create_flight(
+            &fx.admin,
+            &id,
+            &flight_number(&env, n),
+            &5,
+            &100,
+            &symbol_short!("DEL"),
+            &symbol_short!("BOM"),
+            &DEPART,
+        );
+        ids.push_back(id);
+    }
+    assert_eq!(fx.client.total_seats_sold(), 0);
+
+    let alice = fx.new_passenger();
+    let bob = fx.new_passenger();
+    for id in ids.iter() {
+        fx.client.buy_ticket(
+            &id,
+            &alice,
+            &symbol_short!("veg"),
+            &1,
+            &None,
+            &PassengerType::Adult,
+            &None,
+        );
+    }
+    fx.client.buy_ticket(
+        &ids.get(1).unwrap(),
+        &bob,
+        &symbol_short!("veg"),
+        &2,
+        &None,
+        &PassengerType::Adult,
+        &None,
+    );
+    assert_eq!(fx.client.total_seats_sold(), 3);
+
+    fx.client.cancel_ticket(&ids.get(0).unwrap(), &alice, &None);
+    assert_eq!(fx.client.total_seats_sold(), 2);
+}
+
+#[test]
+fn test_passengers_page() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let id = fx.create_flight(1, 5, 100);
+    let mut booked = Vec::new(&env);
+    for seat in 1..=5u32 {
+        let p = fx.new_passenger();
+        fx.client.buy_ticket(
+            &id,
+            &p,
+            &symbol_short!("veg"),
+            &seat,
+            &None,
+            &PassengerType::Adult,
+            &None,
+        );
+        booked.push_back(p);
+    }
+
+    let (page, total) = fx.client.get_passengers_page(&id, &1, &2);
+    assert_eq!(total, 5);
+    assert_eq!(page.len(), 2);
+    assert_eq!(
+        page.get(0).unwrap().passenger,
+        PassengerId::Plain(booked.get(1).unwrap())
+    );
+    assert_eq!(
+        page.get(1).unwrap().passenger,
+        PassengerId::Plain(booked.get(2).unwrap())
+    );
+
+    // The last page is short and anything past the end is empty
+    assert_eq!(fx.client.get_passengers_page(&id, &4, &2).0.len(), 1);
+    assert_eq!(
+        fx.client.get_passengers_page(&id, &9, &2),
+        (Vec::new(&env), 5)
+    );
+    // Oversized limits are capped
+    assert_eq!(fx.client.get_passengers_page(&id, &0, &1_000).0.len(), 5);
+
+    let (manifest, total) = fx.client.get_manifest(&fx.admin, &id, &3, &10);
+    assert_eq!((manifest.len(), total), (2, 5));
+
+    fx.client.set_privacy_mode(&fx.admin, &true);
+    assert_eq!(
+        fx.client.try_get_passengers_page(&id, &0, &2),
+        Err(Ok(FlyStellarError::Unauthorized.into()))
+    );
+    assert_eq!(fx.client.get_manifest(&fx.admin, &id, &0, &2).1, 5);
+}
+
+#[test]
+fn test_book_itinerary() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let second = flight_id(&env, 2);
+    let first = fx.create_flight(1, 5, 100);
+    fx.client.create_flight(
+        &fx.admin,
+        &second,
+        &flight_number(&env, 2),
+        &1,
+        &50,
+        &symbol_short!("BOM"),
+        &symbol_short!("GOI"),
+        &(DEPART + 7_200),
+    );
+    let legs = Vec::from_array(&env, [first.clone(), second.clone()]);
+
+    // Legs must connect in order
+    let alice = fx.new_passenger();
+    let reversed = Vec::from_array(&env, [second.clone(), first.clone()]);
+    assert_eq!(
+        fx.client
+            .try_book_itinerary(&reversed, &alice, &symbol_short!("veg")),
+        Err(Ok(FlyStellarError::InvalidInput.into()))
+    );
+
+    let (index, receipts) = fx
+        .client
+        .book_itinerary(&legs, &alice, &symbol_short!("veg"));
+    assert_eq!(index, 0);
+    let legs_paid: Vec<(BytesN<32>, i128, u32)> =
+        Vec::from_iter(&env, receipts.iter().map(|r| (r.flight_id, r.paid, r.seat)));
+    assert_eq!(
+        legs_paid,
+        Vec::from_array(&env, [(first.clone(), 100, 1), (second.clone(), 50, 1)])
+    );
+    assert_eq!(fx.client.get_flight(&first).passenger_count, 1);
+    assert_eq!(fx.client.get_flight(&second).passenger_count, 1);
+    assert_eq!(fx.balance(&alice), 10_000 - 150);
+    assert_eq!(fx.client.get_itinerary(&alice, &0), Some(legs.clone()));
+
+    // The second leg is now full, so nothing of bob's itinerary sticks
+    let bob = fx.new_passenger();
+    assert_eq!(
+        fx.client
+            .try_book_itinerary(&legs, &bob, &symbol_short!("veg")),
+        Err(Ok(FlyStellarError::FlightFull.into()))
+    );
+    assert_eq!(fx.client.get_flight(&first).passenger_count, 1);
+    assert!(!fx.client.is_booked(&first, &bob));
+    assert_eq!(fx.balance(&bob), 10_000);
+    assert_eq!(fx.client.get_itinerary(&bob, &0), None);
+}
+
+#[test]
+fn test_list_routes() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let routes = [
+        ("DEL", "BOM"),
+        ("BOM", "GOI"),
+        ("DEL", "BOM"),
+        ("GOI", "DEL"),
+    ];
+    for (n, (src, dest)) in routes.iter().enumerate() {
+        let n = n as u8 + 1;
+        fx.client.create_flight(
+            &fx.admin,
+            &flight_id(&env, n),
+            &flight_number(&env, n),
+            &5,
+            &100,
+            &Symbol::new(&env, src),
+            &Symbol::new(&env, dest),
+            &DEPART,
+        );
+    }
+
+    // A route shows up once, however many flights it has
+    let del_bom = (symbol_short!("DEL"), symbol_short!("BOM"));
+    let bom_goi = (symbol_short!("BOM"), symbol_short!("GOI"));
+    let goi_del = (symbol_short!("GOI"), symbol_short!("DEL"));
+    assert_eq!(
+        fx.client.list_routes(&0, &10),
+        Vec::from_array(&env, [del_bom.clone(), bom_goi.clone(), goi_del.clone()])
+    );
+    assert_eq!(
+        fx.client.list_routes(&1, &2),
+        Vec::from_array(&env, [goi_del])
+    );
+    assert_eq!(fx.client.list_routes(&5, &2).len(), 0);
+
+    assert_eq!(fx.client.route_flight_count(&del_bom.0, &del_bom.1), 2);
+    assert_eq!(fx.client.route_flight_count(&bom_goi.0, &bom_goi.1), 1);
+    assert_eq!(
+        fx.client
+            .route_flight_count(&symbol_short!("BOM"), &symbol_short!("DEL")),
+        0
+    );
+}
+
+#[test]
+fn test_adjust_paid() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let id = fx.create_flight(1, 5, 100);
+    let alice = fx.new_passenger();
+    fx.client.buy_ticket(
+        &id,
+        &alice,
+        &symbol_short!("veg"),
+        &1,
+        &None,
+        &PassengerType::Adult,
+        &None,
+    );
+
+    assert_eq!(
+        fx.client.try_adjust_paid(&fx.admin, &id, &alice, &-1),
+        Err(Ok(FlyStellarError::InvalidInput.into()))
+    );
+    let stranger = Address::generate(&env);
+    assert_eq!(
+        fx.client.try_adjust_paid(&fx.admin, &id, &stranger, &50),
+        Err(Ok(FlyStellarError::PassengerNotFound.into()))
+    );
+
+    // Goodwill reduction refunds the difference and keeps the books balanced
+    fx.client.adjust_paid(&fx.admin, &id, &alice, &60);
+    assert_eq!(fx.balance(&alice), 10_000 - 60);
+    assert_eq!(
+        fx.client
+            .get_manifest(&fx.admin, &id, &0, &1)
+            .0
+            .get(0)
+            .unwrap()
+            .paid,
+        60
+    );
+    let books = fx.client.get_flight_balance(&id);
+    assert_eq!((books.collected, books.refunded), (100, 40));
+    assert_eq!(fx.client.reconcile_flight(&fx.admin, &id), (60, 60));
+
+    // Correcting back up charges the payer again
+    fx.client.adjust_paid(&fx.admin, &id, &alice, &100);
+    assert_eq!(fx.balance(&alice), 10_000 - 100);
+    assert_eq!(fx.client.get_flight_balance(&id).collected, 140);
+    assert_eq!(fx.client.reconcile_flight(&fx.admin, &id), (100, 100));
+
+    // Departed fares are the airline's and can no longer be corrected
+    fx.client
+        .update_flight_status(&fx.admin, &id, &symbol_short!("takeoff"), &None);
+    assert_eq!(
+        fx.client.try_adjust_paid(&fx.admin, &id, &alice, &50),
+        Err(Ok(FlyStellarError::InvalidStatus.into()))
+    );
+}
+
+#[test]
+fn test_migrate_flight_index() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let create = |n: u8| {
+        fx.client.create_flight(
+            &fx.admin,
+            &flight_id(&env, n),
+            &flight_number(&env, n),
+            &5,
+            &100,
+            &symbol_short!("DEL"),
+            &symbol_short!("BOM"),
+            &DEPART,
+        );
+    };
+    let ids = |flights: Vec<FlightDetails>| {
+        let mut out = Vec::new(&env);
+        for f in flights.iter() {
+            out.push_back(f.id);
+        }
+        out
+    };
+    for n in 1..=3u8 {
+        create(n);
+    }
+
+    // Rewind storage to the legacy single-vector layout
+    env.as_contract(&fx.client.address, || {
+        let mut global: Vec<BytesN<32>> = Vec::new(&env);
+        for i in 0..3u32 {
+            let key = DataKey::FlightIndex(i);
+            let id: BytesN<32> = env.storage().persistent().get(&key).unwrap();
+            env.storage().persistent().remove(&key);
+            global.push_back(id);
+        }
+        env.storage()
+            .persistent()
+            .set(&DataKey::GlobalRegistry, &global);
+        env.storage().instance().remove(&DataKey::TotalFlights);
+    });
+    assert_eq!(fx.client.get_flights_admin(&fx.admin).len(), 3);
+
+    // Flights created mid-migration land in the legacy vector and still show up
+    create(4);
+    assert_eq!(fx.client.migrate_flight_index(&fx.admin, &3), 1);
+    assert_eq!(fx.client.get_flights_admin(&fx.admin).len(), 4);
+    assert_eq!(fx.client.migrate_flight_index(&fx.admin, &3), 0);
+    env.as_contract(&fx.client.address, || {
+        assert!(!env.storage().persistent().has(&DataKey::GlobalRegistry));
+    });
+
+    create(5);
+    let expected: Vec<BytesN<32>> = Vec::from_array(
+        &env,
+        [
+            flight_id(&env, 1),
+            flight_id(&env, 2),
+            flight_id(&env, 3),
+            flight_id(&env, 4),
+            flight_id(&env, 5),
+        ],
+    );
+    assert_eq!(ids(fx.client.get_flights_admin(&fx.admin)), expected);
+    assert_eq!(fx.client.migrate_flight_index(&fx.admin, &3), 0);
+}
+
+#[test]
+fn test_filtered_search() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let src = symbol_short!("DEL");
+    let dest = symbol_short!("BOM");
+    // (id, seats, departure): 1 open, 2 full, 3 departed, 4 cancelled
+    let specs = [
+        (1u8, 5u32, 7_200u64),
+        (2, 1, 7_200),
+        (3, 5, 0),
+        (4, 5, 7_200),
+    ];
+    for (n, seats, offset) in specs {
+        fx.client.create_flight(
+            &fx.admin,
+            &flight_id(&env, n),
+            &flight_number(&env, n),
+            &seats,
+            &100,
+            &src,
+            &dest,
+            &(DEPART + offset),
+        );
+    }
+    let alice = fx.new_passenger();
+    fx.client.buy_ticket(
+        &flight_id(&env, 2),
+        &alice,
+        &symbol_short!("veg"),
+        &1,
+        &None,
+        &PassengerType::Adult,
+        &None,
+    );
+    fx.client.update_flight_status(
+        &fx.admin,
+        &flight_id(&env, 4),
+        &symbol_short!("cancelled"),
+        &None,
+    );
+    env.ledger().set_timestamp(DEPART + 1);
+
+    let search = |exclude: u32| {
+        let page = fx.client.get_flights_search_filtered(
+            &src,
+            &dest,
+            &SortKey::PriceAsc,
+            &exclude,
+            &0,
+            &10,
+        );
+        let mut out = Vec::new(&env);
+        for f in page.flights.iter() {
+            out.push_back(f.id.get(0).unwrap() as u32);
+        }
+        assert_eq!(page.total, out.len());
+        out
+    };
+    let ids = |list: &[u32]| {
+        let mut out = Vec::new(&env);
+        for id in list {
+            out.push_back(*id);
+        }
+        out
+    };
+
+    assert_eq!(search(0), ids(&[1, 2, 3, 4]));
+    assert_eq!(search(SEARCH_EXCLUDE_FULL), ids(&[1, 3, 4]));
+    assert_eq!(search(SEARCH_EXCLUDE_DEPARTED), ids(&[1, 2, 4]));
+    assert_eq!(search(SEARCH_EXCLUDE_CLOSED), ids(&[1, 2, 3]));
+    assert_eq!(
+        search(SEARCH_EXCLUDE_FULL | SEARCH_EXCLUDE_DEPARTED),
+        ids(&[1, 4])
+    );
+    assert_eq!(
+        search(SEARCH_EXCLUDE_FULL | SEARCH_EXCLUDE_CLOSED),
+        ids(&[1, 3])
+    );
+    assert_eq!(
+        search(SEARCH_EXCLUDE_DEPARTED | SEARCH_EXCLUDE_CLOSED),
+        ids(&[1, 2])
+    );
+    assert_eq!(
+        search(SEARCH_EXCLUDE_FULL | SEARCH_EXCLUDE_DEPARTED | SEARCH_EXCLUDE_CLOSED),
+        ids(&[1])
+    );
+
+    // The unfiltered search is unchanged
+    let page = fx
+        .client
+        .get_flights_search_page(&src, &dest, &SortKey::PriceAsc, &0, &10);
+    assert_eq!(page.total, 4);
+}
+
+#[test]
+fn test_cancel_cooldown() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let id = fx.create_flight(1, 5, 100);
+    let alice = fx.new_passenger();
+    let book = || {
+        fx.client.try_buy_ticket(
+            &id,
+            &alice,
+            &symbol_short!("veg"),
+            &1,
+            &None,
+            &PassengerType::Adult,
+            &None,
+        )
+    };
+
+    // No cooldown by default
+    assert_eq!(fx.client.get_cancel_cooldown(), 0);
+    assert!(book().is_ok());
+    fx.client.cancel_ticket(&id, &alice, &None);
+    assert!(book().is_ok());
+
+    fx.client.set_cancel_cooldown(&fx.admin, &600);
+    env.ledger().set_timestamp(1_000);
+    fx.client.cancel_ticket(&id, &alice, &None);
+    assert_eq!(book(), Err(Ok(FlyStellarError::Cooldown.into())));
+    env.ledger().set_timestamp(1_599);
+    assert_eq!(book(), Err(Ok(FlyStellarError::Cooldown.into())));
+    env.ledger().set_timestamp(1_600);
+    assert!(book().is_ok());
+}
+
+#[test]
+fn test_reschedule_opens_free_cancellation_window() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let id = fx.create_flight(1, 5, 100);
+    let alice = fx.new_passenger();
+    let bob = fx.new_passenger();
+    let carol = fx.new_passenger();
+    for (who, seat) in [(&alice, 1u32), (&bob, 2)] {
+        fx.client.buy_ticket(
+            &id,
+            who,
+            &symbol_short!("veg"),
+            &seat,
+            &None,
+            &PassengerType::Adult,
+            &None,
+        );
+    }
+
+    env.ledger().set_timestamp(1_000);
+    assert_eq!(
+        fx.client
+            .try_reschedule_flight(&fx.admin, &id, &1_000, &2_000),
+        Err(Ok(FlyStellarError::InvalidInput.into()))
+    );
+    assert_eq!(
+        fx.client
+            .try_reschedule_flight(&fx.admin, &id, &DEPART, &DEPART),
+        Err(Ok(FlyStellarError::InvalidInput.into()))
+    );
+    fx.client.set_reschedule_grace(&fx.admin, &3_600);
+    fx.client
+        .reschedule_flight(&fx.admin, &id, &(DEPART + 600), &(DEPART + 8_000));
+    let (_, topics, _) = env.events().all().last().unwrap();
+    assert_eq!(
+        topics,
+        (
+            symbol_short!("flight"),
+            Symbol::new(&env, "rescheduled"),
+            id.clone()
+        )
+            .into_val(&env)
+    );
+    let flight = fx.client.get_flight(&id);
+    assert_eq!(flight.depart_at, DEPART + 600);
+    assert_eq!(flight.arrive_at, Some(DEPART + 8_000));
+
+    // Booked after the reschedule, so the normal 90% applies
+    env.ledger().set_timestamp(1_001);
+    fx.client.buy_ticket(
+        &id,
+        &carol,
+        &symbol_short!("veg"),
+        &3,
+        &None,
+        &PassengerType::Adult,
+        &None,
+    );
+    fx.client.cancel_ticket(&id, &carol, &None);
+    assert_eq!(fx.balance(&carol), 10_000 - 10);
+
+    fx.client.cancel_ticket(&id, &alice, &None);
+    assert_eq!(fx.balance(&alice), 10_000);
+
+    // Once the window closes normal rules resume
+    env.ledger().set_timestamp(1_000 + 3_600);
+    fx.client.cancel_ticket(&id, &bob, &None);
+    assert_eq!(fx.balance(&bob), 10_000 - 10);
+
+    fx.client
+        .update_flight_status(&fx.admin, &id, &symbol_short!("cancelled"), &None);
+    assert_eq!(
+        fx.client
+            .try_reschedule_flight(&fx.admin, &id, &(DEPART + 900), &(DEPART + 9_000)),
+        Err(Ok(FlyStellarError::InvalidStatus.into()))
+    );
+}
+
+#[test]
+fn test_reschedule_moves_flight_number() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let id = fx.create_flight(1, 5, 100);
+    let number = flight_number(&env, 1);
+    let day = DEPART / 86_400;
+
+    // Delayed past midnight, the number stays on the scheduled day
+    fx.client.mark_delayed(&fx.admin, &id, &(DEPART + 86_400));
+    assert_eq!(fx.client.get_flight_on_day(&number, &day).unwrap().id, id);
+
+    // Rescheduling moves the number to the new day and clears the delay
+    fx.client.reschedule_flight(
+        &fx.admin,
+        &id,
+        &(DEPART + 2 * 86_400),
+        &(DEPART + 2 * 86_400 + 1),
+    );
+    assert_eq!(fx.client.get_flight_on_day(&number, &day), None);
+    let moved = fx.client.get_flight_on_day(&number, &(day + 2)).unwrap();
+    assert_eq!((moved.id, moved.delay_secs), (id.clone(), 0));
+
+    // A flight given the freed day can't then move onto the taken one
+    fx.client.create_flight(
+        &fx.admin,
+        &flight_id(&env, 3),
+        &number,
+        &5,
+        &100,
+        &symbol_short!("DEL"),
+        &symbol_short!("BOM"),
+        &DEPART,
+    );
+    assert_eq!(
+        fx.client.try_reschedule_flight(
+            &fx.admin,
+            &flight_id(&env, 3),
+            &(DEPART + 2 * 86_400),
+            &(DEPART + 2 * 86_400 + 1)
+        ),
+        Err(Ok(FlyStellarError::FlightAlreadyExists.into()))
+    );
+
+    // A flight whose departure time has passed can't be moved
+    env.ledger().set_timestamp(DEPART + 2 * 86_400);
+    assert_eq!(
+        fx.client.try_reschedule_flight(
+            &fx.admin,
+            &id,
+            &(DEPART + 3 * 86_400),
+            &(DEPART + 3 * 86_400 + 1)
+        ),
+        Err(Ok(FlyStellarError::InvalidStatus.into()))
+    );
+}
+
+#[test]
+fn test_verify_and_fix_count() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let id = fx.create_flight(1, 5, 100);
+    let parent = fx.new_passenger();
+    fx.client.buy_ticket(
+        &id,
+        &parent,
+        &symbol_short!("veg"),
+        &1,
+        &None,
+        &PassengerType::Adult,
+        &None,
+    );
+    fx.client.buy_ticket(
+        &id,
+        &parent,
+        &symbol_short!("veg"),
+        &0,
+        &None,
+        &PassengerType::Infant,
+        &None,
+    );
+    assert!(fx.client.verify_count(&fx.admin, &id));
+
+    env.as_contract(&fx.client.address, || {
+        let key = DataKey::FlightState(id.clone());
+        let mut state: FlightState = env.storage().persistent().get(&key).unwrap();
+        state.passenger_count = 4;
+        env.storage().persistent().set(&key, &state);
+    });
+    assert!(!fx.client.verify_count(&fx.admin, &id));
+
+    // The infant rides on the adult's seat
+    assert_eq!(fx.client.fix_count(&fx.admin, &id), 1);
+    assert!(fx.client.verify_count(&fx.admin, &id));
+    assert_eq!(fx.client.get_flight(&id).passenger_count, 1);
+}
+
+#[test]
+fn test_reduce_capacity_bumps_latest_bookings() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let id = fx.create_flight(1, 5, 100);
+    let book = |who: &Address, seat: u32| {
+        fx.client.buy_ticket(
+            &id,
+            who,
+            &symbol_short!("veg"),
+            &seat,
+            &None,
+            &PassengerType::Adult,
+            &None,
+        );
+    };
+
+    // A cancellation leaves 10 in the fee pot to pay compensation from
+    let early = fx.new_passenger();
+    book(&early, 4);
+    fx.client.cancel_ticket(&id, &early, &None);
+    assert_eq!(fx.client.get_fee_pot(), 10);
+
+    let (a, b, c, d) = (
+        fx.new_passenger(),
+        fx.new_passenger(),
+        fx.new_passenger(),
+        fx.new_passenger(),
+    );
+    book(&a, 5);
+    book(&b, 1);
+    book(&c, 2);
+    book(&d, 3);
+
+    assert_eq!(
+        fx.client.try_reduce_capacity(&fx.admin, &id, &0, &5, &1),
+        Err(Ok(FlyStellarError::InvalidInput.into()))
+    );
+
+    assert_eq!(fx.client.reduce_capacity(&fx.admin, &id, &2, &5, &1), 1);
+    let (_, topics, _) = env.events().all().last().unwrap();
+    assert_eq!(
+        topics,
+        (
+            symbol_short!("passenger"),
+            symbol_short!("bumped"),
+            id.clone(),
+            d.clone()
+        )
+            .into_val(&env)
+    );
+    assert_eq!(fx.balance(&d), 10_005);
+    assert!(!fx.client.is_booked(&id, &d));
+    assert_eq!(fx.client.get_flights_pass(&d).len(), 0);
+    // Still over, so nobody can book in the meantime
+    assert_eq!(fx.client.get_flight(&id).max_passengers, 2);
+
+    assert_eq!(fx.client.reduce_capacity(&fx.admin, &id, &2, &5, &1), 0);
+    assert_eq!(fx.balance(&c), 10_005);
+    assert_eq!(fx.client.get_fee_pot(), 0);
+
+    // The earliest booking kept its place but moved into the smaller cabin
+    let flight = fx.client.get_flight(&id);
+    assert_eq!(flight.passenger_count, 2);
+    let seats = fx.client.get_seat_map(&id);
+    assert_eq!(seats.len(), 2);
+    assert_eq!(seats.get(1), Some(PassengerId::Plain(b.clone())));
+    assert_eq!(seats.get(2), Some(PassengerId::Plain(a.clone())));
+    assert!(fx.client.verify_count(&fx.admin, &id));
+
+    fx.client
+        .update_flight_status(&fx.admin, &id, &symbol_short!("takeoff"), &None);
+    assert_eq!(
+        fx.client.try_reduce_capacity(&fx.admin, &id, &1, &0, &1),
+        Err(Ok(FlyStellarError::InvalidStatus.into()))
+    );
+}
+
+#[test]
+fn test_pay_with_accepted_token() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let id = fx.create_flight(1, 5, 100);
+    let usdc = env
+        .register_stellar_asset_contract_v2(fx.admin.clone())
+        .address();
+    let usdc_balance = |who: &Address| token::Client::new(&env, &usdc).balance(who);
+    let alice = fx.new_passenger();
+    token::StellarAssetClient::new(&env, &usdc).mint(&alice, &1_000);
+
+    assert_eq!(
+        fx.client.try_buy_ticket(
+            &id,
+            &alice,
+            &symbol_short!("veg"),
+            &1,
+            &None,
+            &PassengerType::Adult,
+            &Some(usdc.clone()),
+        ),
+        Err(Ok(FlyStellarError::InvalidInput.into()))
+    );
+
+    apply_change(
+        &env,
+        &fx.client,
+        &fx.admin,
+        ConfigChange::AcceptedToken(usdc.clone(), 2, 1),
+    );
+    fx.client.buy_ticket(
+        &id,
+        &alice,
+        &symbol_short!("veg"),
+        &1,
+        &None,
+        &PassengerType::Adult,
+        &Some(usdc.clone()),
+    );
+    assert_eq!(usdc_balance(&alice), 800);
+    assert_eq!(fx.balance(&alice), 10_000);
+    let rec = passengers(&env, &fx.client, &id).get(0).unwrap();
+    assert_eq!(rec.paid, 100);
+    assert_eq!(rec.pay_token, Some(usdc.clone()));
+    assert_eq!(rec.token_paid, 200);
+
+    // A later rate change does not alter what the booking gets back
+    apply_change(
+        &env,
+        &fx.client,
+        &fx.admin,
+        ConfigChange::AcceptedToken(usdc.clone(), 3, 1),
+    );
+    // The fare sits in its own escrow, not the base-token accounting
+    let mut held = Map::new(&env);
+    held.set(usdc.clone(), 200);
+    assert_eq!(fx.client.get_token_escrow(&id), held);
+    assert_eq!(fx.client.get_flight_balance(&id).net_held, 0);
+    assert_eq!(fx.client.get_liabilities(), 0);
+
+    fx.client.cancel_ticket(&id, &alice, &None);
+    assert_eq!(usdc_balance(&alice), 980);
+    assert_eq!(fx.balance(&alice), 10_000);
+    assert!(fx.client.get_token_escrow(&id).is_empty());
+    assert_eq!(fx.client.get_token_fee_pot(&usdc), 20);
+    assert_eq!(fx.client.get_fee_pot(), 0);
+
+    fx.client.remove_accepted_token(&fx.admin, &usdc);
+    assert_eq!(fx.client.get_accepted_token(&usdc), None);
+}
+
+#[test]
+fn test_settle_flight_refuses_to_touch_other_escrow() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 2);
+    let flown = fx.create_flight(1, 5, 100);
+    let open = fx.create_flight(2, 5, 100);
+    fx.book(&flown, &fx.passenger(0), 1);
+    fx.book(&open, &fx.passenger(1), 1);
+    fx.client.check_in(&flown, &fx.passenger(0));
+    fx.client
+        .update_flight_status(&fx.admin, &flown, &symbol_short!("takeoff"), &None);
+
+    // Books claiming more than the contract can spare must not dip into the
+    // open flight's fares
+    env.as_contract(&fx.client.address, || {
+        let key = DataKey::Balance(flown.clone());
+        let mut books: FlightBalance = env.storage().persistent().get(&key).unwrap();
+        books.collected += 50;
+        books.net_held += 50;
+        env.storage().persistent().set(&key, &books);
+    });
+    assert_eq!(
+        fx.client.try_settle_flight(&fx.admin, &flown),
+        Err(Ok(FlyStellarError::InsufficientEscrow.into()))
+    );
+    assert_eq!(fx.balance(&fx.client.address), 200);
+}
+
+#[test]
+fn test_settle_pays_accepted_tokens_in_kind() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 2);
+    let id = fx.create_flight(1, 5, 100);
+    let usdc = env
+        .register_stellar_asset_contract_v2(fx.admin.clone())
+        .address();
+    let usdc_client = token::Client::new(&env, &usdc);
+    apply_change(
+        &env,
+        &fx.client,
+        &fx.admin,
+        ConfigChange::AcceptedToken(usdc.clone(), 2, 1),
+    );
+    fx.client.set_booking_fee(&fx.admin, &5);
+
+    let alice = fx.passenger(0);
+    token::StellarAssetClient::new(&env, &usdc).mint(&alice, &1_000);
+    fx.client.buy_ticket(
+        &id,
+        &alice,
+        &symbol_short!("std"),
+        &1,
+        &None,
+        &PassengerType::Adult,
+        &Some(usdc.clone()),
+    );
+    fx.book(&id, &fx.passenger(1), 2);
+    // The booking fee is kept in the token it was paid in
+    assert_eq!(fx.client.get_token_fee_pot(&usdc), 10);
+    assert_eq!(fx.client.get_fee_pot(), 5);
+    assert_eq!(fx.client.get_flight_balance(&id).net_held, 100);
+
+    fx.client.check_in(&id, &alice);
+    fx.client.check_in(&id, &fx.passenger(1));
+    fx.client
+        .update_flight_status(&fx.admin, &id, &symbol_short!("takeoff"), &None);
+    assert_eq!(fx.client.settle_flight(&fx.admin, &id), 100);
+    assert_eq!(fx.balance(&fx.admin), 100);
+    assert_eq!(usdc_client.balance(&fx.admin), 200);
+    assert!(fx.client.get_token_escrow(&id).is_empty());
+    assert_eq!(usdc_client.balance(&fx.client.address), 10);
+}
+
+#[test]
+fn test_nearest_departures() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let src = symbol_short!("DEL");
+    let dest = symbol_short!("BOM");
+    let target = DEPART + 10_000;
+    // (id, departure): 3 and 4 are equally close, 4 leaves earlier
+    let specs = [
+        (1u8, target + 5_000),
+        (2, target - 100),
+        (3, target + 2_000),
+        (4, target - 2_000),
+        (5, target + 50_000),
+    ];
+    for (n, depart_at) in specs {
+        fx.client.create_flight(
+            &fx.admin,
+            &flight_id(&env, n),
+            &flight_number(&env, n),
+            &5,
+            &100,
+            &src,
+            &dest,
+            &depart_at,
+        );
+    }
+    let ids = |flights: Vec<FlightDetails>| {
+        let mut out = Vec::new(&env);
+        for f in flights.iter() {
+            out.push_back(f.id.get(0).unwrap() as u32);
+        }
+        out
+    };
+
+    assert_eq!(
+        ids(fx.client.get_nearest_departures(&src, &dest, &target, &3)),
+        Vec::from_array(&env, [2u32, 4, 3])
+    );
+    assert_eq!(
+        ids(fx.client.get_nearest_departures(&src, &dest, &target, &10)),
+        Vec::from_array(&env, [2u32, 4, 3, 1, 5])
+    );
+    assert_eq!(
+        fx.client
+            .get_nearest_departures(&src, &dest, &target, &0)
+            .len(),
+        0
+    );
+    assert_eq!(
+        fx.client
+            .get_nearest_departures(&dest, &src, &target, &3)
+            .len(),
+        0
+    );
+}
+
+#[test]
+fn test_cancel_on_empty_flight() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let id = fx.create_flight(1, 5, 100);
+    let alice = fx.new_passenger();
+    assert!(!fx.client.has_passengers(&id));
+    assert_eq!(
+        fx.client.try_cancel_ticket(&id, &alice, &None),
+        Err(Ok(FlyStellarError::NoPassengers.into()))
+    );
+
+    fx.client.buy_ticket(
+        &id,
+        &alice,
+        &symbol_short!("veg"),
+        &1,
+        &None,
+        &PassengerType::Adult,
+        &None,
+    );
+    assert!(fx.client.has_passengers(&id));
+    let bob = fx.new_passenger();
+    assert_eq!(
+        fx.client.try_cancel_ticket(&id, &bob, &None),
+        Err(Ok(FlyStellarError::PassengerNotFound.into()))
+    );
+
+    fx.client.cancel_ticket(&id, &alice, &None);
+    assert!(!fx.client.has_passengers(&id));
+    assert_eq!(
+        fx.client.try_cancel_ticket(&id, &alice, &None),
+        Err(Ok(FlyStellarError::NoPassengers.into()))
+    );
+}
+
+#[test]
+fn test_gift_ticket() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let id = fx.create_flight(1, 5, 100);
+    let child = fx.new_passenger();
+    let parent = Address::generate(&env);
+
+    let receipt = fx
+        .client
+        .gift_ticket(&id, &child, &parent, &symbol_short!("veg"));
+    assert_eq!((receipt.paid, receipt.seat), (100, 1));
+    assert_eq!(fx.balance(&child), 9_900);
+    assert_eq!(fx.client.get_flights_pass(&parent).len(), 1);
+    assert_eq!(fx.client.get_flights_pass(&child).len(), 0);
+    let rec = passengers(&env, &fx.client, &id).get(0).unwrap();
+    assert_eq!(
+        (rec.passenger, rec.payer, rec.seat),
+        (
+            PassengerId::Plain(parent.clone()),
+            PassengerId::Plain(child.clone()),
+            1
+        )
+    );
+    fx.client.check_in(&id, &parent);
+
+    // The passenger cancels, the payer gets the refund
+    fx.client.cancel_ticket(&id, &parent, &None);
+    assert_eq!(fx.balance(&child), 9_990);
+    assert_eq!(fx.balance(&parent), 0);
+
+    // Only the payer may cancel from their side
+    fx.client
+        .gift_ticket(&id, &child, &parent, &symbol_short!("veg"));
+    let stranger = Address::generate(&env);
+    assert_eq!(
+        fx.client.try_cancel_gift(&id, &stranger, &parent),
+        Err(Ok(FlyStellarError::Unauthorized.into()))
+    );
+    fx.client.cancel_gift(&id, &child, &parent);
+    assert_eq!(fx.balance(&child), 9_980);
+    assert!(!fx.client.is_booked(&id, &parent));
+}
+
+#[test]
+fn test_ttl_config_renews_entries() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    assert_eq!(fx.client.get_ttl_config().extend_to, 17_280 * 120);
+    assert_eq!(
+        fx.client.try_set_ttl_config(&fx.admin, &6_000, &5_000),
+        Err(Ok(FlyStellarError::InvalidInput.into()))
+    );
+    fx.client.set_ttl_config(&fx.admin, &5_000, &10_000);
+    let id = fx.create_flight(1, 5, 100);
+    let ttl = || {
+        env.as_contract(&fx.client.address, || {
+            env.storage()
+                .persistent()
+                .get_ttl(&DataKey::Flight(id.clone()))
+        })
+    };
+    assert_eq!(ttl(), 10_000);
+
+    // Still above the threshold: a read leaves the TTL alone
+    env.ledger().with_mut(|l| l.sequence_number += 4_000);
+    fx.client.get_flight(&id);
+    assert_eq!(ttl(), 6_000);
+
+    // Below it: the next read renews the full window
+    env.ledger().with_mut(|l| l.sequence_number += 1_001);
+    fx.client.get_flight(&id);
+    assert_eq!(ttl(), 10_000);
+
+    // Untouched, the entry lives to the last ledger of the window
+    env.ledger().with_mut(|l| l.sequence_number += 10_000);
+    assert_eq!(ttl(), 0);
+    assert_eq!(fx.client.get_flight(&id).id, id);
+}
+
+#[test]
+fn test_audit_log() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let id = fx.create_flight(1, 5, 100);
+    fx.client.set_max_fare(&fx.admin, &500);
+    let first = env.cost_estimate().resources();
+    fx.client
+        .update_flight_status(&fx.admin, &id, &symbol_short!("takeoff"), &None);
+
+    let log = fx.client.get_audit_log(&fx.admin, &0, &10);
+    assert_eq!(log.len(), 3);
+    assert_eq!(log.get(0).unwrap().action, AuditAction::FlightStatusChanged);
+    assert_eq!(log.get(1).unwrap().value, 500);
+    let created = log.get(2).unwrap();
+    assert_eq!(
+        (
+            created.action,
+            created.actor,
+            created.flight_id,
+            created.value
+        ),
+        (
+            AuditAction::FlightCreated,
+            fx.admin.clone(),
+            Some(id.clone()),
+            5
+        )
+    );
+
+    // Pages run backwards from the newest entry
+    let page = fx.client.get_audit_log(&fx.admin, &1, &2);
+    assert_eq!(page.len(), 1);
+    assert_eq!(page.get(0).unwrap().action, AuditAction::FlightCreated);
+    assert_eq!(fx.client.get_audit_log(&fx.admin, &5, &2).len(), 0);
+
+    let stranger = Address::generate(&env);
+    assert_eq!(
+        fx.client.try_get_audit_log(&stranger, &0, &10),
+        Err(Ok(FlyStellarError::Unauthorized.into()))
+    );
+
+    // Logging costs one fresh entry per action, however long the log grows
+    for fare in 0..20 {
+        fx.client.set_max_fare(&fx.admin, &(400 - fare));
+    }
+    let later = env.cost_estimate().resources();
+    assert_eq!(later.write_entries, first.write_entries);
+    assert_eq!(later.write_bytes, first.write_bytes);
+    assert_eq!(
+        fx.client
+            .get_audit_log(&fx.admin, &0, &1)
+            .get(0)
+            .unwrap()
+            .value,
+        381
+    );
+}
+
+#[test]
+fn test_export_flight() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let id = fx.create_flight(1, 5, 100);
+    let alice = fx.new_passenger();
+    let bob = fx.new_passenger();
+    for (seat, who) in [(1, &alice), (2, &bob)] {
+        fx.client.buy_ticket(
+            &id,
+            who,
+            &symbol_short!("veg"),
+            &seat,
+            &None,
+            &PassengerType::Adult,
+            &None,
+        );
+    }
+
+    let (flight, records) = fx.client.export_flight(&fx.admin, &id);
+    assert_eq!(flight, fx.client.get_flight(&id));
+    assert_eq!(flight.passenger_count, 2);
+    assert_eq!(records, passengers(&env, &fx.client, &id));
+    assert_eq!(records.len(), 2);
+    assert_eq!(records.get(1).unwrap().passenger, PassengerId::Plain(bob));
+
+    assert_eq!(
+        fx.client.try_export_flight(&alice, &id),
+        Err(Ok(FlyStellarError::Unauthorized.into()))
+    );
+    assert_eq!(
+        fx.client.try_export_flight(&fx.admin, &flight_id(&env, 9)),
+        Err(Ok(FlyStellarError::FlightNotFound.into()))
+    );
+}
+
+#[test]
+fn test_recovery_timelock() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let token = fx.client.get_token();
+    token::StellarAssetClient::new(&env, &token).mint(&fx.client.address, &1_000);
+    let rescuer = Address::generate(&env);
+
+    assert_eq!(
+        fx.client.try_execute_recovery(&fx.admin),
+        Err(Ok(FlyStellarError::NoRecovery.into()))
+    );
+    assert_eq!(
+        fx.client.try_cancel_recovery(&fx.admin),
+        Err(Ok(FlyStellarError::NoRecovery.into()))
+    );
+
+    env.ledger().set_timestamp(1_000);
+    fx.client
+        .propose_recovery(&fx.admin, &token, &400, &rescuer);
+    let (_, topics, _) = env.events().all().last().unwrap();
+    assert_eq!(
+        topics,
+        (
+            symbol_short!("recovery"),
+            symbol_short!("proposed"),
+            token.clone()
+        )
+            .into_val(&env)
+    );
+
+    // A new proposal restarts the clock
+    env.ledger().set_timestamp(1_000 + RECOVERY_DELAY_SECS - 1);
+    fx.client
+        .propose_recovery(&fx.admin, &token, &600, &rescuer);
+    env.ledger().set_timestamp(1_000 + RECOVERY_DELAY_SECS);
+    assert_eq!(
+        fx.client.try_execute_recovery(&fx.admin),
+        Err(Ok(FlyStellarError::RecoveryLocked.into()))
+    );
+
+    let proposed_at = fx.client.get_recovery().unwrap().proposed_at;
+    env.ledger()
+        .set_timestamp(proposed_at + RECOVERY_DELAY_SECS);
+    fx.client.execute_recovery(&fx.admin);
+    assert_eq!(fx.balance(&rescuer), 600);
+    assert_eq!(fx.balance(&fx.client.address), 400);
+    assert_eq!(fx.client.get_recovery(), None);
+
+    // Cancelling drops the proposal for good
+    fx.client
+        .propose_recovery(&fx.admin, &token, &400, &rescuer);
+    fx.client.cancel_recovery(&fx.admin);
+    env.ledger()
+        .set_timestamp(proposed_at + 3 * RECOVERY_DELAY_SECS);
+    assert_eq!(
+        fx.client.try_execute_recovery(&fx.admin),
+        Err(Ok(FlyStellarError::NoRecovery.into()))
+    );
+}
+
+#[test]
+fn test_status_changed_at() {
+    let env = Env::default();
+    env.ledger().set_timestamp(1_000);
+    let fx = TestFixture::new(&env, 0);
+    let id = fx.create_flight(1, 5, 100);
+    assert_eq!(fx.client.get_flight(&id).status_changed_at, 1_000);
+
+    // A ledger clock behind the last change cannot rewrite history
+    env.ledger().set_timestamp(999);
+    assert_eq!(
+        fx.client
+            .try_update_flight_status(&fx.admin, &id, &symbol_short!("takeoff"), &None),
+        Err(Ok(FlyStellarError::StaleStatus.into()))
+    );
+    assert_eq!(fx.client.get_flight(&id).status_changed_at, 1_000);
+
+    env.ledger().set_timestamp(5_000);
+    fx.client
+        .update_flight_status(&fx.admin, &id, &symbol_short!("takeoff"), &None);
+    let flight = fx.client.get_flight(&id);
+    assert_eq!(
+        (flight.status, flight.status_changed_at),
+        (symbol_short!("takeoff"), 5_000)
+    );
+}
+
+#[test]
+fn test_status_change_only_from_booking() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let flown = fx.create_flight(1, 5, 100);
+    fx.client
+        .update_flight_status(&fx.admin, &flown, &symbol_short!("takeoff"), &None);
+
+    // A departed flight can no longer be cancelled
+    assert_eq!(
+        fx.client
+            .try_update_flight_status(&fx.admin, &flown, &symbol_short!("cancelled"), &None),
+        Err(Ok(FlyStellarError::InvalidStatus.into()))
+    );
+    assert_eq!(
+        fx.client.get_flight(&flown).status,
+        symbol_short!("takeoff")
+    );
+
+    let cancelled = fx.create_flight(2, 5, 100);
+    fx.client
+        .update_flight_status(&fx.admin, &cancelled, &symbol_short!("cancelled"), &None);
+
+    // Nor can a cancelled flight take off
+    assert_eq!(
+        fx.client
+            .try_update_flight_status(&fx.admin, &cancelled, &symbol_short!("takeoff"), &None),
+        Err(Ok(FlyStellarError::InvalidStatus.into()))
+    );
+    assert_eq!(
+        fx.client.get_flight(&cancelled).status,
+        symbol_short!("cancelled")
+    );
+}
+
+#[test]
+fn test_config_change_timelock() {
+    let env = Env::default();
+    env.ledger().set_timestamp(1_000);
+    let fx = TestFixture::new(&env, 0);
+
+    // Safer changes apply at once, riskier ones must be queued
+    fx.client.set_max_fare(&fx.admin, &500);
+    assert_eq!(
+        fx.client.try_set_max_fare(&fx.admin, &800),
+        Err(Ok(FlyStellarError::TimelockRequired.into()))
+    );
+    assert_eq!(
+        fx.client.try_set_cancel_policy(&fx.admin, &3_600, &false),
+        Err(Ok(FlyStellarError::TimelockRequired.into()))
+    );
+
+    fx.client
+        .queue_config_change(&fx.admin, &ConfigChange::MaxFare(800));
+    assert_eq!(
+        fx.client.get_pending_change(&ConfigParam::MaxFare),
+        Some(PendingChange {
+            change: ConfigChange::MaxFare(800),
+            executable_at: 1_000 + CONFIG_CHANGE_DELAY_SECS,
+        })
+    );
+    // One pending change per parameter, but parameters queue independently
+    assert_eq!(
+        fx.client
+            .try_queue_config_change(&fx.admin, &ConfigChange::MaxFare(900)),
+        Err(Ok(FlyStellarError::ChangePending.into()))
+    );
+    fx.client
+        .queue_config_change(&fx.admin, &ConfigChange::NoShowShare(5_000));
+    assert_eq!(
+        fx.client
+            .try_queue_config_change(&fx.admin, &ConfigChange::NoShowShare(20_000)),
+        Err(Ok(FlyStellarError::InvalidInput.into()))
+    );
+
+    env.ledger()
+        .set_timestamp(1_000 + CONFIG_CHANGE_DELAY_SECS - 1);
+    assert_eq!(
+        fx.client
+            .try_apply_config_change(&fx.admin, &ConfigParam::MaxFare),
+        Err(Ok(FlyStellarError::ChangeLocked.into()))
+    );
+    env.ledger().set_timestamp(1_000 + CONFIG_CHANGE_DELAY_SECS);
+    fx.client
+        .apply_config_change(&fx.admin, &ConfigParam::MaxFare);
+    assert_eq!(fx.client.get_max_fare(), 800);
+    assert_eq!(fx.client.get_pending_change(&ConfigParam::MaxFare), None);
+
+    fx.client
+        .cancel_config_change(&fx.admin, &ConfigParam::NoShowShare);
+    assert_eq!(
+        fx.client.get_pending_change(&ConfigParam::NoShowShare),
+        None
+    );
+    assert_eq!(
+        fx.client
+            .try_apply_config_change(&fx.admin, &ConfigParam::NoShowShare),
+        Err(Ok(FlyStellarError::NoPendingChange.into()))
+    );
+}
+
+#[test]
+fn test_split_flight() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let id = fx.create_flight(1, 5, 100);
+    let alice = fx.new_passenger();
+    let bob = fx.new_passenger();
+    let carol = fx.new_passenger();
+    for (seat, who) in [(1, &alice), (2, &bob), (3, &carol)] {
+        fx.client.buy_ticket(
+            &id,
+            who,
+            &symbol_short!("veg"),
+            &seat,
+            &None,
+            &PassengerType::Adult,
+            &None,
+        );
+    }
+
+    fx.client
+        .flag_ticket(&fx.admin, &id, &carol, &symbol_short!("chgback"));
+
+    let later = flight_id(&env, 2);
+    fx.client.split_flight(
+        &fx.admin,
+        &id,
+        &later,
+        &flight_number(&env, 2),
+        &(DEPART + 3_600),
+        &soroban_sdk::vec![&env, alice.clone(), carol.clone()],
+    );
+    // A disputed booking stays frozen at the gate on its new flight
+    assert!(fx.client.verify_ticket(&later, &alice));
+    assert!(!fx.client.verify_ticket(&later, &carol));
+
+    let (source, split) = (fx.client.get_flight(&id), fx.client.get_flight(&later));
+    assert_eq!((source.passenger_count, split.passenger_count), (1, 2));
+    assert_eq!(
+        (split.src, split.dest, split.distance, split.max_passengers),
+        (
+            source.src,
+            source.dest,
+            source.distance,
+            source.max_passengers
+        )
+    );
+    assert_eq!(fx.client.get_flights_pass(&alice).get(0).unwrap().id, later);
+    assert_eq!(fx.client.get_flights_pass(&bob).get(0).unwrap().id, id);
+    assert!(!fx.client.is_booked(&id, &carol));
+    assert_eq!(
+        fx.client.get_seat_map(&later).get(3),
+        Some(PassengerId::Plain(carol.clone()))
+    );
+    assert_eq!(fx.client.get_seat_map(&id).len(), 1);
+    // The moved escrow is a transfer, not a refund or a fresh collection
+    let (from, to) = (
+        fx.client.get_flight_balance(&id),
+        fx.client.get_flight_balance(&later),
+    );
+    assert_eq!(
+        (from.collected, from.refunded, from.transferred),
+        (300, 0, 200)
+    );
+    assert_eq!((to.collected, to.refunded, to.transferred), (0, 0, -200));
+    assert_eq!((from.net_held, to.net_held), (100, 200));
+    assert_eq!(fx.client.reconcile_flight(&fx.admin, &id), (100, 100));
+    assert_eq!(fx.client.reconcile_flight(&fx.admin, &later), (200, 200));
+
+    // Everyone listed must still be on the source flight
+    assert_eq!(
+        fx.client.try_split_flight(
+            &fx.admin,
+            &id,
+            &flight_id(&env, 3),
+            &flight_number(&env, 3),
+            &(DEPART + 7_200),
+            &soroban_sdk::vec![&env, carol.clone()],
+        ),
+        Err(Ok(FlyStellarError::PassengerNotFound.into()))
+    );
+    assert!(fx.client.is_booked(&id, &bob));
+}
+
+#[test]
+fn test_fare_per_km_scaling() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let src = symbol_short!("DEL");
+    let dest = symbol_short!("BOM");
+    let create = |n: u8, distance: i128| {
+        fx.client.create_flight(
+            &fx.admin,
+            &flight_id(&env, n),
+            &flight_number(&env, n),
+            &5,
+            &distance,
+            &src,
+            &dest,
+            &DEPART,
+        )
+    };
+    assert_eq!(fx.client.get_fare_per_km(), FARE_SCALE);
+    create(1, 101);
+
+    // 1.5 per km: 101 km comes to 151.5, rounded down
+    fx.client.set_fare_per_km(&fx.admin, &15_000_000);
+    create(2, 101);
+    let scaled = fx.client.get_flight(&flight_id(&env, 2));
+    assert_eq!((scaled.fare, scaled.escrow_amount), (151, 755));
+    assert_eq!(
+        fx.client
+            .get_fare(&flight_id(&env, 2), &None, &None, &PassengerType::Adult),
+        151
+    );
+    // Live inventory keeps the fare it was created with
+    assert_eq!(
+        fx.client
+            .get_fare(&flight_id(&env, 1), &None, &None, &PassengerType::Adult),
+        101
+    );
+
+    // A rate too small to price even one unit is refused at creation
+    fx.client.set_fare_per_km(&fx.admin, &1_000);
+    assert_eq!(
+        fx.client.try_create_flight(
+            &fx.admin,
+            &flight_id(&env, 3),
+            &flight_number(&env, 3),
+            &5,
+            &100,
+            &src,
+            &dest,
+            &DEPART,
+        ),
+        Err(Ok(FlyStellarError::InvalidInput.into()))
+    );
+
+    // Rates that would overflow at the longest distance are rejected
+    assert_eq!(
+        fx.client
+            .try_set_fare_per_km(&fx.admin, &(i128::MAX / MAX_DISTANCE_KM + 1)),
+        Err(Ok(FlyStellarError::InvalidInput.into()))
+    );
+    assert_eq!(
+        fx.client.try_set_fare_per_km(&fx.admin, &0),
+        Err(Ok(FlyStellarError::InvalidInput.into()))
+    );
+    assert_eq!(
+        fx.client.try_create_flight(
+            &fx.admin,
+            &flight_id(&env, 3),
+            &flight_number(&env, 3),
+            &5,
+            &(MAX_DISTANCE_KM + 1),
+            &src,
+            &dest,
+            &DEPART,
+        ),
+        Err(Ok(FlyStellarError::InvalidInput.into()))
+    );
+}
+
+#[test]
+fn test_flights_by_operator() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let ops = [Address::generate(&env), Address::generate(&env)];
+    for (n, op) in [(1, &ops[0]), (2, &ops[1]), (3, &ops[0])] {
+        fx.client.add_operator(&fx.admin, op);
+        fx.client.create_flight(
+            op,
+            &flight_id(&env, n),
+            &flight_number(&env, n),
+            &5,
+            &100,
+            &symbol_short!("DEL"),
+            &symbol_short!("BOM"),
+            &DEPART,
+        );
+    }
+    let ids = |flights: Vec<FlightDetails>| {
+        let mut out = Vec::new(&env);
+        for f in flights.iter() {
+            out.push_back(f.id);
+        }
+        out
+    };
+
+    assert_eq!(
+        ids(fx.client.get_flights_by_operator(&ops[0], &ops[0])),
+        soroban_sdk::vec![&env, flight_id(&env, 1), flight_id(&env, 3)]
+    );
+    assert_eq!(
+        ids(fx.client.get_flights_by_operator(&fx.admin, &ops[1])),
+        soroban_sdk::vec![&env, flight_id(&env, 2)]
+    );
+    assert_eq!(
+        fx.client
+            .get_flights_by_operator(&fx.admin, &fx.admin)
+            .len(),
+        0
+    );
+
+    // Operators only see their own flights
+    assert_eq!(
+        fx.client.try_get_flights_by_operator(&ops[1], &ops[0]),
+        Err(Ok(FlyStellarError::Unauthorized.into()))
+    );
+}
+
+#[test]
+fn test_occupancy_thresholds() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let id = fx.create_flight(1, 5, 100);
+    assert_eq!(
+        fx.client.get_occupancy_thresholds(),
+        soroban_sdk::vec![&env, 5_000, 8_000, 10_000]
+    );
+    for bad in [
+        soroban_sdk::vec![&env, 8_000, 5_000],
+        soroban_sdk::vec![&env, 5_000, 5_000],
+        soroban_sdk::vec![&env, 10_001],
+    ] {
+        assert_eq!(
+            fx.client.try_set_occupancy_thresholds(&fx.admin, &bad),
+            Err(Ok(FlyStellarError::InvalidInput.into()))
+        );
+    }
+    fx.client
+        .set_occupancy_thresholds(&fx.admin, &soroban_sdk::vec![&env, 4_000, 10_000]);
+
+    // Thresholds the last call announced
+    let announced = || {
+        let mut out: Vec<u32> = Vec::new(&env);
+        for (_, topics, data) in env.events().all().iter() {
+            if topics
+                == (
+                    symbol_short!("flight"),
+                    symbol_short!("occupancy"),
+                    id.clone(),
+                )
+                    .into_val(&env)
+            {
+                let fields: Map<Symbol, u32> = data.into_val(&env);
+                out.push_back(fields.get(Symbol::new(&env, "threshold_bps")).unwrap());
+            }
+        }
+        out
+    };
+    let book = |who: &Address, seat: u32| {
+        fx.client.buy_ticket(
+            &id,
+            who,
+            &symbol_short!("veg"),
+            &seat,
+            &None,
+            &PassengerType::Adult,
+            &None,
+        );
+    };
+    let riders: [Address; 5] = core::array::from_fn(|_| fx.new_passenger());
+
+    book(&riders[0], 1);
+    assert_eq!(announced().len(), 0);
+    book(&riders[1], 2);
+    assert_eq!(announced(), soroban_sdk::vec![&env, 4_000]);
+    assert_eq!(fx.client.get_occupancy(&id), 4_000);
+
+    // Dropping back under 40% and returning is a new crossing
+    fx.client.cancel_ticket(&id, &riders[1], &None);
+    assert_eq!(announced().len(), 0);
+    assert_eq!(fx.client.get_occupancy(&id), 2_000);
+    book(&riders[1], 2);
+    assert_eq!(announced(), soroban_sdk::vec![&env, 4_000]);
+
+    book(&riders[2], 3);
+    book(&riders[3], 4);
+    assert_eq!(announced().len(), 0);
+    book(&riders[4], 5);
+    assert_eq!(announced(), soroban_sdk::vec![&env, 10_000]);
+    assert_eq!(fx.client.get_occupancy(&id), 10_000);
+}
+
+#[test]
+fn test_cancel_seats() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let id = fx.create_flight(1, 5, 100);
+    let agency = fx.new_passenger();
+    let booker = Address::generate(&env);
+    fx.client.authorize_agent(&booker, &agency);
+    for seat in 1..=5 {
+        fx.client.buy_ticket_for(
+            &id,
+            &agency,
+            &booker,
+            &symbol_short!("group"),
+            &seat,
+            &None,
+            &PassengerType::Adult,
+        );
+    }
+    assert_eq!(fx.balance(&agency), 9_500);
+
+    fx.client.cancel_seats(&id, &booker, &3);
+    assert_eq!(fx.client.get_flight(&id).passenger_count, 2);
+    let left = passengers(&env, &fx.client, &id);
+    assert_eq!(left.len(), 2);
+    assert_eq!(
+        (left.get(0).unwrap().seat, left.get(1).unwrap().seat),
+        (1, 2)
+    );
+    // Three 90% refunds back to the agency that paid
+    assert_eq!(fx.balance(&agency), 9_770);
+    assert_eq!(fx.client.get_fee_pot(), 30);
+    assert!(fx.client.is_booked(&id, &booker));
+    assert_eq!(fx.client.get_seat_map(&id).len(), 2);
+
+    assert_eq!(
+        fx.client.try_cancel_seats(&id, &booker, &3),
+        Err(Ok(FlyStellarError::InvalidInput.into()))
+    );
+    assert_eq!(
+        fx.client.try_cancel_seats(&id, &agency, &1),
+        Err(Ok(FlyStellarError::PassengerNotFound.into()))
+    );
+
+    // Giving back the last seats drops the flight from the registry too
+    fx.client.cancel_seats(&id, &booker, &2);
+    assert!(!fx.client.is_booked(&id, &booker));
+    assert_eq!(fx.client.get_flights_pass(&booker).len(), 0);
+
+    // A lap infant leaves with the last adult seat
+    let parent = fx.new_passenger();
+    for (seat, kind) in [(3, PassengerType::Adult), (0, PassengerType::Infant)] {
+        fx.client.buy_ticket(
+            &id,
+            &parent,
+            &symbol_short!("veg"),
+            &seat,
+            &None,
+            &kind,
+            &None,
+        );
+    }
+    fx.client.cancel_seats(&id, &parent, &1);
+    assert!(!fx.client.is_booked(&id, &parent));
+    assert_eq!(passengers(&env, &fx.client, &id).len(), 0);
+    assert_eq!(fx.client.get_flight(&id).passenger_count, 0);
+}
+
+#[test]
+fn test_can_book() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let id = fx.create_flight(1, 5, 100);
+    let alice = fx.new_passenger();
+    let check = |who: &Address, flight: &BytesN<32>| {
+        let res = fx.client.can_book(flight, who);
+        (res.ok, res.reason, res.fare)
+    };
+
+    assert_eq!(check(&alice, &id), (true, BookReason::Ok, 100));
+    // Read-only, so free to simulate
+    assert_eq!(env.cost_estimate().resources().write_entries, 0);
+    let broke = Address::generate(&env);
+    assert_eq!(
+        check(&broke, &id),
+        (false, BookReason::InsufficientFunds, 100)
+    );
+    assert_eq!(
+        check(&alice, &flight_id(&env, 9)),
+        (false, BookReason::FlightNotFound, 0)
+    );
+
+    // A recent cancellation blocks rebooking while the cooldown runs
+    fx.client.set_cancel_cooldown(&fx.admin, &3_600);
+    fx.client.buy_ticket(
+        &id,
+        &alice,
+        &symbol_short!("veg"),
+        &1,
+        &None,
+        &PassengerType::Adult,
+        &None,
+    );
+    fx.client.cancel_ticket(&id, &alice, &None);
+    assert_eq!(check(&alice, &id), (false, BookReason::PassengerBlocked, 0));
+    assert_eq!(
+        fx.client.try_buy_ticket(
+            &id,
+            &alice,
+            &symbol_short!("veg"),
+            &1,
+            &None,
+            &PassengerType::Adult,
+            &None,
+        ),
+        Err(Ok(FlyStellarError::Cooldown.into()))
+    );
+
+    let bob = fx.new_passenger();
+    for seat in 1..=5 {
+        fx.client.buy_ticket(
+            &id,
+            &bob,
+            &symbol_short!("veg"),
+            &seat,
+            &None,
+            &PassengerType::Adult,
+            &None,
+        );
+    }
+    assert_eq!(check(&bob, &id), (false, BookReason::FlightFull, 0));
+
+    fx.client
+        .update_flight_status(&fx.admin, &id, &symbol_short!("takeoff"), &None);
+    assert_eq!(check(&bob, &id), (false, BookReason::BookingClosed, 0));
+}
+
+#[test]
+fn test_update_statuses_batch() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    for n in 1..=2 {
+        fx.client.create_flight(
+            &fx.admin,
+            &flight_id(&env, n),
+            &flight_number(&env, n),
+            &5,
+            &100,
+            &symbol_short!("DEL"),
+            &symbol_short!("BOM"),
+            &DEPART,
+        );
+    }
+
+    let results = fx.client.update_statuses(
+        &fx.admin,
+        &soroban_sdk::vec![
+            &env,
+            (flight_id(&env, 1), symbol_short!("cancelled")),
+            (flight_id(&env, 9), symbol_short!("cancelled")),
+            (flight_id(&env, 2), symbol_short!("delayed")),
+            (flight_id(&env, 2), symbol_short!("takeoff")),
+        ],
+    );
+    assert_eq!(
+        results,
+        soroban_sdk::vec![
+            &env,
+            0,
+            FlyStellarError::FlightNotFound as u32,
+            FlyStellarError::InvalidStatus as u32,
+            0
+        ]
+    );
+    // One status event per applied update, as a single update publishes
+    let status_topics = |n: u8| -> Vec<Val> {
+        (
+            symbol_short!("flight"),
+            symbol_short!("status"),
+            flight_id(&env, n),
+        )
+            .into_val(&env)
+    };
+    let status_events = env
+        .events()
+        .all()
+        .iter()
+        .filter(|(_, topics, _)| *topics == status_topics(1) || *topics == status_topics(2))
+        .count();
+    assert_eq!(status_events, 2);
+    assert_eq!(
+        fx.client.get_flight(&flight_id(&env, 1)).status,
+        symbol_short!("cancelled")
+    );
+    assert_eq!(
+        fx.client.get_flight(&flight_id(&env, 2)).status,
+        symbol_short!("takeoff")
+    );
+
+    let mut too_many = Vec::new(&env);
+    for _ in 0..=MAX_STATUS_BATCH {
+        too_many.push_back((flight_id(&env, 1), symbol_short!("cancelled")));
+    }
+    assert_eq!(
+        fx.client.try_update_statuses(&fx.admin, &too_many),
+        Err(Ok(FlyStellarError::InvalidInput.into()))
+    );
+}
+
+#[test]
+fn test_booking_fee() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let id = fx.create_flight(1, 5, 100);
+    let alice = fx.new_passenger();
+    assert_eq!(
+        fx.client.try_set_booking_fee(&fx.admin, &-1),
+        Err(Ok(FlyStellarError::InvalidInput.into()))
+    );
+    fx.client.set_booking_fee(&fx.admin, &5);
+
+    fx.client.buy_ticket(
+        &id,
+        &alice,
+        &symbol_short!("veg"),
+        &1,
+        &None,
+        &PassengerType::Adult,
+        &None,
+    );
+    assert_eq!(fx.balance(&alice), 10_000 - 100 - 5);
+    let rec = passengers(&env, &fx.client, &id).get(0).unwrap();
+    assert_eq!((rec.paid, rec.fee_paid), (100, 5));
+    assert_eq!(fx.client.get_fee_pot(), 5);
+
+    // Only the fare is refundable: 90 back, the 10 cancellation fee and the
+    // booking fee stay in the pot
+    fx.client.cancel_ticket(&id, &alice, &None);
+    assert_eq!(fx.balance(&alice), 10_000 - 100 - 5 + 90);
+    assert_eq!(fx.client.get_fee_pot(), 15);
+    assert_eq!(
+        fx.balance(&fx.client.address),
+        fx.client.get_flight_balance(&id).net_held + fx.client.get_fee_pot()
+    );
+}
+
+#[test]
+fn test_verify_ticket_and_boarding() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let id = fx.create_flight(1, 5, 100);
+    let alice = fx.new_passenger();
+    let bob = fx.new_passenger();
+    for (who, seat) in [(&alice, 1), (&bob, 2)] {
+        fx.client.buy_ticket(
+            &id,
+            who,
+            &symbol_short!("veg"),
+            &seat,
+            &None,
+            &PassengerType::Adult,
+            &None,
+        );
+    }
+    assert!(fx.client.verify_ticket(&id, &alice));
+    assert!(!fx.client.verify_ticket(&flight_id(&env, 9), &alice));
+    assert!(!fx.client.verify_ticket(&id, &Address::generate(&env)));
+
+    // A disputed ticket is turned away at the gate
+    fx.client
+        .flag_ticket(&fx.admin, &id, &alice, &symbol_short!("fraud"));
+    assert!(!fx.client.verify_ticket(&id, &alice));
+    assert_eq!(
+        fx.client.try_mark_boarded(&fx.admin, &id, &alice),
+        Err(Ok(FlyStellarError::TicketDisputed.into()))
+    );
+    fx.client
+        .resolve_dispute(&fx.admin, &id, &alice, &DisputeOutcome::Clear);
+    assert!(fx.client.verify_ticket(&id, &alice));
+
+    fx.client.mark_boarded(&fx.admin, &id, &alice);
+    assert!(!fx.client.verify_ticket(&id, &alice));
+    assert!(fx.client.get_ticket(&id, &alice).checked_in);
+    assert_eq!(
+        fx.client.try_mark_boarded(&fx.admin, &id, &alice),
+        Err(Ok(FlyStellarError::AlreadyBoarded.into()))
+    );
+    // ...and a boarded seat can't be cancelled
+    assert_eq!(
+        fx.client.try_cancel_ticket(&id, &alice, &None),
+        Err(Ok(FlyStellarError::AlreadyBoarded.into()))
+    );
+
+    // A cancelled booking no longer opens the gate
+    fx.client.cancel_seats(&id, &bob, &1);
+    assert!(!fx.client.verify_ticket(&id, &bob));
+    assert_eq!(
+        fx.client.try_mark_boarded(&fx.admin, &id, &bob),
+        Err(Ok(FlyStellarError::PassengerNotFound.into()))
+    );
+
+    fx.client
+        .update_flight_status(&fx.admin, &id, &symbol_short!("takeoff"), &None);
+    assert_eq!(
+        fx.client.try_mark_boarded(&fx.admin, &id, &alice),
+        Err(Ok(FlyStellarError::InvalidStatus.into()))
+    );
+}
+
+#[test]
+fn test_buy_ticket_returns_receipt() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let id = fx.create_flight(1, 5, 100);
+    let alice = fx.new_passenger();
+    env.ledger().set_timestamp(1_000);
+    let receipt = fx.client.buy_ticket(
+        &id,
+        &alice,
+        &symbol_short!("veg"),
+        &3,
+        &None,
+        &PassengerType::Adult,
+        &None,
+    );
+
+    let rec = passengers(&env, &fx.client, &id).get(0).unwrap();
+    assert_eq!(receipt.flight_id, id);
+    assert_eq!(receipt.paid, rec.paid);
+    assert_eq!(receipt.seat, rec.seat);
+    assert_eq!(receipt.booked_at, rec.booked_at);
+    assert_eq!(
+        (receipt.paid, receipt.seat, receipt.booked_at),
+        (100, 3, 1_000)
+    );
+}
+
+#[test]
+fn test_revenue_report_written_once_at_departure() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let id = fx.create_flight(1, 5, 100);
+    let flyer = fx.new_passenger();
+    let canceller = fx.new_passenger();
+    let no_show = fx.new_passenger();
+    for (who, seat) in [(&flyer, 1), (&canceller, 2), (&no_show, 3)] {
+        fx.client.buy_ticket(
+            &id,
+            who,
+            &symbol_short!("veg"),
+            &seat,
+            &None,
+            &PassengerType::Adult,
+            &None,
+        );
+    }
+    fx.client.cancel_ticket(&id, &canceller, &None);
+    fx.client.check_in(&id, &flyer);
+    assert_eq!(fx.client.get_report(&id), None);
+
+    env.ledger().set_timestamp(500);
+    fx.client
+        .update_flight_status(&fx.admin, &id, &symbol_short!("takeoff"), &None);
+    let report = fx.client.get_report(&id).unwrap();
+    assert_eq!(
+        report,
+        RevenueReport {
+            gross: 300,
+            refunded: 90,
+            fees_retained: 10,
+            compensation: 0,
+            net_to_airline: 200,
+            generated_at: 500,
+        }
+    );
+    // Everything the contract holds is the airline's share or the fee pot
+    assert_eq!(
+        fx.balance(&fx.client.address),
+        report.net_to_airline + fx.client.get_fee_pot()
+    );
+    assert_eq!(
+        report.gross - report.refunded - report.fees_retained,
+        report.net_to_airline
+    );
+
+    // The no-show sweep leaves the report alone and adds a supplement
+    fx.client.sweep_no_shows(&fx.admin, &id, &10);
+    assert_eq!(fx.client.get_report(&id), Some(report));
+    let supplements = fx.client.get_report_supplements(&id);
+    assert_eq!(supplements.len(), 1);
+    let sweep = supplements.get(0).unwrap();
+    assert_eq!(sweep.airline + sweep.fees, 100);
+    assert_eq!(sweep.airline, fx.client.get_airline_payout(&id));
+}
+
+#[test]
+fn test_status_change_voids_live_holds() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let id = fx.create_flight(1, 5, 100);
+    let other = fx.create_flight(2, 5, 100);
+    fx.client.set_hold_ledgers(&fx.admin, &10);
+    let alice = fx.new_passenger();
+    let bob = fx.new_passenger();
+    let seq = env.ledger().sequence();
+    let carol = fx.new_passenger();
+    fx.client.hold_seat(&id, &alice);
+    fx.client.hold_seat(&id, &carol);
+    fx.client.hold_seat(&other, &bob);
+
+    let voided = |flight: &BytesN<32>, who: &Address| -> Vec<Val> {
+        (
+            symbol_short!("hold"),
+            symbol_short!("voided"),
+            flight.clone(),
+            who.clone(),
+        )
+            .into_val(&env)
+    };
+    let hold_list = |flight: &BytesN<32>| {
+        env.as_contract(&fx.client.address, || {
+            env.storage()
+                .persistent()
+                .has(&DataKey::HoldList(flight.clone()))
+        })
+    };
+
+    // Takeoff does not wait on live holds; it voids them
+    fx.client.release_hold(&id, &carol);
+    fx.client
+        .update_flight_status(&fx.admin, &id, &symbol_short!("takeoff"), &None);
+    let events = env.events().all();
+    assert!(events
+        .iter()
+        .any(|(_, topics, _)| topics == voided(&id, &alice)));
+    assert!(!events
+        .iter()
+        .any(|(_, topics, _)| topics == voided(&id, &carol)));
+    assert!(!hold_list(&id));
+
+    // Cancelling voids the holds still live, but not the ones lapsed on their own
+    env.ledger().set_sequence_number(seq + 5);
+    fx.client.hold_seat(&other, &alice);
+    env.ledger().set_sequence_number(seq + 11);
+    fx.client
+        .update_flight_status(&fx.admin, &other, &symbol_short!("cancelled"), &None);
+    let events = env.events().all();
+    assert!(events
+        .iter()
+        .any(|(_, topics, _)| topics == voided(&other, &alice)));
+    assert!(!events
+        .iter()
+        .any(|(_, topics, _)| topics == voided(&other, &bob)));
+    assert!(!hold_list(&other));
+}
+
+#[test]
+fn test_passenger_stats() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let id = fx.create_flight(1, 5, 100);
+    let other = fx.create_flight(2, 5, 100);
+    let alice = fx.new_passenger();
+    assert_eq!(fx.client.get_passenger_stats(&alice), (0, 0, 0));
+
+    let book = |flight: &BytesN<32>, seat: u32| {
+        fx.client.buy_ticket(
+            flight,
+            &alice,
+            &symbol_short!("veg"),
+            &seat,
+            &None,
+            &PassengerType::Adult,
+            &None,
+        );
+    };
+    book(&id, 1);
+    book(&other, 1);
+    assert_eq!(fx.client.get_passenger_stats(&alice), (200, 0, 2));
+
+    // Each cancellation refunds 90% of the fare
+    fx.client.cancel_ticket(&id, &alice, &None);
+    book(&id, 1);
+    fx.client.cancel_ticket(&id, &alice, &None);
+    assert_eq!(fx.client.get_passenger_stats(&alice), (300, 180, 1));
+    assert_eq!(fx.balance(&alice), 10_000 - 300 + 180);
+
+    // A second seat on the same flight is still one flight, and a departed
+    // flight is no longer active
+    book(&other, 2);
+    assert_eq!(fx.client.get_passenger_stats(&alice), (400, 180, 1));
+    fx.client
+        .update_flight_status(&fx.admin, &other, &symbol_short!("takeoff"), &None);
+    assert_eq!(fx.client.get_passenger_stats(&alice), (400, 180, 0));
+}
+
+#[test]
+fn test_fixture_lifecycle() {
+    let env = Env::default();
+    let f = TestFixture::new(&env, 3);
+    let id = f.create_flight(1, 5, 100);
+    let (flyer, canceller, no_show) = (f.passenger(0), f.passenger(1), f.passenger(2));
+
+    for (seat, who) in [&flyer, &canceller, &no_show].into_iter().enumerate() {
+        let receipt = f.book(&id, who, seat as u32 + 1);
+        assert_eq!(receipt.paid, 100);
+        assert_eq!(f.balance(who), 9_900);
+    }
+    assert_eq!(f.balance(&f.client.address), 300);
+
+    // A 90% refund, the rest stays as a fee
+    f.client.cancel_ticket(&id, &canceller, &None);
+    assert_eq!(f.balance(&canceller), 9_990);
+    assert_eq!(f.client.get_fee_pot(), 10);
+
+    f.client.check_in(&id, &flyer);
+    f.client
+        .update_flight_status(&f.admin, &id, &symbol_short!("takeoff"), &None);
+    assert_eq!(f.client.sweep_no_shows(&f.admin, &id, &10), 0);
+
+    // The no-show fare splits 90/10 between the airline and the fee pot; the
+    // airline's share stays in escrow with the flyer's fare
+    let held = f.client.get_flight_balance(&id).net_held;
+    assert_eq!((held, f.client.get_airline_payout(&id)), (190, 90));
+    assert_eq!(f.client.get_fee_pot(), 20);
+    assert_eq!(f.balance(&f.client.address), held + f.client.get_fee_pot());
+
+    // Settlement pays the airline what is left after the platform commission,
+    // leaving the contract holding only the fee pot
+    let paid = f.client.settle_flight(&f.admin, &id);
+    assert_eq!(f.balance(&f.admin), paid);
+    assert_eq!(f.client.get_fee_pot(), 20 + held - paid);
+    assert_eq!(f.client.get_flight_balance(&id).net_held, 0);
+    assert_eq!(f.balance(&f.client.address), f.client.get_fee_pot());
+}
+
+#[test]
+fn test_fixture_flight_numbers() {
+    let env = Env::default();
+    assert_eq!(flight_number(&env, 7), symbol_short!("FS07"));
+    assert_eq!(flight_number(&env, 99), symbol_short!("FS99"));
+    assert_eq!(flight_number(&env, 204), symbol_short!("FS204"));
+    let f = TestFixture::new(&env, 0);
+    f.create_flight(150, 5, 100);
+    assert_eq!(
+        f.client.get_flight_by_number(&symbol_short!("FS150")),
+        Some(f.client.get_flight(&flight_id(&env, 150)))
+    );
+}
+
+#[test]
+fn test_create_flight_logs_only_with_feature() {
+    use soroban_sdk::testutils::Logs;
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    fx.create_flight(1, 5, 100);
+
+    let expected_logs = if cfg!(feature = "debug-logs") { 13 } else { 0 };
+    assert_eq!(env.logs().all().len(), expected_logs);
+}
+
+#[test]
+fn test_frozen_flight_blocks_booking_and_cancellation() {
+    let env = Env::default();
+    let f = TestFixture::new(&env, 2);
+    let id = f.create_flight(1, 5, 100);
+    let (alice, bob) = (f.passenger(0), f.passenger(1));
+    f.book(&id, &alice, 1);
+
+    f.client.set_flight_frozen(&f.admin, &id, &true);
+    assert!(f.client.is_flight_frozen(&id));
+    assert_eq!(
+        f.client.try_buy_ticket(
+            &id,
+            &bob,
+            &symbol_short!("std"),
+            &2,
+            &None,
+            &PassengerType::Adult,
+            &None
+        ),
+        Err(Ok(FlyStellarError::FlightFrozen.into()))
+    );
+    assert_eq!(
+        f.client.try_cancel_ticket(&id, &alice, &None),
+        Err(Ok(FlyStellarError::FlightFrozen.into()))
+    );
+    assert_eq!(
+        f.client.can_book(&id, &bob).reason,
+        BookReason::BookingClosed
+    );
+    assert_eq!(f.balance(&f.client.address), 100);
+
+    f.client.set_flight_frozen(&f.admin, &id, &false);
+    assert!(!f.client.is_flight_frozen(&id));
+    f.book(&id, &bob, 2);
+    f.client.cancel_ticket(&id, &alice, &None);
+    assert_eq!(f.balance(&alice), 9_990);
+}
+
+#[test]
+fn test_archive_and_restore_flight() {
+    let env = Env::default();
+    let f = TestFixture::new(&env, 3);
+    let flown = f.create_flight(1, 5, 100);
+    let scrapped = f.create_flight(2, 5, 100);
+    let (flyer, no_show, stranded) = (f.passenger(0), f.passenger(1), f.passenger(2));
+    f.book(&flown, &flyer, 1);
+    f.book(&flown, &no_show, 2);
+    f.book(&scrapped, &stranded, 1);
+    f.client.check_in(&flown, &flyer);
+
+    assert_eq!(
+        f.client.try_archive_flight(&f.admin, &flown),
+        Err(Ok(FlyStellarError::InvalidStatus.into()))
+    );
+
+    // Cancelled flights must have refunded everyone first
+    f.client
+        .update_flight_status(&f.admin, &scrapped, &symbol_short!("cancelled"), &None);
+    assert_eq!(
+        f.client.try_archive_flight(&f.admin, &scrapped),
+        Err(Ok(FlyStellarError::RefundsPending.into()))
+    );
+    f.client.refund_batch(&scrapped, &10);
+    f.client.archive_flight(&f.admin, &scrapped);
+
+    // Departed flights must have swept their no-shows
+    f.client
+        .update_flight_status(&f.admin, &flown, &symbol_short!("takeoff"), &None);
+    assert_eq!(
+        f.client.try_archive_flight(&f.admin, &flown),
+        Err(Ok(FlyStellarError::NotSettled.into()))
+    );
+    f.client.sweep_no_shows(&f.admin, &flown, &10);
+
+    // ...and paid out what they still hold
+    assert_eq!(
+        f.client.try_archive_flight(&f.admin, &flown),
+        Err(Ok(FlyStellarError::NotSettled.into()))
+    );
+    f.client.settle_flight(&f.admin, &flown);
+    let report = f.client.get_report(&flown);
+    let balance = f.client.get_flight_balance(&flown);
+    let sold = f.client.total_seats_sold();
+    f.client.archive_flight(&f.admin, &flown);
+
+    let archive = f.client.get_archived(&flown).unwrap();
+    assert_eq!(archive.flight.passenger_count, 2);
+    assert_eq!(archive.passengers.len(), 2);
+    assert_eq!(archive.report.first(), report);
+    assert_eq!(archive.balance, balance);
+
+    // Gone from every live path
+    assert!(f.client.try_get_flight(&flown).is_err());
+    assert_eq!(
+        f.client
+            .get_flights_search(&symbol_short!("DEL"), &symbol_short!("BOM"))
+            .len(),
+        0
+    );
+    assert_eq!(
+        f.client.try_get_flight_balance(&flown),
+        Err(Ok(FlyStellarError::FlightNotFound.into()))
+    );
+    assert_eq!(
+        f.client.can_book(&flown, &flyer).reason,
+        BookReason::FlightNotFound
+    );
+    assert_eq!(f.client.get_flights_pass(&stranded).len(), 0);
+
+    // An archived id stays taken
+    assert_eq!(
+        f.client.try_create_flight(
+            &f.admin,
+            &scrapped,
+            &flight_number(&env, 9),
+            &5,
+            &100,
+            &symbol_short!("DEL"),
+            &symbol_short!("BOM"),
+            &(DEPART + 7 * 86_400),
+        ),
+        Err(Ok(FlyStellarError::FlightAlreadyExists.into()))
+    );
+
+    // Its number was given up at takeoff and stays free
+    f.client.unarchive_flight(&f.admin, &flown);
+    let day = DEPART / 86_400;
+    assert_eq!(
+        f.client.get_flight_on_day(&flight_number(&env, 1), &day),
+        None
+    );
+    assert_eq!(f.client.get_archived(&flown), None);
+    assert_eq!(f.client.get_flight(&flown), archive.flight);
+    assert_eq!(f.client.get_report(&flown), report);
+    assert_eq!(f.client.get_flight_balance(&flown), balance);
+    assert_eq!(f.client.total_seats_sold(), sold);
+    assert_eq!(
+        f.client
+            .get_flights_search(&symbol_short!("DEL"), &symbol_short!("BOM"))
+            .len(),
+        1
+    );
+}
+
+#[test]
+fn test_flight_number_freed_at_takeoff() {
+    let env = Env::default();
+    let f = TestFixture::new(&env, 1);
+    let flown = f.create_flight(1, 5, 100);
+    let number = flight_number(&env, 1);
+    let day = DEPART / 86_400;
+    f.book(&flown, &f.passenger(0), 1);
+    f.client.check_in(&flown, &f.passenger(0));
+    f.client
+        .update_flight_status(&f.admin, &flown, &symbol_short!("takeoff"), &None);
+    assert_eq!(f.client.get_flight_on_day(&number, &day), None);
+
+    // A recurring number can be handed to a new flight the same day
+    let reused = flight_id(&env, 2);
+    f.client.create_flight(
+        &f.admin,
+        &reused,
+        &number,
+        &5,
+        &100,
+        &symbol_short!("DEL"),
+        &symbol_short!("BOM"),
+        &DEPART,
+    );
+    assert_eq!(
+        f.client.get_flight_on_day(&number, &day).unwrap().id,
+        reused
+    );
+
+    // Restoring the departed flight from the archive leaves the number alone
+    f.client.settle_flight(&f.admin, &flown);
+    f.client.archive_flight(&f.admin, &flown);
+    f.client.unarchive_flight(&f.admin, &flown);
+    assert_eq!(
+        f.client.get_flight_on_day(&number, &day).unwrap().id,
+        reused
+    );
+}
 
 #[test]
-fn test() {
+fn test_is_bookable() {
     let env = Env::default();
-    let contract_id = env.register(Contract, ());
-    let client = ContractClient::new(&env, &contract_id);
+    let f = TestFixture::new(&env, 2);
+    let id = f.create_flight(1, 1, 100);
+    assert!(f.client.is_bookable(&id));
+    assert!(!f.client.is_bookable(&flight_id(&env, 9)));
+
+    f.client.set_flight_frozen(&f.admin, &id, &true);
+    assert!(!f.client.is_bookable(&id));
+    f.client.set_flight_frozen(&f.admin, &id, &false);
+    assert!(f.client.is_bookable(&id));
 
-    let words = client.hello(&String::from_str(&env, "Dev"));
+    // Full once the only seat is sold
+    f.book(&id, &f.passenger(0), 1);
+    assert!(!f.client.is_bookable(&id));
+    f.client.cancel_ticket(&id, &f.passenger(0), &None);
+    assert!(f.client.is_bookable(&id));
+
+    // Past departure nothing sells, matching buy_ticket
+    env.ledger().set_timestamp(DEPART);
+    assert!(!f.client.is_bookable(&id));
     assert_eq!(
-        words,
-        vec![
+        f.client.try_buy_ticket(
+            &id,
+            &f.passenger(1),
+            &symbol_short!("std"),
+            &1,
+            &None,
+            &PassengerType::Adult,
+            &None
+        ),
+        Err(Ok(FlyStellarError::InvalidStatus.into()))
+    );
+    env.ledger().set_timestamp(0);
+
+    f.client
+        .update_flight_status(&f.admin, &id, &symbol_short!("cancelled"), &None);
+    assert!(!f.client.is_bookable(&id));
+}
+
+#[test]
+fn test_liabilities_block_recovery() {
+    let env = Env::default();
+    let f = TestFixture::new(&env, 3);
+    let id = f.create_flight(1, 5, 100);
+    let (alice, bob, funder) = (f.passenger(0), f.passenger(1), f.passenger(2));
+    f.client.fund_fee_pot(&funder, &1_000);
+    f.client.set_delay_tiers(
+        &f.admin,
+        &Vec::from_array(
             &env,
-            String::from_str(&env, "Hello"),
-            String::from_str(&env, "Dev"),
-        ]
+            [DelayTier {
+                min_delay_secs: 3_600,
+                flat: 50,
+                bps: 0,
+            }],
+        ),
+    );
+    f.book(&id, &alice, 1);
+    f.book(&id, &bob, 2);
+    assert_eq!(f.client.get_liabilities(), 200);
+
+    // Delay compensation becomes a refund claim owed to each passenger
+    f.client.mark_delayed(&f.admin, &id, &(DEPART + 3_600));
+    assert_eq!(f.client.get_flight_liabilities(&id), 200);
+    assert_eq!(f.client.get_liabilities(), 300);
+    assert_eq!(f.balance(&f.client.address), 1_200);
+
+    // Only the fee pot is free to leave
+    f.client
+        .propose_recovery(&f.admin, &f.token.address, &1_000, &f.admin);
+    env.ledger().set_timestamp(RECOVERY_DELAY_SECS);
+    assert_eq!(
+        f.client.try_execute_recovery(&f.admin),
+        Err(Ok(FlyStellarError::InsufficientEscrow.into()))
+    );
+
+    // Claims paid out still leave the fares owed
+    f.client.claim_refund(&alice);
+    f.client.claim_refund(&bob);
+    assert_eq!(f.client.get_liabilities(), 200);
+    assert_eq!(
+        f.client.try_execute_recovery(&f.admin),
+        Err(Ok(FlyStellarError::InsufficientEscrow.into()))
+    );
+
+    // Departure settles the fares to the airline
+    f.client
+        .update_flight_status(&f.admin, &id, &symbol_short!("takeoff"), &None);
+    assert_eq!(f.client.get_flight_liabilities(&id), 0);
+    assert_eq!(f.client.get_liabilities(), 0);
+    f.client.execute_recovery(&f.admin);
+    assert_eq!(f.balance(&f.client.address), 100);
+}
+
+#[test]
+fn test_refund_rounding_modes() {
+    let env = Env::default();
+    let f = TestFixture::new(&env, 1);
+    let alice = f.passenger(0);
+    assert_eq!(f.client.get_refund_rounding(), Rounding::Floor);
+
+    // 90% of 105 is 94.5 and of 107 is 96.3
+    let cases = [
+        (Rounding::Floor, [94, 96]),
+        (Rounding::Nearest, [95, 96]),
+        (Rounding::Ceil, [95, 97]),
+    ];
+    let mut n = 0;
+    for (mode, refunds) in cases {
+        f.client.set_refund_rounding(&f.admin, &mode);
+        for (fare, refund) in [105, 107].into_iter().zip(refunds) {
+            n += 1;
+            let id = f.create_flight(n, 5, fare);
+            f.book(&id, &alice, 1);
+            let (before, pot) = (f.balance(&alice), f.client.get_fee_pot());
+            f.client.cancel_ticket(&id, &alice, &None);
+            assert_eq!(f.balance(&alice) - before, refund);
+            assert_eq!(refund + f.client.get_fee_pot() - pot, fare);
+        }
+    }
+
+    // Rounding back down is timelocked
+    assert_eq!(
+        f.client.try_set_refund_rounding(&f.admin, &Rounding::Floor),
+        Err(Ok(FlyStellarError::TimelockRequired.into()))
+    );
+}
+
+#[test]
+fn test_booking_sequence_numbers() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 3);
+    let id = fx.create_flight(1, 5, 100);
+    let (a, b, c) = (fx.passenger(0), fx.passenger(1), fx.passenger(2));
+
+    fx.book(&id, &a, 1);
+    fx.book(&id, &b, 2);
+    assert_eq!(fx.client.get_ticket(&id, &b).booking_seq, 2);
+    assert_eq!(
+        fx.client.get_booking_holder(&id, &1),
+        Some(PassengerId::Plain(a.clone()))
+    );
+
+    // A cancelled number is never handed out again
+    fx.client.cancel_ticket(&id, &b, &None);
+    assert_eq!(fx.client.get_booking_holder(&id, &2), None);
+    fx.book(&id, &c, 3);
+    fx.book(&id, &b, 2);
+    assert_eq!(fx.client.get_ticket(&id, &c).booking_seq, 3);
+    assert_eq!(fx.client.get_ticket(&id, &b).booking_seq, 4);
+    assert_eq!(
+        fx.client.get_booking_holder(&id, &4),
+        Some(PassengerId::Plain(b))
+    );
+
+    let err = fx.client.try_get_ticket(&id, &Address::generate(&env));
+    assert_eq!(err, Err(Ok(FlyStellarError::PassengerNotFound.into())));
+}
+
+#[test]
+fn test_pending_refund_reads() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 3);
+    let id = fx.create_flight(1, 5, 100);
+    let (alice, bob) = (fx.passenger(0), fx.passenger(1));
+    fx.client.fund_fee_pot(&fx.passenger(2), &1_000);
+    fx.client.set_delay_tiers(
+        &fx.admin,
+        &Vec::from_array(
+            &env,
+            [DelayTier {
+                min_delay_secs: 3_600,
+                flat: 25,
+                bps: 0,
+            }],
+        ),
+    );
+    fx.book(&id, &alice, 1);
+    fx.book(&id, &bob, 2);
+    assert_eq!(fx.client.list_pending_refunds(&fx.admin, &0, &10).1, 0);
+
+    fx.client.mark_delayed(&fx.admin, &id, &(DEPART + 3_600));
+    assert_eq!(fx.client.get_pending_refund(&alice), 25);
+    assert_eq!(
+        fx.client.list_pending_refunds(&fx.admin, &0, &10),
+        (
+            Vec::from_array(&env, [(alice.clone(), 25), (bob.clone(), 25)]),
+            2
+        )
+    );
+    assert_eq!(
+        fx.client.list_pending_refunds(&fx.admin, &1, &10),
+        (Vec::from_array(&env, [(bob.clone(), 25)]), 2)
+    );
+
+    fx.client.claim_refund(&alice);
+    assert_eq!(fx.client.get_pending_refund(&alice), 0);
+    assert_eq!(
+        fx.client.list_pending_refunds(&fx.admin, &0, &10),
+        (Vec::from_array(&env, [(bob, 25)]), 1)
+    );
+    assert_eq!(
+        fx.client.try_list_pending_refunds(&alice, &0, &10),
+        Err(Ok(FlyStellarError::Unauthorized.into()))
+    );
+}
+
+#[test]
+fn test_duplicate_flight() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 2);
+    let source = fx.create_flight(1, 5, 120);
+    fx.client.set_overbook(&fx.admin, &source, &1_000);
+    fx.client.set_promotional(&fx.admin, &source, &true);
+    fx.client.set_charter_price(&fx.admin, &source, &Some(450));
+    fx.client
+        .set_flight_cancel_cutoff(&fx.admin, &source, &Some(7_200));
+    fx.book(&source, &fx.passenger(0), 1);
+    fx.book(&source, &fx.passenger(1), 2);
+
+    let next_day = DEPART + SECS_PER_DAY;
+    let copy = flight_id(&env, 2);
+    fx.client
+        .duplicate_flight(&fx.admin, &source, &copy, &next_day, &(next_day + 5_400));
+
+    let original = fx.client.get_flight(&source);
+    let flight = fx.client.get_flight(&copy);
+    assert_eq!(flight.flight_number, original.flight_number);
+    assert_eq!((flight.src, flight.dest), (original.src, original.dest));
+    assert_eq!(flight.max_passengers, 5);
+    assert_eq!(flight.fare, original.fare);
+    assert_eq!(flight.overbook_bps, 1_000);
+    assert!(flight.promotional);
+    assert_eq!(flight.charter_price, Some(450));
+    assert_eq!(
+        (flight.depart_at, flight.arrive_at),
+        (next_day, Some(next_day + 5_400))
+    );
+    assert_eq!(fx.client.get_cancel_deadline(&copy), next_day - 7_200);
+
+    // Bookings stay with the source
+    assert_eq!(flight.status, symbol_short!("booking"));
+    assert_eq!(flight.passenger_count, 0);
+    assert_eq!(passengers(&env, &fx.client, &copy).len(), 0);
+    assert_eq!(fx.client.get_seat_map(&copy).len(), 0);
+    assert_eq!(fx.client.get_flight_balance(&copy).collected, 0);
+    assert!(!fx.client.verify_ticket(&copy, &fx.passenger(0)));
+
+    assert_eq!(
+        fx.client.try_duplicate_flight(
+            &fx.admin,
+            &source,
+            &copy,
+            &(next_day + SECS_PER_DAY),
+            &(next_day + SECS_PER_DAY + 1)
+        ),
+        Err(Ok(FlyStellarError::FlightAlreadyExists.into()))
+    );
+    assert_eq!(
+        fx.client.try_duplicate_flight(
+            &fx.passenger(0),
+            &source,
+            &flight_id(&env, 3),
+            &(next_day + SECS_PER_DAY),
+            &(next_day + SECS_PER_DAY + 1)
+        ),
+        Err(Ok(FlyStellarError::Unauthorized.into()))
+    );
+}
+
+#[test]
+fn test_create_flight_derived() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    let number = flight_number(&env, 7);
+    let day = DEPART / SECS_PER_DAY;
+
+    let expected = fx.client.derive_flight_id(&fx.admin, &number, &day);
+    assert_eq!(
+        fx.client.derive_flight_id(&fx.admin, &number, &day),
+        expected
+    );
+    assert_ne!(
+        fx.client.derive_flight_id(&fx.admin, &number, &(day + 1)),
+        expected
+    );
+    assert_ne!(
+        fx.client
+            .derive_flight_id(&Address::generate(&env), &number, &day),
+        expected
+    );
+
+    let create = |depart_at: u64| {
+        fx.client.try_create_flight_derived(
+            &fx.admin,
+            &number,
+            &5,
+            &100,
+            &symbol_short!("DEL"),
+            &symbol_short!("BOM"),
+            &depart_at,
+        )
+    };
+    assert_eq!(create(DEPART), Ok(Ok(expected.clone())));
+    assert_eq!(
+        fx.client.get_flight_on_day(&number, &day).unwrap().id,
+        expected
+    );
+    assert_eq!(fx.client.get_flight_on_day(&number, &(day + 1)), None);
+
+    // Same airline, number and day: same id, so refused
+    assert_eq!(
+        create(DEPART + 60),
+        Err(Ok(FlyStellarError::FlightAlreadyExists.into()))
+    );
+    let next = create(DEPART + SECS_PER_DAY).unwrap().unwrap();
+    assert_eq!(
+        next,
+        fx.client.derive_flight_id(&fx.admin, &number, &(day + 1))
+    );
+}
+
+#[test]
+fn test_flight_fees() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 2);
+    let id = fx.create_flight(1, 5, 125);
+    let other = fx.create_flight(2, 5, 100);
+    let (alice, bob) = (fx.passenger(0), fx.passenger(1));
+    fx.book(&id, &alice, 1);
+    fx.book(&other, &bob, 1);
+    assert_eq!(fx.client.get_flight_fees(&fx.admin, &id), 0);
+
+    let pot = fx.client.get_fee_pot();
+    fx.client.cancel_ticket(&id, &alice, &None);
+    let refund = fx.balance(&alice) - (PASSENGER_FUNDS - 125);
+    assert_eq!(refund, 112);
+    assert_eq!(fx.client.get_flight_fees(&fx.admin, &id), 125 - refund);
+    assert_eq!(fx.client.get_fee_pot() - pot, 125 - refund);
+    assert_eq!(fx.client.get_flight_fees(&fx.admin, &other), 0);
+
+    assert_eq!(
+        fx.client.try_get_flight_fees(&alice, &id),
+        Err(Ok(FlyStellarError::Unauthorized.into()))
+    );
+}
+
+#[test]
+fn test_bulk_reads_by_ids() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 1);
+    let a = fx.create_flight(1, 5, 100);
+    let b = fx.create_flight(2, 5, 100);
+    let alice = fx.passenger(0);
+    fx.book(&b, &alice, 3);
+    let unknown = flight_id(&env, 9);
+
+    let ids = Vec::from_array(&env, [b.clone(), unknown.clone(), a.clone()]);
+    let flights = fx.client.get_flights_by_ids(&ids);
+    assert_eq!(flights.len(), 3);
+    assert_eq!(flights.get(0).unwrap().unwrap().id, b);
+    assert_eq!(flights.get(1).unwrap(), None);
+    assert_eq!(flights.get(2).unwrap().unwrap().id, a);
+
+    let tickets = fx.client.get_tickets_by_ids(&alice, &ids);
+    assert_eq!(tickets.get(0).unwrap().unwrap().seat, 3);
+    assert_eq!(tickets.get(1).unwrap(), None);
+    assert_eq!(tickets.get(2).unwrap(), None);
+
+    // Nothing known is still an answer, not an error
+    let missing = Vec::from_array(&env, [unknown.clone(), flight_id(&env, 8)]);
+    assert_eq!(
+        fx.client.get_flights_by_ids(&missing),
+        Vec::from_array(&env, [None, None])
+    );
+    assert_eq!(
+        fx.client.get_tickets_by_ids(&alice, &missing),
+        Vec::from_array(&env, [None, None])
+    );
+
+    let mut too_many = Vec::new(&env);
+    for _ in 0..=MAX_BULK_IDS {
+        too_many.push_back(a.clone());
+    }
+    assert_eq!(
+        fx.client.try_get_flights_by_ids(&too_many),
+        Err(Ok(FlyStellarError::InvalidInput.into()))
+    );
+    assert_eq!(
+        fx.client.try_get_tickets_by_ids(&alice, &too_many),
+        Err(Ok(FlyStellarError::InvalidInput.into()))
+    );
+    too_many.pop_back();
+    assert_eq!(fx.client.get_flights_by_ids(&too_many).len(), MAX_BULK_IDS);
+}
+
+#[test]
+fn test_passenger_list_cap() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 2);
+    env.cost_estimate().budget().reset_unlimited();
+    let id = fx.create_flight(1, 300, 100);
+
+    // Lap infants take no seat, so a flight of families fills its list before
+    // its seats. `n` records alternate adults, seated from `first_seat`, and
+    // their infants.
+    let families = |n: u32, first_seat: u32| {
+        let mut records = Vec::new(&env);
+        let mut parent = Address::generate(&env);
+        for i in 0..n {
+            let rec = if i % 2 == 0 {
+                parent = Address::generate(&env);
+                imported(&parent, 0, first_seat + i / 2)
+            } else {
+                PassengerRecord {
+                    passenger_type: PassengerType::Infant,
+                    ..imported(&parent, 0, 0)
+                }
+            };
+            records.push_back(rec);
+        }
+        records
+    };
+    assert_eq!(
+        fx.client
+            .try_import_passengers(&fx.admin, &id, &families(MAX_PASSENGER_LIST + 1, 1)),
+        Err(Ok(FlyStellarError::PassengerListFull.into()))
+    );
+    fx.client
+        .import_passengers(&fx.admin, &id, &families(MAX_PASSENGER_LIST - 1, 1));
+    let seated = MAX_PASSENGER_LIST / 2;
+    assert_eq!(fx.client.get_flight(&id).passenger_count, seated);
+
+    fx.book(&id, &fx.passenger(0), seated + 1);
+    assert_eq!(passengers(&env, &fx.client, &id).len(), MAX_PASSENGER_LIST);
+    assert_eq!(
+        fx.client.try_buy_ticket(
+            &id,
+            &fx.passenger(1),
+            &symbol_short!("std"),
+            &(seated + 2),
+            &None,
+            &PassengerType::Adult,
+            &None,
+        ),
+        Err(Ok(FlyStellarError::PassengerListFull.into()))
+    );
+    assert_eq!(
+        fx.client
+            .try_import_passengers(&fx.admin, &id, &families(1, seated + 3)),
+        Err(Ok(FlyStellarError::PassengerListFull.into()))
+    );
+}
+
+#[test]
+fn test_ticket_disputes() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 3);
+    let id = fx.create_flight(1, 5, 100);
+    let other = fx.create_flight(2, 5, 100);
+    let (alice, bob, carol) = (fx.passenger(0), fx.passenger(1), fx.passenger(2));
+    fx.book(&id, &alice, 1);
+    fx.book(&id, &bob, 2);
+    fx.book(&id, &carol, 3);
+
+    assert_eq!(
+        fx.client
+            .try_flag_ticket(&fx.admin, &other, &alice, &symbol_short!("fraud")),
+        Err(Ok(FlyStellarError::PassengerNotFound.into()))
+    );
+    fx.client
+        .flag_ticket(&fx.admin, &id, &alice, &symbol_short!("fraud"));
+    let (_, topics, _) = env.events().all().last().unwrap();
+    assert_eq!(
+        topics,
+        (
+            symbol_short!("ticket"),
+            symbol_short!("flagged"),
+            id.clone(),
+            alice.clone()
+        )
+            .into_val(&env)
+    );
+    let latest = fx.client.get_audit_log(&fx.admin, &0, &1).get(0).unwrap();
+    assert_eq!(latest.action, AuditAction::PassengersEdited);
+
+    // Only alice is frozen, and she still shows up with her flag
+    let disputed = Err(Ok(FlyStellarError::TicketDisputed.into()));
+    assert_eq!(fx.client.try_cancel_ticket(&id, &alice, &None), disputed);
+    assert_eq!(fx.client.try_check_in(&id, &alice), disputed);
+    assert_eq!(fx.client.try_rebook(&id, &other, &alice), disputed);
+    fx.client.check_in(&id, &bob);
+    let (manifest, _) = fx.client.get_manifest(&fx.admin, &id, &0, &10);
+    assert_eq!(
+        manifest.get(0).unwrap().dispute,
+        Some(symbol_short!("fraud"))
+    );
+    assert_eq!(manifest.get(1).unwrap().dispute, None);
+
+    assert_eq!(
+        fx.client
+            .try_resolve_dispute(&fx.admin, &id, &bob, &DisputeOutcome::Clear),
+        Err(Ok(FlyStellarError::InvalidStatus.into()))
+    );
+    fx.client
+        .resolve_dispute(&fx.admin, &id, &alice, &DisputeOutcome::Clear);
+    fx.client.check_in(&id, &alice);
+
+    // Full refund to the payer
+    fx.client
+        .flag_ticket(&fx.admin, &id, &bob, &symbol_short!("dup_pay"));
+    fx.client
+        .resolve_dispute(&fx.admin, &id, &bob, &DisputeOutcome::Refund);
+    assert_eq!(fx.balance(&bob), PASSENGER_FUNDS);
+    assert!(!fx.client.verify_ticket(&id, &bob));
+
+    // Forfeited fare goes to the fee pot
+    let pot = fx.client.get_fee_pot();
+    fx.client
+        .flag_ticket(&fx.admin, &id, &carol, &symbol_short!("chargebk"));
+    fx.client
+        .resolve_dispute(&fx.admin, &id, &carol, &DisputeOutcome::Forfeit);
+    let (_, topics, data) = env.events().all().last().unwrap();
+    assert_eq!(
+        topics,
+        (
+            symbol_short!("ticket"),
+            symbol_short!("resolved"),
+            id.clone(),
+            carol.clone()
+        )
+            .into_val(&env)
+    );
+    let resolved: Map<Symbol, Val> = data.into_val(&env);
+    let amount: i128 = resolved
+        .get(symbol_short!("amount"))
+        .unwrap()
+        .into_val(&env);
+    assert_eq!(amount, 100);
+    assert_eq!(fx.client.get_fee_pot(), pot + 100);
+    assert_eq!(fx.balance(&carol), PASSENGER_FUNDS - 100);
+    assert_eq!(fx.client.get_flight(&id).passenger_count, 1);
+    assert_eq!(fx.client.get_flight_balance(&id).net_held, 100);
+}
+
+#[test]
+fn test_status_histogram() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 0);
+    assert_eq!(fx.client.status_histogram(&fx.admin).len(), 0);
+
+    for n in 1..=6 {
+        fx.create_flight(n, 5, 100);
+    }
+    let set = |n: u8, status: &str| {
+        fx.client.update_flight_status(
+            &fx.admin,
+            &flight_id(&env, n),
+            &Symbol::new(&env, status),
+            &None,
+        );
+    };
+    set(1, "takeoff");
+    set(2, "takeoff");
+    set(3, "cancelled");
+
+    let histogram = fx.client.status_histogram(&fx.admin);
+    let count = |status: &str| {
+        histogram
+            .iter()
+            .find(|(s, _)| *s == Symbol::new(&env, status))
+            .map_or(0, |(_, n)| n)
+    };
+    assert_eq!(histogram.len(), 3);
+    assert_eq!(count("booking"), 3);
+    assert_eq!(count("takeoff"), 2);
+    assert_eq!(count("cancelled"), 1);
+
+    assert_eq!(
+        fx.client.try_status_histogram(&Address::generate(&env)),
+        Err(Ok(FlyStellarError::Unauthorized.into()))
+    );
+}
+
+#[test]
+fn test_settle_flight_with_payout_split() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 4);
+    let id = fx.create_flight(1, 5, 100);
+    apply_change(
+        &env,
+        &fx.client,
+        &fx.admin,
+        ConfigChange::PlatformCommission(800),
+    );
+    assert_eq!(
+        fx.client.try_set_platform_commission(&fx.admin, &900),
+        Err(Ok(FlyStellarError::TimelockRequired.into()))
+    );
+    fx.client.set_platform_commission(&fx.admin, &500);
+    let carriers = [
+        Address::generate(&env),
+        Address::generate(&env),
+        Address::generate(&env),
+    ];
+
+    assert_eq!(
+        fx.client.get_payout_split(&id).shares,
+        Vec::from_array(&env, [(fx.admin.clone(), 10_000)])
+    );
+    let split = |shares: &[(usize, u32)]| {
+        let mut out = Vec::new(&env);
+        for (i, bps) in shares {
+            out.push_back((carriers[*i].clone(), *bps));
+        }
+        PayoutSplit { shares: out }
+    };
+    for bad in [
+        split(&[(0, 5_000), (1, 4_000)]),
+        split(&[(0, 5_000), (0, 5_000)]),
+        split(&[(0, 10_000), (1, 0)]),
+    ] {
+        assert_eq!(
+            fx.client.try_set_payout_split(&fx.admin, &id, &bad),
+            Err(Ok(FlyStellarError::InvalidInput.into()))
+        );
+    }
+    let codeshare = split(&[(0, 3_333), (1, 3_333), (2, 3_334)]);
+    fx.client.set_payout_split(&fx.admin, &id, &codeshare);
+    assert_eq!(fx.client.get_payout_split(&id), codeshare);
+
+    for seat in 1..=3 {
+        fx.book(&id, &fx.passenger(seat - 1), seat);
+        fx.client.check_in(&id, &fx.passenger(seat - 1));
+    }
+    fx.book(&id, &fx.passenger(3), 4);
+    fx.client
+        .update_flight_status(&fx.admin, &id, &symbol_short!("takeoff"), &None);
+    assert_eq!(
+        fx.client.try_set_payout_split(&fx.admin, &id, &codeshare),
+        Err(Ok(FlyStellarError::InvalidStatus.into()))
+    );
+
+    // The no-show has to be swept first
+    assert_eq!(
+        fx.client.try_settle_flight(&fx.admin, &id),
+        Err(Ok(FlyStellarError::NotSettled.into()))
+    );
+    fx.client.sweep_no_shows(&fx.admin, &id, &10);
+    let held = fx.client.get_flight_balance(&id).net_held;
+    assert_eq!(held, 390);
+
+    // 5% commission off 390 is 19; 371 split three ways rounds to 123, 123,
+    // 123 and the 2 left over go to the first carrier
+    let pot = fx.client.get_fee_pot();
+    assert_eq!(fx.client.settle_flight(&fx.admin, &id), 371);
+    assert_eq!(fx.balance(&carriers[0]), 125);
+    assert_eq!(fx.balance(&carriers[1]), 123);
+    assert_eq!(fx.balance(&carriers[2]), 123);
+    assert_eq!(fx.client.get_fee_pot(), pot + 19);
+
+    let balance = fx.client.get_flight_balance(&id);
+    assert_eq!((balance.paid_out, balance.net_held), (371, 0));
+    assert_eq!(fx.balance(&fx.client.address), fx.client.get_fee_pot());
+    assert_eq!(
+        fx.client.try_settle_flight(&fx.admin, &id),
+        Err(Ok(FlyStellarError::InvalidStatus.into()))
+    );
+}
+
+#[test]
+fn test_ticket_events_carry_passenger_topic() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 1);
+    let id = fx.create_flight(1, 5, 100);
+    let alice = fx.passenger(0);
+
+    let find = |action: Symbol| -> Map<Symbol, Val> {
+        let topics: Vec<Val> = (symbol_short!("ticket"), action, alice.clone()).into_val(&env);
+        let (_, _, data) = env
+            .events()
+            .all()
+            .iter()
+            .find(|(_, t, _)| *t == topics)
+            .expect("event not published");
+        data.into_val(&env)
+    };
+
+    fx.book(&id, &alice, 2);
+    let booked = find(symbol_short!("booked"));
+    let flight: BytesN<32> = booked
+        .get(symbol_short!("flight_id"))
+        .unwrap()
+        .into_val(&env);
+    let paid: i128 = booked.get(symbol_short!("paid")).unwrap().into_val(&env);
+    assert_eq!((flight, paid), (id.clone(), 100));
+
+    fx.client.cancel_ticket(&id, &alice, &None);
+    let cancelled = find(Symbol::new(&env, "cancelled"));
+    let flight: BytesN<32> = cancelled
+        .get(symbol_short!("flight_id"))
+        .unwrap()
+        .into_val(&env);
+    let refund: i128 = cancelled
+        .get(symbol_short!("refund"))
+        .unwrap()
+        .into_val(&env);
+    assert_eq!((flight, refund), (id, 90));
+}
+
+#[test]
+fn test_promo_fares() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 4);
+    let id = fx.create_flight(1, 5, 100);
+    let fare = || fx.client.get_fare(&id, &None, &None, &PassengerType::Adult);
+    let promo = Promo {
+        fare: 50,
+        seats_left: 2,
+        expires_at: 5_000,
+    };
+
+    assert_eq!(
+        fx.client.try_set_promo(
+            &fx.admin,
+            &id,
+            &Promo {
+                seats_left: 0,
+                ..promo.clone()
+            }
+        ),
+        Err(Ok(FlyStellarError::InvalidInput.into()))
+    );
+    // A promo has to undercut the normal fare
+    assert_eq!(
+        fx.client.try_set_promo(
+            &fx.admin,
+            &id,
+            &Promo {
+                fare: 100,
+                ..promo.clone()
+            }
+        ),
+        Err(Ok(FlyStellarError::InvalidFare.into()))
+    );
+    fx.client.set_promo(&fx.admin, &id, &promo);
+    assert_eq!(fare(), 50);
+
+    assert_eq!(fx.book(&id, &fx.passenger(0), 1).paid, 50);
+    assert_eq!(fx.book(&id, &fx.passenger(1), 2).paid, 50);
+    assert_eq!(fx.client.get_promo(&id).unwrap().seats_left, 0);
+    assert_eq!(fare(), 100);
+    assert_eq!(fx.book(&id, &fx.passenger(2), 3).paid, 100);
+
+    // A cancelled promo ticket frees its place in the quota, not a full-fare one
+    fx.client.cancel_ticket(&id, &fx.passenger(2), &None);
+    assert_eq!(fare(), 100);
+    fx.client.cancel_ticket(&id, &fx.passenger(0), &None);
+    assert_eq!(fx.balance(&fx.passenger(0)), PASSENGER_FUNDS - 5);
+    assert_eq!(fx.client.get_promo(&id).unwrap().seats_left, 1);
+    assert_eq!(fare(), 50);
+
+    // Moving a promo booking to another flight returns its seat too
+    let other = fx.create_flight(2, 5, 100);
+    fx.client.rebook(&id, &other, &fx.passenger(1));
+    assert_eq!(fx.client.get_promo(&id).unwrap().seats_left, 2);
+
+    env.ledger().set_timestamp(5_000);
+    assert_eq!(fare(), 100);
+    assert_eq!(fx.book(&id, &fx.passenger(3), 4).paid, 100);
+    assert_eq!(fx.client.get_promo(&id).unwrap().seats_left, 2);
+
+    fx.client.clear_promo(&fx.admin, &id);
+    assert_eq!(fx.client.get_promo(&id), None);
+
+    fx.client
+        .update_flight_status(&fx.admin, &id, &symbol_short!("takeoff"), &None);
+    assert_eq!(
+        fx.client.try_set_promo(
+            &fx.admin,
+            &id,
+            &Promo {
+                expires_at: 9_000,
+                ..promo
+            }
+        ),
+        Err(Ok(FlyStellarError::InvalidStatus.into()))
+    );
+}
+
+#[test]
+fn test_release_escrow_before_departure() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 4);
+    let id = fx.create_flight(1, 5, 100);
+    for seat in 1..=4 {
+        fx.book(&id, &fx.passenger(seat - 1), seat);
+    }
+    assert_eq!(
+        fx.client.try_release_escrow(&fx.admin, &id, &0),
+        Err(Ok(FlyStellarError::InvalidInput.into()))
+    );
+
+    assert_eq!(fx.client.release_escrow(&fx.admin, &id, &5_000), 200);
+    assert_eq!(fx.balance(&fx.admin), 200);
+    let balance = fx.client.get_flight_balance(&id);
+    assert_eq!((balance.paid_out, balance.net_held), (200, 200));
+    assert_eq!(fx.client.get_liabilities(), 200);
+
+    // Each cancellation draws 90 refund and 10 fee from what is left
+    fx.client.cancel_ticket(&id, &fx.passenger(0), &None);
+    fx.client.cancel_ticket(&id, &fx.passenger(1), &None);
+    assert_eq!(fx.balance(&fx.passenger(1)), PASSENGER_FUNDS - 10);
+    assert_eq!(fx.client.get_flight_balance(&id).net_held, 0);
+    assert_eq!(
+        fx.client.try_cancel_ticket(&id, &fx.passenger(2), &None),
+        Err(Ok(FlyStellarError::InsufficientEscrow.into()))
+    );
+    assert_eq!(fx.balance(&fx.client.address), fx.client.get_fee_pot());
+
+    // Returning part of the release unblocks the next cancellation
+    assert_eq!(
+        fx.client.try_top_up_escrow(&fx.admin, &id, &201),
+        Err(Ok(FlyStellarError::InvalidInput.into()))
+    );
+    fx.client.top_up_escrow(&fx.admin, &id, &100);
+    assert_eq!(fx.client.get_flight_balance(&id).paid_out, 100);
+    fx.client.cancel_ticket(&id, &fx.passenger(2), &None);
+    assert_eq!(fx.balance(&fx.passenger(2)), PASSENGER_FUNDS - 10);
+}
+
+#[test]
+fn test_release_escrow_keeps_other_flights_covered() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 3);
+    let id = fx.create_flight(1, 5, 100);
+    let other = fx.create_flight(2, 5, 100);
+    fx.book(&id, &fx.passenger(0), 1);
+    fx.book(&id, &fx.passenger(1), 2);
+    fx.book(&other, &fx.passenger(2), 1);
+
+    // The contract comes up 50 short; after releasing 100 the 150 left still
+    // covers the 100 the other flight owes
+    let sink = Address::generate(&env);
+    fx.token.transfer(&fx.client.address, &sink, &50);
+    assert_eq!(fx.client.release_escrow(&fx.admin, &id, &5_000), 100);
+
+    // Releasing the rest would leave 50 against the other flight's 100
+    assert_eq!(
+        fx.client.try_release_escrow(&fx.admin, &id, &10_000),
+        Err(Ok(FlyStellarError::InsufficientEscrow.into()))
+    );
+    assert_eq!(fx.client.get_flight_balance(&id).net_held, 100);
+}
+
+#[test]
+fn test_top_up_escrow_refunds_cancelled_flight() {
+    let env = Env::default();
+    let fx = TestFixture::new(&env, 2);
+    let id = fx.create_flight(1, 5, 100);
+    fx.book(&id, &fx.passenger(0), 1);
+    fx.book(&id, &fx.passenger(1), 2);
+    fx.client.release_escrow(&fx.admin, &id, &5_000);
+    fx.client
+        .update_flight_status(&fx.admin, &id, &symbol_short!("cancelled"), &None);
+
+    // The airline cancelled after drawing down, so it has to pay back first
+    assert_eq!(
+        fx.client.try_refund_batch(&id, &10),
+        Err(Ok(FlyStellarError::InsufficientEscrow.into()))
     );
+    fx.client.top_up_escrow(&fx.admin, &id, &100);
+    assert_eq!(fx.client.refund_batch(&id, &10), 0);
+    assert_eq!(fx.balance(&fx.passenger(0)), PASSENGER_FUNDS);
+    assert_eq!(fx.balance(&fx.passenger(1)), PASSENGER_FUNDS);
+    assert_eq!(fx.client.get_liabilities(), 0);
 }
@@ -0,0 +1,116 @@
+#![cfg(any(test, feature = "testutils"))]
+//! Fixtures for tests of FlyStellar and of code built on top of it. Enable the
+//! `testutils` feature to use them from another crate.
+
+use crate::{FlyStellar, FlyStellarClient, PassengerType, Receipt};
+use soroban_sdk::testutils::Address as _;
+use soroban_sdk::{symbol_short, token, Address, BytesN, Env, Symbol, Vec};
+
+/// Fare token balance every fixture passenger starts with
+pub const PASSENGER_FUNDS: i128 = 10_000;
+
+/// Departure timestamp of flights created through the fixture
+pub const DEPART: u64 = 100_000;
+
+/// Flight id made of the byte `n` repeated
+pub fn flight_id(env: &Env, n: u8) -> BytesN<32> {
+    BytesN::from_array(env, &[n; 32])
+}
+
+/// Flight number `FS<nn>`, or `FS<nnn>` from 100 up
+pub fn flight_number(env: &Env, n: u8) -> Symbol {
+    let mut code = [b'F', b'S', 0, 0, 0];
+    let mut len = 2;
+    if n >= 100 {
+        code[len] = b'0' + n / 100;
+        len += 1;
+    }
+    code[len] = b'0' + n / 10 % 10;
+    code[len + 1] = b'0' + n % 10;
+    Symbol::new(env, core::str::from_utf8(&code[..len + 2]).unwrap())
+}
+
+/// A fresh address holding `PASSENGER_FUNDS` of `token`
+pub fn funded_address(env: &Env, token: &Address) -> Address {
+    let who = Address::generate(env);
+    token::StellarAssetClient::new(env, token).mint(&who, &PASSENGER_FUNDS);
+    who
+}
+
+/// An initialized FlyStellar contract paying out in a fresh Stellar asset,
+/// with every auth mocked
+pub struct TestFixture<'a> {
+    pub env: Env,
+    pub client: FlyStellarClient<'a>,
+    pub admin: Address,
+    pub token: token::Client<'a>,
+    pub passengers: Vec<Address>,
+}
+
+impl TestFixture<'_> {
+    /// Register the contract and its token and fund `passengers` addresses
+    pub fn new(env: &Env, passengers: u32) -> Self {
+        env.mock_all_auths();
+        let admin = Address::generate(env);
+        let asset = env.register_stellar_asset_contract_v2(admin.clone());
+        let client = FlyStellarClient::new(env, &env.register(FlyStellar, ()));
+        client.initialize(&admin, &asset.address());
+
+        let mut funded = Vec::new(env);
+        for _ in 0..passengers {
+            funded.push_back(funded_address(env, &asset.address()));
+        }
+        TestFixture {
+            env: env.clone(),
+            client,
+            admin,
+            token: token::Client::new(env, &asset.address()),
+            passengers: funded,
+        }
+    }
+
+    /// The `i`th funded passenger
+    pub fn passenger(&self, i: u32) -> Address {
+        self.passengers.get(i).unwrap()
+    }
+
+    /// A further passenger holding `PASSENGER_FUNDS`, not kept in `passengers`
+    pub fn new_passenger(&self) -> Address {
+        funded_address(&self.env, &self.token.address)
+    }
+
+    /// Fare token balance of `who`
+    pub fn balance(&self, who: &Address) -> i128 {
+        self.token.balance(who)
+    }
+
+    /// Schedule flight `n` from DEL to BOM at `DEPART`. With the default
+    /// pricing the fare equals `distance`.
+    pub fn create_flight(&self, n: u8, max_passengers: u32, distance: i128) -> BytesN<32> {
+        let id = flight_id(&self.env, n);
+        self.client.create_flight(
+            &self.admin,
+            &id,
+            &flight_number(&self.env, n),
+            &max_passengers,
+            &distance,
+            &symbol_short!("DEL"),
+            &symbol_short!("BOM"),
+            &DEPART,
+        );
+        id
+    }
+
+    /// Book `passenger` as an adult into `seat`, paying in the base token
+    pub fn book(&self, flight_id: &BytesN<32>, passenger: &Address, seat: u32) -> Receipt {
+        self.client.buy_ticket(
+            flight_id,
+            passenger,
+            &symbol_short!("std"),
+            &seat,
+            &None,
+            &PassengerType::Adult,
+            &None,
+        )
+    }
+}
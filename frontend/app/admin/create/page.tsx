@@ -23,6 +23,8 @@ export default function CreateFlightPage() {
   const [distance, setDistance] = useState("");
   const [src, setSrc] = useState("");
   const [dest, setDest] = useState("");
+  const [flightNumber, setFlightNumber] = useState("");
+  const [departAt, setDepartAt] = useState("");
 
   const [showModal, setShowModal] = useState(false);
   const [generatedPublicKey, setGeneratedPublicKey] = useState("");
@@ -59,7 +61,7 @@ export default function CreateFlightPage() {
 
   const handleCreateFlight = async () => {
     if (!publicKey) return toast.error("Connect wallet first!");
-    if (!maxPassengers || !distance || !src || !dest) {
+    if (!maxPassengers || !distance || !src || !dest || !flightNumber || !departAt) {
       return toast.error("Fill all fields!");
     }
 
@@ -77,13 +79,17 @@ export default function CreateFlightPage() {
       const flightIdScVal = StellarSdk.xdr.ScVal.scvBytes(rawBytes);
 
       // Build the operation first
+      const departSecs = Math.floor(new Date(departAt).getTime() / 1000);
       const operation = contract.call(
         "create_flight",
+        StellarSdk.Address.fromString(publicKey).toScVal(),
         flightIdScVal,
+        StellarSdk.nativeToScVal(flightNumber, { type: "symbol" }),
         StellarSdk.nativeToScVal(parseInt(maxPassengers), { type: "u32" }),
         StellarSdk.nativeToScVal(parseInt(distance), { type: "i128" }),
         StellarSdk.nativeToScVal(src, { type: "symbol" }),
-        StellarSdk.nativeToScVal(dest, { type: "symbol" })
+        StellarSdk.nativeToScVal(dest, { type: "symbol" }),
+        StellarSdk.nativeToScVal(departSecs, { type: "u64" })
       );
 
       // Build transaction
@@ -240,6 +246,28 @@ export default function CreateFlightPage() {
               </div>
             </div>
 
+            <div className="grid grid-cols-2 gap-4">
+              <div>
+                <label className="block text-white/80 text-sm mb-2">Flight Number</label>
+                <input
+                  type="text"
+                  placeholder="FS204"
+                  value={flightNumber}
+                  onChange={(e) => setFlightNumber(e.target.value.toUpperCase())}
+                  className="w-full px-4 py-3 rounded-lg bg-white/20 text-white placeholder-white/40 border border-white/30 focus:outline-none"
+                />
+              </div>
+              <div>
+                <label className="block text-white/80 text-sm mb-2">Departure</label>
+                <input
+                  type="datetime-local"
+                  value={departAt}
+                  onChange={(e) => setDepartAt(e.target.value)}
+                  className="w-full px-4 py-3 rounded-lg bg-white/20 text-white placeholder-white/40 border border-white/30 focus:outline-none"
+                />
+              </div>
+            </div>
+
             <button
               onClick={handleCreateFlight}
               disabled={loading}
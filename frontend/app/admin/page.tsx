@@ -66,7 +66,12 @@ export default function AdminDashboard() {
         fee: StellarSdk.BASE_FEE,
         networkPassphrase: NETWORK_PASSPHRASE,
       })
-        .addOperation(contract.call("get_flights_admin"))
+        .addOperation(
+          contract.call(
+            "get_flights_admin",
+            StellarSdk.Address.fromString(walletKey).toScVal()
+          )
+        )
         .setTimeout(180)
         .build();
 
@@ -150,7 +155,13 @@ export default function AdminDashboard() {
         networkPassphrase: NETWORK_PASSPHRASE,
       })
         .addOperation(
-          contract.call("update_flight_status", flightIdScVal, statusScVal)
+          contract.call(
+            "update_flight_status",
+            StellarSdk.Address.fromString(publicKey).toScVal(),
+            flightIdScVal,
+            statusScVal,
+            StellarSdk.xdr.ScVal.scvVoid() // no expected status
+          )
         )
         .setTimeout(180)
         .build();
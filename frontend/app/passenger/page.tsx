@@ -146,7 +146,12 @@ export default function PassengerDashboard() {
         networkPassphrase: NETWORK_PASSPHRASE,
       })
         .addOperation(
-          contract.call("cancel_ticket", flightIdScVal, passengerAddress.toScVal())
+          contract.call(
+            "cancel_ticket",
+            flightIdScVal,
+            passengerAddress.toScVal(),
+            StellarSdk.xdr.ScVal.scvVoid() // refund to the passenger
+          )
         )
         .setTimeout(180)
         .build();
@@ -122,7 +122,7 @@ export default function SearchFlightsPage() {
     }
   };
 
-  const handleBuyTicket = async (flightId: Uint8Array) => {
+  const handleBuyTicket = async (flightId: Uint8Array, maxPassengers: number) => {
     if (!publicKey) return;
 
     try {
@@ -149,6 +149,27 @@ export default function SearchFlightsPage() {
 
       const contract = new StellarSdk.Contract(CONTRACT_ID!);
 
+      // ✅ Take the lowest seat nobody holds yet
+      const seatsTx = new StellarSdk.TransactionBuilder(account, {
+        fee: StellarSdk.BASE_FEE,
+        networkPassphrase: NETWORK_PASSPHRASE,
+      })
+        .addOperation(contract.call("get_taken_seats", flightIdScVal))
+        .setTimeout(180)
+        .build();
+      const seatsSim = await server.simulateTransaction(seatsTx);
+      if (StellarRpc.Api.isSimulationError(seatsSim) || !seatsSim.result?.retval) {
+        toast.error("Failed to load seats");
+        return;
+      }
+      const taken: number[] = StellarSdk.scValToNative(seatsSim.result.retval);
+      let seat = 1;
+      while (taken.includes(seat)) seat++;
+      if (seat > maxPassengers) {
+        toast.error("Flight is full");
+        return;
+      }
+
       let tx = new StellarSdk.TransactionBuilder(account, {
         fee: StellarSdk.BASE_FEE,
         networkPassphrase: NETWORK_PASSPHRASE,
@@ -158,7 +179,11 @@ export default function SearchFlightsPage() {
             "buy_ticket",
             flightIdScVal,
             passengerAddress.toScVal(),
-            details
+            details,
+            StellarSdk.nativeToScVal(seat, { type: "u32" }),
+            StellarSdk.xdr.ScVal.scvVoid(), // no voucher
+            StellarSdk.xdr.ScVal.scvVec([StellarSdk.xdr.ScVal.scvSymbol("Adult")]),
+            StellarSdk.xdr.ScVal.scvVoid() // pay in the base token
           )
         )
         .setTimeout(180)
@@ -297,7 +322,7 @@ export default function SearchFlightsPage() {
                     Flight ID: {Buffer.from(flight.id).toString('hex').slice(0, 16)}...
                   </div>
                   <button
-                    onClick={() => handleBuyTicket(flight.id)}
+                    onClick={() => handleBuyTicket(flight.id, flight.max_passengers)}
                     disabled={
                       loading ||
                       flight.status !== "booking" ||